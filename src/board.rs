@@ -6,13 +6,14 @@ use crate::color::{Color, ALL_COLORS, NUM_COLORS};
 use crate::error::Error;
 use crate::file::File;
 use crate::magic::{
-    between, get_adjacent_files, get_bishop_rays, get_castle_moves, get_file, get_king_moves,
-    get_knight_moves, get_pawn_attacks, get_pawn_dest_double_moves, get_pawn_source_double_moves,
-    get_rank, get_rook_rays,
+    between, get_adjacent_files, get_bishop_moves, get_bishop_rays, get_castle_moves, get_file,
+    get_king_moves, get_knight_moves, get_pawn_attacks, get_pawn_dest_double_moves,
+    get_pawn_source_double_moves, get_rank, get_rook_moves, get_rook_rays, line,
 };
 use crate::movegen::*;
-use crate::piece::{Piece, ALL_PIECES, NUM_PIECES};
-use crate::square::{Square, ALL_SQUARES};
+use crate::piece::{Piece, PieceValues, ALL_PIECES, NUM_PIECES};
+use crate::polyglot;
+use crate::square::{Square, ALL_SQUARES, NUM_SQUARES};
 use crate::zobrist::Zobrist;
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
@@ -21,6 +22,11 @@ use std::mem;
 use std::str::FromStr;
 
 /// A representation of a chess board.  That's why you're here, right?
+///
+/// Two boards are only equal (`==`) if everything about them is, including the halfmove clock,
+/// the fullmove number and whether they are Chess960 boards.  So the same position reached by
+/// different move orders may not be equal.  To compare just the positions, use
+/// `Board::same_position`, or compare `Board::get_hash`.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Board {
     pieces: [BitBoard; NUM_PIECES],
@@ -31,7 +37,34 @@ pub struct Board {
     pinned: BitBoard,
     checkers: BitBoard,
     hash: u64,
+    pawn_hash: u64,
+    material_key: u64,
     en_passant: Option<Square>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    chess960: bool,
+    kingside_rook_files: [File; NUM_COLORS],
+    queenside_rook_files: [File; NUM_COLORS],
+}
+
+/// Everything needed to take back a move made with `Board::play`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Undo {
+    captured: Option<Piece>,
+    castle_rights: [CastleRights; NUM_COLORS],
+    en_passant: Option<Square>,
+    hash: u64,
+    pinned: BitBoard,
+    checkers: BitBoard,
+    halfmove_clock: u16,
+}
+
+impl Undo {
+    /// Which piece (if any) was captured by the move?  For en passant, this is the captured pawn.
+    #[inline]
+    pub fn captured(&self) -> Option<Piece> {
+        self.captured
+    }
 }
 
 /// What is the status of this game?
@@ -40,8 +73,17 @@ pub enum BoardStatus {
     Ongoing,
     Stalemate,
     Checkmate,
+    /// Neither side has enough material left to checkmate.  See
+    /// `Board::is_insufficient_material`.
+    InsufficientMaterial,
 }
 
+/// The game phase of a board with all of the pieces.  See `Board::game_phase`.
+pub const MAX_GAME_PHASE: u32 = 24;
+
+// the dark squares, starting with a1
+const DARK_SQUARES: BitBoard = BitBoard(0xAA55_AA55_AA55_AA55);
+
 /// Construct the initial position.
 impl Default for Board {
     #[inline]
@@ -71,10 +113,31 @@ impl Board {
             pinned: EMPTY,
             checkers: EMPTY,
             hash: 0,
+            pawn_hash: 0,
+            material_key: 0,
             en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            chess960: false,
+            kingside_rook_files: [File::H; NUM_COLORS],
+            queenside_rook_files: [File::A; NUM_COLORS],
         }
     }
 
+    /// Construct one of the 960 Chess960 (Fischer Random) starting positions, numbered using the
+    /// standard (Scharnagl) numbering scheme.  Position 518 is the normal starting position.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    ///
+    /// let board = Board::from_chess960_index(518).expect("Valid index");
+    /// assert!(board.is_chess960());
+    /// assert_eq!(MoveGen::movegen_perft_test(&board, 3), 8902);
+    /// ```
+    pub fn from_chess960_index(index: usize) -> Result<Board, Error> {
+        BoardBuilder::from_chess960_index(index)?.try_into()
+    }
+
     /// Construct a board from a FEN string.
     ///
     /// ```
@@ -115,10 +178,12 @@ impl Board {
         size
     }
 
-    /// Is this game Ongoing, is it Stalemate, or is it Checkmate?
+    /// Is this game Ongoing, is it Stalemate, or is it Checkmate?  Positions where neither side
+    /// can checkmate are reported as InsufficientMaterial.
     ///
     /// ```
     /// use minorhacks_chess::{Board, BoardStatus, Square, ChessMove};
+    /// use std::str::FromStr;
     ///
     /// let mut board = Board::default();
     ///
@@ -153,6 +218,9 @@ impl Board {
     ///                                            None));
     ///
     /// assert_eq!(board.status(), BoardStatus::Checkmate);
+    ///
+    /// let board = Board::from_str("8/8/4k3/8/8/3K4/5B2/8 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(board.status(), BoardStatus::InsufficientMaterial);
     /// ```
     #[inline]
     pub fn status(&self) -> BoardStatus {
@@ -165,7 +233,13 @@ impl Board {
                     BoardStatus::Checkmate
                 }
             }
-            _ => BoardStatus::Ongoing,
+            _ => {
+                if self.is_insufficient_material() {
+                    BoardStatus::InsufficientMaterial
+                } else {
+                    BoardStatus::Ongoing
+                }
+            }
         }
     }
 
@@ -245,6 +319,129 @@ impl Board {
         unsafe { self.pieces.get_unchecked(piece.to_index()) }
     }
 
+    /// How many pieces of a particular type does `color` have?
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, Color, Piece};
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(board.piece_count(Color::White, Piece::Pawn), 8);
+    /// assert_eq!(board.piece_count(Color::Black, Piece::Queen), 1);
+    /// ```
+    #[inline]
+    pub fn piece_count(&self, color: Color, piece: Piece) -> u32 {
+        (self.pieces(piece) & self.color_combined(color)).popcnt()
+    }
+
+    /// Get the material signature of this board: the pieces of white, then "v", then the pieces
+    /// of black, each from the king down to the pawns.
+    ///
+    /// ```
+    /// use minorhacks_chess::Board;
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("8/4k3/4p3/8/8/1R6/3PK3/5r2 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(board.material_signature(), "KRPvKRP");
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(board.material_signature(), "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP");
+    /// ```
+    pub fn material_signature(&self) -> String {
+        let mut result = String::new();
+        for &color in ALL_COLORS.iter() {
+            if color == Color::Black {
+                result.push('v');
+            }
+            for &piece in ALL_PIECES.iter().rev() {
+                for _ in 0..self.piece_count(color, piece) {
+                    result.push_str(&piece.to_string(Color::White));
+                }
+            }
+        }
+        result
+    }
+
+    /// Estimate how far the game has gone from the opening to the endgame, by the pieces that
+    /// are left.  Each knight and bishop counts 1, each rook 2 and each queen 4, so this is
+    /// `MAX_GAME_PHASE` with all of the pieces on the board, and 0 with only kings and pawns.
+    /// Promotions can't make it go over `MAX_GAME_PHASE`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MAX_GAME_PHASE};
+    /// use std::str::FromStr;
+    ///
+    /// assert_eq!(Board::default().game_phase(), MAX_GAME_PHASE);
+    ///
+    /// let board = Board::from_str("8/4k3/4p3/8/8/1R6/3PK3/5r2 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(board.game_phase(), 4);
+    /// ```
+    pub fn game_phase(&self) -> u32 {
+        let phase = self.pieces(Piece::Knight).popcnt()
+            + self.pieces(Piece::Bishop).popcnt()
+            + 2 * self.pieces(Piece::Rook).popcnt()
+            + 4 * self.pieces(Piece::Queen).popcnt();
+        phase.min(MAX_GAME_PHASE)
+    }
+
+    /// Is it impossible for `color` to checkmate their opponent, by any series of legal moves?
+    ///
+    /// This is the case with a lone king.  A lone knight can only mate if the opponent has some
+    /// other piece or pawn to hem their own king in, and bishops all on squares of one color
+    /// need the opponent to have something other than bishops on that color.  With anything
+    /// more, mate is possible.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, Color};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("8/8/4k3/8/8/3K4/5N2/8 w - - 0 1").expect("Valid FEN");
+    /// assert!(board.has_insufficient_material(Color::White));
+    /// assert!(board.has_insufficient_material(Color::Black));
+    ///
+    /// let board = Board::from_str("8/4p3/4k3/8/8/3K4/5N2/8 w - - 0 1").expect("Valid FEN");
+    /// assert!(!board.has_insufficient_material(Color::White));
+    /// assert!(!board.has_insufficient_material(Color::Black));
+    /// ```
+    pub fn has_insufficient_material(&self, color: Color) -> bool {
+        let kings = *self.pieces(Piece::King);
+        let ours = self.color_combined(color) & !kings;
+        let theirs = self.color_combined(!color) & !kings;
+        let knights = ours & self.pieces(Piece::Knight);
+        let bishops = ours & self.pieces(Piece::Bishop);
+
+        if ours == EMPTY {
+            true
+        } else if ours == knights && ours.popcnt() == 1 {
+            theirs == EMPTY
+        } else if ours == bishops && bishops & DARK_SQUARES == EMPTY {
+            theirs & !(self.pieces(Piece::Bishop) & !DARK_SQUARES) == EMPTY
+        } else if ours == bishops && bishops & !DARK_SQUARES == EMPTY {
+            theirs & !(self.pieces(Piece::Bishop) & DARK_SQUARES) == EMPTY
+        } else {
+            false
+        }
+    }
+
+    /// Is this a dead position, where neither side can checkmate by any series of legal moves?
+    /// This is the case when there are only kings, plus either a single knight or bishop, or
+    /// any number of bishops all on squares of the same color.
+    ///
+    /// ```
+    /// use minorhacks_chess::Board;
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("8/2b5/4k3/8/8/3K4/5B2/8 w - - 0 1").expect("Valid FEN");
+    /// assert!(board.is_insufficient_material());
+    ///
+    /// // bishops on squares of different colors can still mate, with help
+    /// let board = Board::from_str("8/3b4/4k3/8/8/3K4/5B2/8 w - - 0 1").expect("Valid FEN");
+    /// assert!(!board.is_insufficient_material());
+    /// assert!(!Board::default().is_insufficient_material());
+    /// ```
+    pub fn is_insufficient_material(&self) -> bool {
+        self.has_insufficient_material(Color::White) && self.has_insufficient_material(Color::Black)
+    }
+
     /// Grab the `CastleRights` for a particular side.
     ///
     /// ```
@@ -333,6 +530,61 @@ impl Board {
         self.side_to_move
     }
 
+    /// Is this board using Chess960 (Fischer Random) castling rules?
+    ///
+    /// In Chess960 mode, castling moves are represented as the king capturing its own rook (so
+    /// `e1h1` instead of `e1g1`), both by `MoveGen` and when making moves.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, MoveGen, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("1r2k1r1/6p1/8/8/8/8/6P1/1R2K1R1 w GBgb - 0 1").expect("Valid FEN");
+    /// assert!(board.is_chess960());
+    ///
+    /// let castles = ChessMove::new(Square::E1, Square::G1, None);
+    /// assert!(board.legal(castles));
+    /// assert_eq!(
+    ///     format!("{}", board.make_move_new(castles)),
+    ///     "1r2k1r1/6p1/8/8/8/8/6P1/1R3RK1 b gb - 1 1"
+    /// );
+    /// ```
+    #[inline]
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Which file does the rook `color` castles kingside with start on?  This is always
+    /// `File::H`, except in Chess960.
+    #[inline]
+    pub fn kingside_rook_file(&self, color: Color) -> File {
+        unsafe { *self.kingside_rook_files.get_unchecked(color.to_index()) }
+    }
+
+    /// Which file does the rook `color` castles queenside with start on?  This is always
+    /// `File::A`, except in Chess960.
+    #[inline]
+    pub fn queenside_rook_file(&self, color: Color) -> File {
+        unsafe { *self.queenside_rook_files.get_unchecked(color.to_index()) }
+    }
+
+    /// Which castle rights does `color` lose when a piece moves from (or is captured on) `sq`?
+    fn square_to_castle_rights(&self, color: Color, sq: Square) -> CastleRights {
+        if !self.chess960 {
+            CastleRights::square_to_castle_rights(color, sq)
+        } else if sq.get_rank() != color.to_my_backrank() {
+            CastleRights::NoRights
+        } else if sq == self.king_square(color) {
+            CastleRights::Both
+        } else if sq.get_file() == self.kingside_rook_file(color) {
+            CastleRights::KingSide
+        } else if sq.get_file() == self.queenside_rook_file(color) {
+            CastleRights::QueenSide
+        } else {
+            CastleRights::NoRights
+        }
+    }
+
     /// Grab my `CastleRights`.
     ///
     /// ```
@@ -434,12 +686,28 @@ impl Board {
     }
 
     /// Add or remove a piece from the bitboards in this struct.
+    ///
+    /// When replacing a piece, the old piece must be removed before the new one is added, so that
+    /// the material key can tell additions from removals.
     fn xor(&mut self, piece: Piece, bb: BitBoard, color: Color) {
         unsafe {
             *self.pieces.get_unchecked_mut(piece.to_index()) ^= bb;
             *self.color_combined.get_unchecked_mut(color.to_index()) ^= bb;
             self.combined ^= bb;
-            self.hash ^= Zobrist::piece(piece, bb.to_square(), color);
+            let square = bb.to_square();
+            self.hash ^= Zobrist::piece(piece, square, color);
+            if piece == Piece::Pawn || piece == Piece::King {
+                self.pawn_hash ^= Zobrist::piece(piece, square, color);
+            }
+
+            // The material key holds one value per piece of each type, so adding the nth piece or
+            // removing it toggles the same value.
+            let count = (self.pieces(piece) & self.color_combined(color)).popcnt() as usize;
+            if self.color_combined(color) & bb == EMPTY {
+                self.material_key ^= Zobrist::material(piece, count, color);
+            } else {
+                self.material_key ^= Zobrist::material(piece, count - 1, color);
+            }
         }
     }
 
@@ -547,6 +815,9 @@ impl Board {
     /// Note that this erases the en-passant information, so applying this function twice does not
     /// always give the same result back.
     ///
+    /// The null move counts as a reversible move, so the halfmove clock is incremented, and the
+    /// fullmove number is incremented if Black passes.
+    ///
     /// ```
     /// use minorhacks_chess::{Board, Color};
     ///
@@ -557,6 +828,7 @@ impl Board {
     /// let new_board = board.null_move().expect("Valid Position");
     ///
     /// assert_eq!(new_board.side_to_move(), Color::Black);
+    /// assert_eq!(new_board.halfmove_clock(), 1);
     /// ```
     #[inline]
     pub fn null_move(&self) -> Option<Board> {
@@ -564,6 +836,10 @@ impl Board {
             None
         } else {
             let mut result = *self;
+            result.halfmove_clock = result.halfmove_clock.saturating_add(1);
+            if result.side_to_move == Color::Black {
+                result.fullmove_number = result.fullmove_number.saturating_add(1);
+            }
             result.side_to_move = !result.side_to_move;
             result.remove_ep();
             result.update_pin_info();
@@ -648,24 +924,46 @@ impl Board {
         for color in ALL_COLORS.iter() {
             // get the castle rights
             let castle_rights = self.castle_rights(*color);
+            if castle_rights == CastleRights::NoRights {
+                continue;
+            }
 
-            // the castle rights object will tell us which rooks shouldn't have moved yet.
-            // verify there are rooks on all those squares
-            if castle_rights.unmoved_rooks(*color)
-                & self.pieces(Piece::Rook)
-                & self.color_combined(*color)
-                != castle_rights.unmoved_rooks(*color)
-            {
-                return false;
+            let my_backrank = color.to_my_backrank();
+            let ksq = self.king_square(*color);
+
+            // verify there is a rook on each square this side can castle with
+            let mut unmoved_rooks = EMPTY;
+            if castle_rights.has_kingside() {
+                unmoved_rooks |= BitBoard::set(my_backrank, self.kingside_rook_file(*color));
+            }
+            if castle_rights.has_queenside() {
+                unmoved_rooks |= BitBoard::set(my_backrank, self.queenside_rook_file(*color));
             }
-            // if we have castle rights, make sure we have a king on the (E, {1,8}) square,
-            // depending on the color
-            if castle_rights != CastleRights::NoRights
-                && self.pieces(Piece::King) & self.color_combined(*color)
-                    != get_file(File::E) & get_rank(color.to_my_backrank())
+            if unmoved_rooks & self.pieces(Piece::Rook) & self.color_combined(*color)
+                != unmoved_rooks
             {
                 return false;
             }
+
+            if self.chess960 {
+                // the king must be on the backrank, between the two castling rooks
+                if ksq.get_rank() != my_backrank
+                    || (castle_rights.has_kingside()
+                        && self.kingside_rook_file(*color) <= ksq.get_file())
+                    || (castle_rights.has_queenside()
+                        && self.queenside_rook_file(*color) >= ksq.get_file())
+                {
+                    return false;
+                }
+            } else {
+                // make sure we have a king on the (E, {1,8}) square, and the rooks are on the
+                // (A, {1,8}) and (H, {1,8}) squares, depending on the color
+                if castle_rights.unmoved_rooks(*color) != unmoved_rooks
+                    || BitBoard::from_square(ksq) != get_file(File::E) & get_rank(my_backrank)
+                {
+                    return false;
+                }
+            }
         }
 
         // we must make sure the kings aren't touching
@@ -677,6 +975,39 @@ impl Board {
         true
     }
 
+    /// Is the position on this board the same as on `other`?  Unlike `==`, this ignores the
+    /// halfmove clock, the fullmove number and whether the boards are Chess960 boards, so it
+    /// is true for the same position reached by different move orders.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    ///
+    /// let nf3 = ChessMove::new(Square::G1, Square::F3, None);
+    /// let ng1 = ChessMove::new(Square::F3, Square::G1, None);
+    /// let nf6 = ChessMove::new(Square::G8, Square::F6, None);
+    /// let ng8 = ChessMove::new(Square::F6, Square::G8, None);
+    ///
+    /// let board = Board::default();
+    /// let after = board
+    ///     .make_move_new(nf3)
+    ///     .make_move_new(nf6)
+    ///     .make_move_new(ng1)
+    ///     .make_move_new(ng8);
+    ///
+    /// assert!(after != board);
+    /// assert!(after.same_position(&board));
+    /// assert!(!after.same_position(&board.make_move_new(nf3)));
+    /// ```
+    pub fn same_position(&self, other: &Board) -> bool {
+        self.pieces == other.pieces
+            && self.color_combined == other.color_combined
+            && self.side_to_move == other.side_to_move
+            && self.castle_rights == other.castle_rights
+            && self.en_passant == other.en_passant
+            && self.kingside_rook_files == other.kingside_rook_files
+            && self.queenside_rook_files == other.queenside_rook_files
+    }
+
     /// Get a hash of the board.
     #[inline]
     pub fn get_hash(&self) -> u64 {
@@ -701,12 +1032,85 @@ impl Board {
             }
     }
 
-    /// Get a pawn hash of the board (a hash that only changes on color change and pawn moves).
+    /// Get the hash of the board used by Polyglot opening books.  Unlike `get_hash()`, this uses
+    /// the standard Polyglot keys, so it can be used to look positions up in any Polyglot book.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(board.polyglot_hash(), 0x463b96181691fc9c);
+    ///
+    /// let board = board.make_move_new(ChessMove::new(Square::E2, Square::E4, None));
+    /// assert_eq!(board.polyglot_hash(), 0x823c9b50fd114196);
+    /// ```
+    pub fn polyglot_hash(&self) -> u64 {
+        let mut result = 0;
+        for color in ALL_COLORS.iter() {
+            for piece in ALL_PIECES.iter() {
+                for square in self.pieces(*piece) & self.color_combined(*color) {
+                    result ^= polyglot::keys::piece(*piece, square, *color);
+                }
+            }
+            result ^= polyglot::keys::castles(self.castle_rights(*color), *color);
+        }
+
+        // Polyglot only includes the en passant file if a pawn is next to the pawn that just
+        // moved, whether or not the capture is actually legal.
+        if let Some(ep) = self.en_passant {
+            if get_adjacent_files(ep.get_file())
+                & get_rank(ep.get_rank())
+                & self.pieces(Piece::Pawn)
+                & self.color_combined(self.side_to_move)
+                != EMPTY
+            {
+                result ^= polyglot::keys::en_passant(ep.get_file());
+            }
+        }
+
+        if self.side_to_move == Color::White {
+            result ^= polyglot::keys::white_to_move();
+        }
+        result
+    }
+
+    /// Get a pawn hash of the board (a hash of the pawns and kings of both sides, which only
+    /// changes on pawn and king moves).  This is useful for caching pawn structure evaluation.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    ///
+    /// let board = Board::default();
+    ///
+    /// let knight_move = board.make_move_new(ChessMove::new(Square::G1, Square::F3, None));
+    /// assert_eq!(board.get_pawn_hash(), knight_move.get_pawn_hash());
     ///
-    /// Currently not implemented...
+    /// let pawn_move = board.make_move_new(ChessMove::new(Square::E2, Square::E4, None));
+    /// assert_ne!(board.get_pawn_hash(), pawn_move.get_pawn_hash());
+    /// ```
     #[inline]
     pub fn get_pawn_hash(&self) -> u64 {
-        0
+        self.pawn_hash
+    }
+
+    /// Get a material key for the board (a hash of how many pieces of each type each side has,
+    /// ignoring where they are).  This only changes on captures and promotions, and is useful for
+    /// caching material-based evaluation, such as endgame recognition.
+    ///
+    /// ```
+    /// use minorhacks_chess::Board;
+    /// use std::str::FromStr;
+    ///
+    /// let krk = Board::from_str("8/8/4k3/8/8/8/8/R3K3 w - - 0 1").expect("Valid FEN");
+    /// let krk2 = Board::from_str("8/1k6/8/8/3R4/8/8/7K b - - 0 1").expect("Valid FEN");
+    /// let kqk = Board::from_str("8/8/4k3/8/8/8/8/Q3K3 w - - 0 1").expect("Valid FEN");
+    ///
+    /// assert_eq!(krk.get_material_key(), krk2.get_material_key());
+    /// assert_ne!(krk.get_material_key(), kqk.get_material_key());
+    /// ```
+    #[inline]
+    pub fn get_material_key(&self) -> u64 {
+        self.material_key
     }
 
     /// What piece is on a particular `Square`?  Is there even one?
@@ -803,6 +1207,42 @@ impl Board {
         self.en_passant
     }
 
+    /// How many halfmoves have been played since the last capture or pawn move?  This is the
+    /// counter used by the 50-move rule.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    ///
+    /// let board = Board::default()
+    ///     .make_move_new(ChessMove::new(Square::G1, Square::F3, None))
+    ///     .make_move_new(ChessMove::new(Square::G8, Square::F6, None));
+    /// assert_eq!(board.halfmove_clock(), 2);
+    ///
+    /// let board = board.make_move_new(ChessMove::new(Square::E2, Square::E4, None));
+    /// assert_eq!(board.halfmove_clock(), 0);
+    /// ```
+    #[inline]
+    pub fn halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Which move of the game is this?  This starts at 1, and is incremented after Black moves.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    ///
+    /// let board = Board::default()
+    ///     .make_move_new(ChessMove::new(Square::E2, Square::E4, None));
+    /// assert_eq!(board.fullmove_number(), 1);
+    ///
+    /// let board = board.make_move_new(ChessMove::new(Square::E7, Square::E5, None));
+    /// assert_eq!(board.fullmove_number(), 2);
+    /// ```
+    #[inline]
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
     /// Set the en_passant square.  Note: This must only be called when self.en_passant is already
     /// None.
     fn set_ep(&mut self, sq: Square) {
@@ -841,6 +1281,71 @@ impl Board {
         MoveGen::new_legal(&self).any(|x| x == m)
     }
 
+    /// Is a pseudo-legal move legal?  This is much faster than `Board::legal`, but `m` must come
+    /// from `MoveGen::new_pseudo_legal` on this board: it only checks that the move doesn't
+    /// leave the king in check.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// // the bishop on d2 is pinned
+    /// let board = Board::from_str("4k3/8/8/8/1q6/8/3B4/4K3 w - - 0 1").expect("Valid FEN");
+    /// assert!(board.is_legal_after_pseudo(ChessMove::new(Square::D2, Square::C3, None)));
+    /// assert!(!board.is_legal_after_pseudo(ChessMove::new(Square::D2, Square::E3, None)));
+    /// ```
+    #[inline]
+    pub fn is_legal_after_pseudo(&self, m: ChessMove) -> bool {
+        let (source, dest) = (m.get_source(), m.get_dest());
+        let source_bb = BitBoard::from_square(source);
+        let dest_bb = BitBoard::from_square(dest);
+        let ksq = self.king_square(self.side_to_move);
+
+        if source == ksq {
+            // castling is only generated when it is legal
+            return self.is_castling(m) || KingType::legal_king_move(self, dest);
+        }
+
+        if self.pieces(Piece::Pawn) & source_bb != EMPTY
+            && self.en_passant.map(|ep| ep.uforward(self.side_to_move)) == Some(dest)
+        {
+            return PawnType::legal_ep_move(self, source, dest);
+        }
+
+        match self.checkers.popcnt() {
+            0 => {}
+            1 => {
+                let checker = self.checkers.to_square();
+                if (between(checker, ksq) | self.checkers) & dest_bb == EMPTY {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+
+        self.pinned & source_bb == EMPTY || line(source, ksq) & dest_bb != EMPTY
+    }
+
+    /// Can the side to move capture the opponent's king?  This only happens after a
+    /// pseudo-legal move that left its own king in check, so under king-capture semantics the
+    /// side to move has won.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/8/8/1q6/8/3B4/4K3 w - - 0 1").expect("Valid FEN");
+    /// assert!(!board.can_capture_king());
+    ///
+    /// let illegal = board.make_move_new(ChessMove::new(Square::D2, Square::E3, None));
+    /// assert!(illegal.can_capture_king());
+    /// ```
+    #[inline]
+    pub fn can_capture_king(&self) -> bool {
+        let king = self.king_square(!self.side_to_move);
+        self.is_square_attacked(king, self.side_to_move)
+    }
+
     /// Make a chess move onto a new board.
     ///
     /// panic!() if king is captured.
@@ -883,38 +1388,223 @@ impl Board {
     #[inline]
     pub fn make_move(&self, m: ChessMove, result: &mut Board) {
         *result = *self;
-        result.remove_ep();
-        result.checkers = EMPTY;
-        result.pinned = EMPTY;
+        result.apply_move(m);
+    }
+
+    /// Make a chess move on this `Board` in place, returning an `Undo` record that can be passed
+    /// to `Board::undo` to take the move back.
+    ///
+    /// This is an alternative to `make_move` for searches that keep a single mutable `Board`.
+    ///
+    /// panic!() if king is captured.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square, Color};
+    ///
+    /// let m = ChessMove::new(Square::D2,
+    ///                        Square::D4,
+    ///                        None);
+    ///
+    /// let mut board = Board::default();
+    /// let undo = board.play(m);
+    /// assert_eq!(board.side_to_move(), Color::Black);
+    ///
+    /// board.undo(m, undo);
+    /// assert_eq!(board, Board::default());
+    /// ```
+    #[inline]
+    pub fn play(&mut self, m: ChessMove) -> Undo {
+        let mut undo = Undo {
+            captured: None,
+            castle_rights: self.castle_rights,
+            en_passant: self.en_passant,
+            hash: self.hash,
+            pinned: self.pinned,
+            checkers: self.checkers,
+            halfmove_clock: self.halfmove_clock,
+        };
+        undo.captured = self.apply_move(m);
+        undo
+    }
+
+    /// Take back a move made with `Board::play`.  `m` and `undo` must be the move that was
+    /// played and the `Undo` record it returned, and moves must be taken back in the reverse order
+    /// they were played.  Anything else will leave the `Board` in an invalid state.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let start = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").expect("Valid FEN");
+    /// let mut board = start;
+    ///
+    /// let castles = ChessMove::new(Square::E1, Square::G1, None);
+    /// let capture = ChessMove::new(Square::A8, Square::A1, None);
+    ///
+    /// let undo_castles = board.play(castles);
+    /// let undo_capture = board.play(capture);
+    /// assert_eq!(board.castle_rights(minorhacks_chess::Color::White),
+    ///            minorhacks_chess::CastleRights::NoRights);
+    ///
+    /// board.undo(capture, undo_capture);
+    /// board.undo(castles, undo_castles);
+    /// assert_eq!(board, start);
+    /// ```
+    #[inline]
+    pub fn undo(&mut self, m: ChessMove, undo: Undo) {
+        let color = !self.side_to_move;
         let source = m.get_source();
         let dest = m.get_dest();
-
         let source_bb = BitBoard::from_square(source);
         let dest_bb = BitBoard::from_square(dest);
-        let move_bb = source_bb ^ dest_bb;
-        let moved = self.piece_on(source).unwrap();
+        let my_backrank = color.to_my_backrank();
+
+        // Was this a castling move?  In Chess960 a piece can only land on the starting square of
+        // a castling rook by castling.  In standard chess, only the king moves two squares.
+        let castle_rights = undo.castle_rights[color.to_index()];
+        let castles_960 = self.chess960
+            && dest.get_rank() == my_backrank
+            && ((castle_rights.has_kingside()
+                && dest.get_file() == self.kingside_rook_file(color))
+                || (castle_rights.has_queenside()
+                    && dest.get_file() == self.queenside_rook_file(color)));
+        let castles = !self.chess960
+            && source.get_rank() == my_backrank
+            && self.pieces(Piece::King) & dest_bb != EMPTY
+            && (source.get_file().to_index() as i8 - dest.get_file().to_index() as i8).abs() == 2;
+
+        if castles_960 || castles {
+            let kingside = dest.get_file() > source.get_file();
+            let (king_file, rook_file) = if kingside {
+                (File::G, File::F)
+            } else {
+                (File::C, File::D)
+            };
+            let rook_start = if castles_960 {
+                dest_bb
+            } else if kingside {
+                BitBoard::set(my_backrank, File::H)
+            } else {
+                BitBoard::set(my_backrank, File::A)
+            };
+            self.xor(Piece::King, BitBoard::set(my_backrank, king_file), color);
+            self.xor(Piece::Rook, BitBoard::set(my_backrank, rook_file), color);
+            self.xor(Piece::King, source_bb, color);
+            self.xor(Piece::Rook, rook_start, color);
+        } else {
+            let on_dest = self.piece_on(dest).unwrap();
+            let moved = if m.get_promotion().is_some() {
+                Piece::Pawn
+            } else {
+                on_dest
+            };
+            self.xor(on_dest, dest_bb, color);
+            self.xor(moved, source_bb, color);
+
+            if let Some(captured) = undo.captured {
+                if moved == Piece::Pawn
+                    && m.get_promotion().is_none()
+                    && Some(dest.ubackward(color)) == undo.en_passant
+                {
+                    self.xor(
+                        captured,
+                        BitBoard::from_square(dest.ubackward(color)),
+                        !color,
+                    );
+                } else {
+                    self.xor(captured, dest_bb, !color);
+                }
+            }
+        }
 
-        result.xor(moved, source_bb, self.side_to_move);
-        result.xor(moved, dest_bb, self.side_to_move);
-        if let Some(captured) = self.piece_on(dest) {
-            result.xor(captured, dest_bb, !self.side_to_move);
+        if color == Color::Black {
+            self.fullmove_number = self.fullmove_number.saturating_sub(1);
         }
+        self.side_to_move = color;
+        self.castle_rights = undo.castle_rights;
+        self.en_passant = undo.en_passant;
+        self.hash = undo.hash;
+        self.pinned = undo.pinned;
+        self.checkers = undo.checkers;
+        self.halfmove_clock = undo.halfmove_clock;
+    }
 
-        #[allow(deprecated)]
-        result.remove_their_castle_rights(CastleRights::square_to_castle_rights(
-            !self.side_to_move,
-            dest,
-        ));
+    /// Make a chess move on this `Board`, and return the captured piece, if any.
+    #[inline]
+    fn apply_move(&mut self, m: ChessMove) -> Option<Piece> {
+        let source = m.get_source();
+        let dest = m.get_dest();
+
+        let source_bb = BitBoard::from_square(source);
+        let dest_bb = BitBoard::from_square(dest);
+        let move_bb = source_bb ^ dest_bb;
+        let side_to_move = self.side_to_move;
+        let en_passant = self.en_passant;
+        let moved = self.piece_on(source).unwrap();
+
+        // In Chess960, castling is represented as the king capturing its own rook
+        let castles_960 = self.chess960
+            && moved == Piece::King
+            && self.color_combined(side_to_move) & dest_bb != EMPTY;
+        let mut captured = if castles_960 {
+            None
+        } else {
+            self.piece_on(dest)
+        };
+
+        let their_lost_rights = self.square_to_castle_rights(!side_to_move, dest);
+        let my_lost_rights = self.square_to_castle_rights(side_to_move, source);
+
+        self.remove_ep();
+        self.checkers = EMPTY;
+        self.pinned = EMPTY;
+
+        if castles_960 {
+            let my_backrank = side_to_move.to_my_backrank();
+            let (king_file, rook_file) = if dest.get_file() > source.get_file() {
+                (File::G, File::F)
+            } else {
+                (File::C, File::D)
+            };
+            self.xor(Piece::King, source_bb, side_to_move);
+            self.xor(Piece::Rook, dest_bb, side_to_move);
+            self.xor(
+                Piece::King,
+                BitBoard::set(my_backrank, king_file),
+                side_to_move,
+            );
+            self.xor(
+                Piece::Rook,
+                BitBoard::set(my_backrank, rook_file),
+                side_to_move,
+            );
+        } else {
+            if let Some(captured) = captured {
+                self.xor(captured, dest_bb, !side_to_move);
+            }
+            self.xor(moved, source_bb, side_to_move);
+            self.xor(moved, dest_bb, side_to_move);
+        }
+
+        if moved == Piece::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+        if side_to_move == Color::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
 
         #[allow(deprecated)]
-        result.remove_my_castle_rights(CastleRights::square_to_castle_rights(
-            self.side_to_move,
-            source,
-        ));
+        self.remove_their_castle_rights(their_lost_rights);
 
-        let opp_king = result.pieces(Piece::King) & result.color_combined(!result.side_to_move);
+        #[allow(deprecated)]
+        self.remove_my_castle_rights(my_lost_rights);
 
-        let castles = moved == Piece::King && (move_bb & get_castle_moves()) == move_bb;
+        let opp_king = self.pieces(Piece::King) & self.color_combined(!self.side_to_move);
+
+        let castles =
+            !self.chess960 && moved == Piece::King && (move_bb & get_castle_moves()) == move_bb;
 
         let ksq = opp_king.to_square();
 
@@ -940,32 +1630,33 @@ impl Board {
         ];
 
         if moved == Piece::Knight {
-            result.checkers ^= get_knight_moves(ksq) & dest_bb;
+            self.checkers ^= get_knight_moves(ksq) & dest_bb;
         } else if moved == Piece::Pawn {
             if let Some(Piece::Knight) = m.get_promotion() {
-                result.xor(Piece::Pawn, dest_bb, self.side_to_move);
-                result.xor(Piece::Knight, dest_bb, self.side_to_move);
-                result.checkers ^= get_knight_moves(ksq) & dest_bb;
+                self.xor(Piece::Pawn, dest_bb, side_to_move);
+                self.xor(Piece::Knight, dest_bb, side_to_move);
+                self.checkers ^= get_knight_moves(ksq) & dest_bb;
             } else if let Some(promotion) = m.get_promotion() {
-                result.xor(Piece::Pawn, dest_bb, self.side_to_move);
-                result.xor(promotion, dest_bb, self.side_to_move);
+                self.xor(Piece::Pawn, dest_bb, side_to_move);
+                self.xor(promotion, dest_bb, side_to_move);
             } else if (source_bb & get_pawn_source_double_moves()) != EMPTY
                 && (dest_bb & get_pawn_dest_double_moves()) != EMPTY
             {
-                result.set_ep(dest);
-                result.checkers ^= get_pawn_attacks(ksq, !result.side_to_move, dest_bb);
-            } else if Some(dest.ubackward(self.side_to_move)) == self.en_passant {
-                result.xor(
+                self.set_ep(dest);
+                self.checkers ^= get_pawn_attacks(ksq, !self.side_to_move, dest_bb);
+            } else if Some(dest.ubackward(side_to_move)) == en_passant {
+                self.xor(
                     Piece::Pawn,
-                    BitBoard::from_square(dest.ubackward(self.side_to_move)),
-                    !self.side_to_move,
+                    BitBoard::from_square(dest.ubackward(side_to_move)),
+                    !side_to_move,
                 );
-                result.checkers ^= get_pawn_attacks(ksq, !result.side_to_move, dest_bb);
+                captured = Some(Piece::Pawn);
+                self.checkers ^= get_pawn_attacks(ksq, !self.side_to_move, dest_bb);
             } else {
-                result.checkers ^= get_pawn_attacks(ksq, !result.side_to_move, dest_bb);
+                self.checkers ^= get_pawn_attacks(ksq, !self.side_to_move, dest_bb);
             }
         } else if castles {
-            let my_backrank = self.side_to_move.to_my_backrank();
+            let my_backrank = side_to_move.to_my_backrank();
             let index = dest.get_file().to_index();
             let start = BitBoard::set(my_backrank, unsafe {
                 *CASTLE_ROOK_START.get_unchecked(index)
@@ -973,26 +1664,26 @@ impl Board {
             let end = BitBoard::set(my_backrank, unsafe {
                 *CASTLE_ROOK_END.get_unchecked(index)
             });
-            result.xor(Piece::Rook, start, self.side_to_move);
-            result.xor(Piece::Rook, end, self.side_to_move);
+            self.xor(Piece::Rook, start, side_to_move);
+            self.xor(Piece::Rook, end, side_to_move);
         }
         // now, lets see if we're in check or pinned
-        let attackers = result.color_combined(result.side_to_move)
-            & ((get_bishop_rays(ksq)
-                & (result.pieces(Piece::Bishop) | result.pieces(Piece::Queen)))
-                | (get_rook_rays(ksq)
-                    & (result.pieces(Piece::Rook) | result.pieces(Piece::Queen))));
+        let attackers = self.color_combined(self.side_to_move)
+            & ((get_bishop_rays(ksq) & (self.pieces(Piece::Bishop) | self.pieces(Piece::Queen)))
+                | (get_rook_rays(ksq) & (self.pieces(Piece::Rook) | self.pieces(Piece::Queen))));
 
         for sq in attackers {
-            let between = between(sq, ksq) & result.combined();
+            let between = between(sq, ksq) & self.combined();
             if between == EMPTY {
-                result.checkers ^= BitBoard::from_square(sq);
+                self.checkers ^= BitBoard::from_square(sq);
             } else if between.popcnt() == 1 {
-                result.pinned ^= between;
+                self.pinned ^= between;
             }
         }
 
-        result.side_to_move = !result.side_to_move;
+        self.side_to_move = !self.side_to_move;
+
+        captured
     }
 
     /// Update the pin information.
@@ -1000,7 +1691,12 @@ impl Board {
         self.pinned = EMPTY;
         self.checkers = EMPTY;
 
-        let ksq = (self.pieces(Piece::King) & self.color_combined(self.side_to_move)).to_square();
+        let king = self.pieces(Piece::King) & self.color_combined(self.side_to_move);
+        if king == EMPTY {
+            // only possible while setting up a board
+            return;
+        }
+        let ksq = king.to_square();
 
         let pinners = self.color_combined(!self.side_to_move)
             & ((get_bishop_rays(ksq) & (self.pieces(Piece::Bishop) | self.pieces(Piece::Queen)))
@@ -1037,6 +1733,481 @@ impl Board {
     pub fn checkers(&self) -> &BitBoard {
         &self.checkers
     }
+
+    /// If `m` castles, get the squares the king lands on, the rook starts from, and the rook
+    /// lands on.
+    fn castling_squares(&self, m: ChessMove) -> Option<(Square, Square, Square)> {
+        let (source, dest) = (m.get_source(), m.get_dest());
+        if self.piece_on(source) != Some(Piece::King) {
+            return None;
+        }
+
+        // In Chess960, castling is represented as the king capturing its own rook
+        let move_bb = BitBoard::from_square(source) ^ BitBoard::from_square(dest);
+        let rook_source = if self.chess960 {
+            if self.color_on(dest) != Some(self.side_to_move) {
+                return None;
+            }
+            dest
+        } else {
+            if move_bb & get_castle_moves() != move_bb {
+                return None;
+            }
+            let rook_file = if dest.get_file() > source.get_file() {
+                File::H
+            } else {
+                File::A
+            };
+            Square::make_square(dest.get_rank(), rook_file)
+        };
+
+        let (king_file, rook_file) = if dest.get_file() > source.get_file() {
+            (File::G, File::F)
+        } else {
+            (File::C, File::D)
+        };
+        Some((
+            Square::make_square(dest.get_rank(), king_file),
+            rook_source,
+            Square::make_square(dest.get_rank(), rook_file),
+        ))
+    }
+
+    /// Does `m` castle?  In Chess960, castling moves are written as the king capturing its own
+    /// rook.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").expect("Valid FEN");
+    /// assert!(board.is_castling(ChessMove::new(Square::E1, Square::G1, None)));
+    /// assert!(!board.is_castling(ChessMove::new(Square::E1, Square::F1, None)));
+    /// ```
+    pub fn is_castling(&self, m: ChessMove) -> bool {
+        self.castling_squares(m).is_some()
+    }
+
+    /// Does `m` capture en passant?
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").expect("Valid FEN");
+    /// assert!(board.is_en_passant(ChessMove::new(Square::E5, Square::D6, None)));
+    /// assert!(!board.is_en_passant(ChessMove::new(Square::E5, Square::E6, None)));
+    /// ```
+    pub fn is_en_passant(&self, m: ChessMove) -> bool {
+        let (source, dest) = (m.get_source(), m.get_dest());
+        self.piece_on(source) == Some(Piece::Pawn)
+            && source.get_file() != dest.get_file()
+            && self.en_passant == Some(dest.ubackward(self.side_to_move))
+    }
+
+    /// Does `m` move a pawn two squares forward?
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    ///
+    /// let board = Board::default();
+    /// assert!(board.is_double_push(ChessMove::new(Square::E2, Square::E4, None)));
+    /// assert!(!board.is_double_push(ChessMove::new(Square::E2, Square::E3, None)));
+    /// ```
+    pub fn is_double_push(&self, m: ChessMove) -> bool {
+        self.piece_on(m.get_source()) == Some(Piece::Pawn)
+            && BitBoard::from_square(m.get_source()) & get_pawn_source_double_moves() != EMPTY
+            && BitBoard::from_square(m.get_dest()) & get_pawn_dest_double_moves() != EMPTY
+    }
+
+    /// Does `m` promote a pawn?
+    #[inline]
+    pub fn is_promotion(&self, m: ChessMove) -> bool {
+        m.get_promotion().is_some()
+    }
+
+    /// What piece does `m` capture, if any?  En passant captures a pawn, and castling captures
+    /// nothing.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Piece, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/8/3pP3/8/8/8/R3K2r w Q d6 0 1").expect("Valid FEN");
+    /// let captured = |m| board.captured_piece(m);
+    /// assert_eq!(captured(ChessMove::new(Square::E5, Square::D6, None)), Some(Piece::Pawn));
+    /// assert_eq!(captured(ChessMove::new(Square::E1, Square::F1, None)), None);
+    /// ```
+    pub fn captured_piece(&self, m: ChessMove) -> Option<Piece> {
+        if self.is_en_passant(m) {
+            Some(Piece::Pawn)
+        } else if self.color_on(m.get_dest()) == Some(!self.side_to_move) {
+            self.piece_on(m.get_dest())
+        } else {
+            None
+        }
+    }
+
+    /// Does `m` capture a piece?
+    #[inline]
+    pub fn is_capture(&self, m: ChessMove) -> bool {
+        self.captured_piece(m).is_some()
+    }
+
+    /// Does `m` put the opponent in check?  This works out direct checks, discovered checks,
+    /// checks along the rank opened by an en passant capture, and checks by the rook when
+    /// castling, without making the move.  `m` must be a pseudo-legal move.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// // the knight uncovers the bishop on a3, and castling brings the rook to f1
+    /// let board = Board::from_str("5k2/8/8/2N5/8/B7/8/4K2R w K - 0 1").expect("Valid FEN");
+    /// assert!(board.gives_check(ChessMove::new(Square::C5, Square::E4, None)));
+    /// assert!(board.gives_check(ChessMove::new(Square::E1, Square::G1, None)));
+    /// assert!(!board.gives_check(ChessMove::new(Square::H1, Square::G1, None)));
+    /// ```
+    pub fn gives_check(&self, m: ChessMove) -> bool {
+        let color = self.side_to_move;
+        let king = self.pieces(Piece::King) & self.color_combined(!color);
+        let (source, dest) = (m.get_source(), m.get_dest());
+        let moved = match self.piece_on(source) {
+            Some(piece) if king != EMPTY => piece,
+            _ => return false,
+        };
+
+        // work out where the pieces are after the move
+        let mut occupied = self.combined ^ BitBoard::from_square(source);
+        let mut unmoved = self.color_combined(color) ^ BitBoard::from_square(source);
+        let (piece, square) = match self.castling_squares(m) {
+            Some((king_dest, rook_source, rook_dest)) => {
+                occupied ^= BitBoard::from_square(rook_source);
+                unmoved ^= BitBoard::from_square(rook_source);
+                occupied |= BitBoard::from_square(king_dest) | BitBoard::from_square(rook_dest);
+                (Piece::Rook, rook_dest)
+            }
+            None => {
+                if self.is_en_passant(m) {
+                    occupied ^= BitBoard::from_square(dest.ubackward(color));
+                }
+                occupied |= BitBoard::from_square(dest);
+                (m.get_promotion().unwrap_or(moved), dest)
+            }
+        };
+
+        // a direct check by the piece that moved, or a check by a slider it uncovered
+        let ksq = king.to_square();
+        let rooks = (self.pieces(Piece::Rook) | self.pieces(Piece::Queen)) & unmoved;
+        let bishops = (self.pieces(Piece::Bishop) | self.pieces(Piece::Queen)) & unmoved;
+        Board::piece_attacks(piece, color, square, occupied) & king != EMPTY
+            || get_rook_moves(ksq, occupied) & rooks != EMPTY
+            || get_bishop_moves(ksq, occupied) & bishops != EMPTY
+    }
+
+    /// Give me the `BitBoard` of the pieces pinning a piece of `color` to `color`'s king.  For
+    /// the side to move, the pinned pieces are `Board::pinned`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{BitBoard, Board, Color, Square, EMPTY};
+    /// use std::str::FromStr;
+    ///
+    /// // the knight on e4 is pinned by the rook on e8
+    /// let board = Board::from_str("4r1k1/8/8/8/4N3/8/8/4K3 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(board.pinners(Color::White), BitBoard::from_square(Square::E8));
+    /// assert_eq!(*board.pinned(), BitBoard::from_square(Square::E4));
+    /// assert_eq!(board.pinners(Color::Black), EMPTY);
+    /// ```
+    pub fn pinners(&self, color: Color) -> BitBoard {
+        let king = self.pieces(Piece::King) & self.color_combined(color);
+        if king == EMPTY {
+            return EMPTY;
+        }
+        let ksq = king.to_square();
+
+        let sliders = self.color_combined(!color)
+            & ((get_bishop_rays(ksq) & (self.pieces(Piece::Bishop) | self.pieces(Piece::Queen)))
+                | (get_rook_rays(ksq) & (self.pieces(Piece::Rook) | self.pieces(Piece::Queen))));
+
+        let mut result = EMPTY;
+        for sq in sliders {
+            let between = between(sq, ksq) & self.combined();
+            if between.popcnt() == 1 && between & self.color_combined(color) != EMPTY {
+                result ^= BitBoard::from_square(sq);
+            }
+        }
+        result
+    }
+
+    /// Get the squares attacked by a `piece` of `color` standing on `square`, as if the pieces
+    /// on the board were only those in `occupied`.
+    fn piece_attacks(piece: Piece, color: Color, square: Square, occupied: BitBoard) -> BitBoard {
+        match piece {
+            Piece::Pawn => get_pawn_attacks(square, color, !EMPTY),
+            Piece::Knight => get_knight_moves(square),
+            Piece::Bishop => get_bishop_moves(square, occupied),
+            Piece::Rook => get_rook_moves(square, occupied),
+            Piece::Queen => get_bishop_moves(square, occupied) | get_rook_moves(square, occupied),
+            Piece::King => get_king_moves(square),
+        }
+    }
+
+    /// Get the pieces of `color` that attack `square`, as if the pieces on the board were only
+    /// those in `occupied`.  Use `*board.combined()` for the position as it is, or remove pieces
+    /// from it to see what attacks `square` once they are gone.
+    ///
+    /// ```
+    /// use minorhacks_chess::{BitBoard, Board, Color, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/8/8/8/8/3R4/3QK3 w - - 0 1").expect("Valid FEN");
+    /// let d2 = BitBoard::from_square(Square::D2);
+    /// let d1 = BitBoard::from_square(Square::D1);
+    ///
+    /// let attackers = board.attackers_to(Square::D5, Color::White, *board.combined());
+    /// assert_eq!(attackers, d2);
+    ///
+    /// // with the rook gone, the queen attacks d5
+    /// let attackers = board.attackers_to(Square::D5, Color::White, board.combined() ^ d2);
+    /// assert_eq!(attackers, d1);
+    /// ```
+    pub fn attackers_to(&self, square: Square, color: Color, occupied: BitBoard) -> BitBoard {
+        self.all_attackers_to(square, occupied) & self.color_combined(color)
+    }
+
+    /// Is `square` attacked by a piece of `color`?
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, Color, Square};
+    ///
+    /// let board = Board::default();
+    /// assert!(board.is_square_attacked(Square::F3, Color::White));
+    /// assert!(!board.is_square_attacked(Square::F4, Color::White));
+    /// assert!(board.is_square_attacked(Square::E7, Color::Black));
+    /// ```
+    pub fn is_square_attacked(&self, square: Square, color: Color) -> bool {
+        self.attackers_to(square, color, self.combined) != EMPTY
+    }
+
+    /// Get all of the squares attacked by the pieces of `color`.  Squares are attacked even when
+    /// they are occupied by a piece of `color`, which is then defended.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, Color, get_rank, Rank};
+    ///
+    /// let board = Board::default();
+    /// let squares = board.attacked_squares(Color::White);
+    /// assert_eq!(squares.popcnt(), 22);
+    /// assert_eq!(squares & get_rank(Rank::Third), get_rank(Rank::Third));
+    /// ```
+    pub fn attacked_squares(&self, color: Color) -> BitBoard {
+        let mut result = EMPTY;
+        for &piece in ALL_PIECES.iter() {
+            for square in self.pieces(piece) & self.color_combined(color) {
+                result |= Board::piece_attacks(piece, color, square, self.combined);
+            }
+        }
+        result
+    }
+
+    /// Get the sliders of `color` that would attack `square` if one piece in between, of
+    /// either color, was gone.  This includes pieces lined up behind an attacker of `square`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{BitBoard, Board, Color, Square, EMPTY};
+    /// use std::str::FromStr;
+    ///
+    /// // the queen is behind the rook, and the bishop is behind the black knight
+    /// let board = Board::from_str("4k3/8/8/8/8/5n2/3R2B1/3QK3 w - - 0 1").expect("Valid FEN");
+    /// let xrays = board.xray_attackers_to(Square::D5, Color::White);
+    /// assert_eq!(xrays, BitBoard::from_square(Square::D1) | BitBoard::from_square(Square::G2));
+    /// assert_eq!(board.xray_attackers_to(Square::D5, Color::Black), EMPTY);
+    /// ```
+    pub fn xray_attackers_to(&self, square: Square, color: Color) -> BitBoard {
+        let rooks = self.pieces(Piece::Rook) | self.pieces(Piece::Queen);
+        let bishops = self.pieces(Piece::Bishop) | self.pieces(Piece::Queen);
+
+        let rook_attacks = get_rook_moves(square, self.combined);
+        let bishop_attacks = get_bishop_moves(square, self.combined);
+        let rook_xrays =
+            get_rook_moves(square, self.combined & !rook_attacks) & !rook_attacks & rooks;
+        let bishop_xrays =
+            get_bishop_moves(square, self.combined & !bishop_attacks) & !bishop_attacks & bishops;
+
+        (rook_xrays | bishop_xrays) & self.color_combined(color)
+    }
+
+    /// Count how many pieces of `color` attack each square, indexed by `Square::to_index`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, Color, Square};
+    ///
+    /// let board = Board::default();
+    /// let control = board.control_map(Color::White);
+    /// assert_eq!(control[Square::D3.to_index()], 2);
+    /// assert_eq!(control[Square::F3.to_index()], 3);
+    /// assert_eq!(control[Square::E1.to_index()], 1);
+    /// assert_eq!(control[Square::E4.to_index()], 0);
+    /// ```
+    pub fn control_map(&self, color: Color) -> [u8; NUM_SQUARES] {
+        let mut result = [0; NUM_SQUARES];
+        for &piece in ALL_PIECES.iter() {
+            for square in self.pieces(piece) & self.color_combined(color) {
+                for attacked in Board::piece_attacks(piece, color, square, self.combined) {
+                    result[attacked.to_index()] += 1;
+                }
+            }
+        }
+        result
+    }
+
+    /// Get the pieces of both sides that attack `square`, as if the pieces on the board were
+    /// only those in `occupied`.
+    fn all_attackers_to(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        let rooks = self.pieces(Piece::Rook) | self.pieces(Piece::Queen);
+        let bishops = self.pieces(Piece::Bishop) | self.pieces(Piece::Queen);
+        let pawns = self.pieces(Piece::Pawn);
+
+        (get_rook_moves(square, occupied) & rooks
+            | get_bishop_moves(square, occupied) & bishops
+            | get_knight_moves(square) & self.pieces(Piece::Knight)
+            | get_king_moves(square) & self.pieces(Piece::King)
+            | get_pawn_attacks(
+                square,
+                Color::White,
+                pawns & self.color_combined(Color::Black),
+            )
+            | get_pawn_attacks(
+                square,
+                Color::Black,
+                pawns & self.color_combined(Color::White),
+            ))
+            & occupied
+    }
+
+    /// Statically evaluate the exchange started by `m` on its destination square, with the
+    /// default `PieceValues`.  See `Board::see_with`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// // the knight on e5 is defended by the pawn on d6
+    /// let board = Board::from_str("4k3/8/3p4/4n3/8/8/4R3/4K3 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(board.see(ChessMove::new(Square::E2, Square::E5, None)), 320 - 500);
+    /// ```
+    pub fn see(&self, m: ChessMove) -> i32 {
+        self.see_with(m, &PieceValues::default())
+    }
+
+    /// Does the exchange started by `m` win at least `threshold`, with the default
+    /// `PieceValues`?  See `Board::see_with`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/3p4/4n3/8/8/4R3/4K3 w - - 0 1").expect("Valid FEN");
+    /// let rxe5 = ChessMove::new(Square::E2, Square::E5, None);
+    /// assert!(!board.see_ge(rxe5, 0));
+    /// assert!(board.see_ge(rxe5, -200));
+    /// ```
+    pub fn see_ge(&self, m: ChessMove, threshold: i32) -> bool {
+        self.see_ge_with(m, threshold, &PieceValues::default())
+    }
+
+    /// Does the exchange started by `m` win at least `threshold`, with these `values`?  See
+    /// `Board::see_with`.
+    pub fn see_ge_with(&self, m: ChessMove, threshold: i32, values: &PieceValues) -> bool {
+        self.see_with(m, values) >= threshold
+    }
+
+    /// Statically evaluate the exchange started by `m` on its destination square: how much
+    /// material does the side making `m` win, or lose if negative, when both sides keep
+    /// capturing on that square with their least valuable piece for as long as it pays?
+    ///
+    /// Pieces behind a slider join in once the slider has captured, en passant captures take the
+    /// pawn that moved, and pawns that capture onto the last rank promote to queens.  The king
+    /// only captures when the square is no longer defended.  Pins are ignored, and quiet moves
+    /// count as capturing nothing, so a negative value means the moved piece can be won.
+    /// Castling, even in Chess960 where the king takes its own rook, is always worth 0.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, PieceValues, Square};
+    /// use std::str::FromStr;
+    ///
+    /// // the queen behind the rook joins in, so the pawn on d5 is won
+    /// let board = Board::from_str("3rk3/8/8/3p4/8/8/3R4/3QK3 w - - 0 1").expect("Valid FEN");
+    /// let rxd5 = ChessMove::new(Square::D2, Square::D5, None);
+    /// assert_eq!(board.see(rxd5), 100);
+    ///
+    ///
+    /// // the rook is lost for the knight
+    /// let board = Board::from_str("4k3/8/3p4/4n3/8/8/4R3/4K3 w - - 0 1").expect("Valid FEN");
+    /// let rxe5 = ChessMove::new(Square::E2, Square::E5, None);
+    /// let values = PieceValues::new(100, 300, 300, 450, 900);
+    /// assert_eq!(board.see_with(rxe5, &values), 300 - 450);
+    /// ```
+    pub fn see_with(&self, m: ChessMove, values: &PieceValues) -> i32 {
+        let (source, dest) = (m.get_source(), m.get_dest());
+        let (mut on_dest, mut side) = match (self.piece_on(source), self.color_on(source)) {
+            (Some(piece), Some(color)) => (piece, color),
+            _ => return 0,
+        };
+        if self.is_castling(m) {
+            return 0;
+        }
+
+        let mut occupied = self.combined ^ BitBoard::from_square(source);
+        let mut gain = [0; 32];
+        gain[0] = match self.piece_on(dest) {
+            Some(piece) => values.get(piece),
+            None if on_dest == Piece::Pawn && source.get_file() != dest.get_file() => {
+                occupied ^= BitBoard::from_square(dest.ubackward(side));
+                values.get(Piece::Pawn)
+            }
+            None => 0,
+        };
+        if let Some(promotion) = m.get_promotion() {
+            gain[0] += values.get(promotion) - values.get(Piece::Pawn);
+            on_dest = promotion;
+        }
+
+        let mut depth = 0;
+        loop {
+            side = !side;
+            let attackers = self.all_attackers_to(dest, occupied);
+            let ours = attackers & self.color_combined(side);
+            let piece = match ALL_PIECES
+                .iter()
+                .find(|&&piece| ours & self.pieces(piece) != EMPTY)
+            {
+                Some(&piece) => piece,
+                None => break,
+            };
+            if piece == Piece::King && attackers & self.color_combined(!side) != EMPTY {
+                break;
+            }
+
+            depth += 1;
+            gain[depth] = values.get(on_dest) - gain[depth - 1];
+            on_dest = piece;
+            if piece == Piece::Pawn && dest.get_rank() == side.to_their_backrank() {
+                gain[depth] += values.get(Piece::Queen) - values.get(Piece::Pawn);
+                on_dest = Piece::Queen;
+            }
+
+            let attacker = (ours & self.pieces(piece)).to_square();
+            occupied ^= BitBoard::from_square(attacker);
+        }
+
+        // each side can stop capturing when that is better for them
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
 }
 
 impl fmt::Display for Board {
@@ -1059,6 +2230,13 @@ impl TryFrom<&BoardBuilder> for Board {
         }
 
         board.side_to_move = fen.get_side_to_move();
+        board.halfmove_clock = fen.get_halfmove_clock();
+        board.fullmove_number = fen.get_fullmove_number();
+        board.chess960 = fen.is_chess960();
+        for color in ALL_COLORS.iter() {
+            board.kingside_rook_files[color.to_index()] = fen.get_kingside_rook_file(*color);
+            board.queenside_rook_files[color.to_index()] = fen.get_queenside_rook_file(*color);
+        }
 
         if let Some(ep) = fen.get_en_passant() {
             board.side_to_move = !board.side_to_move;
@@ -1110,6 +2288,377 @@ fn test_null_move_en_passant() {
     let start =
         Board::from_str("rnbqkbnr/pppp2pp/8/4pP2/8/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 0").unwrap();
     let expected =
-        Board::from_str("rnbqkbnr/pppp2pp/8/4pP2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 0").unwrap();
+        Board::from_str("rnbqkbnr/pppp2pp/8/4pP2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 0").unwrap();
     assert_eq!(start.null_move().unwrap(), expected);
 }
+
+#[test]
+fn test_move_counters() {
+    let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 12 40").unwrap();
+    assert_eq!(board.halfmove_clock(), 12);
+    assert_eq!(board.fullmove_number(), 40);
+
+    // a quiet move increments the halfmove clock, and black moving increments the move number
+    let board = board.make_move_new(ChessMove::new(Square::E8, Square::G8, None));
+    assert_eq!(board.halfmove_clock(), 13);
+    assert_eq!(board.fullmove_number(), 41);
+
+    // a capture resets the halfmove clock
+    let board = board.make_move_new(ChessMove::new(Square::A1, Square::A8, None));
+    assert_eq!(board.halfmove_clock(), 0);
+    assert_eq!(board.fullmove_number(), 41);
+
+    let board = board.null_move().unwrap();
+    assert_eq!(board.halfmove_clock(), 1);
+    assert_eq!(board.fullmove_number(), 42);
+    assert_eq!(format!("{}", board), "R4rk1/8/8/8/8/8/8/4K2R w K - 1 42");
+}
+
+#[test]
+fn test_play_undo_special_moves() {
+    let positions = [
+        // en passant
+        (
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            "e5d6",
+            Some(Piece::Pawn),
+        ),
+        // promotion with capture
+        (
+            "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+            "a7b8q",
+            Some(Piece::Knight),
+        ),
+        // standard castling
+        ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 7", "e8c8", None),
+        // Chess960 castling, where the king does not move
+        ("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1h1", None),
+    ];
+
+    for &(fen, mv, captured) in positions.iter() {
+        let start = Board::from_str(fen).unwrap();
+        let m = ChessMove::from_str(mv).unwrap();
+
+        let mut board = start;
+        let undo = board.play(m);
+        assert_eq!(undo.captured(), captured);
+        assert_eq!(board, start.make_move_new(m));
+        assert_eq!(board.get_hash(), start.make_move_new(m).get_hash());
+
+        board.undo(m, undo);
+        assert_eq!(board, start);
+        assert_eq!(board.get_hash(), start.get_hash());
+    }
+}
+
+#[cfg(test)]
+fn check_incremental_keys(board: &Board, depth: usize) {
+    let rebuilt: Board = BoardBuilder::from(board).try_into().unwrap();
+    assert_eq!(board.get_pawn_hash(), rebuilt.get_pawn_hash());
+    assert_eq!(board.get_material_key(), rebuilt.get_material_key());
+
+    if depth > 0 {
+        for m in MoveGen::new_legal(board) {
+            check_incremental_keys(&board.make_move_new(m), depth - 1);
+        }
+    }
+}
+
+#[test]
+fn test_incremental_pawn_hash_and_material_key() {
+    let positions = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    ];
+    for fen in positions.iter() {
+        check_incremental_keys(&Board::from_str(fen).unwrap(), 3);
+    }
+}
+
+#[test]
+fn test_pawn_hash_and_material_key() {
+    let board = Board::default();
+
+    // pieces moving around don't change the material key
+    let moved = board.make_move_new(ChessMove::new(Square::G1, Square::F3, None));
+    assert_eq!(board.get_material_key(), moved.get_material_key());
+    assert_eq!(board.get_pawn_hash(), moved.get_pawn_hash());
+    assert_ne!(board.get_hash(), moved.get_hash());
+
+    // but king moves change the pawn hash
+    let king = Board::from_str("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let moved = king.make_move_new(ChessMove::new(Square::E1, Square::D1, None));
+    assert_eq!(king.get_material_key(), moved.get_material_key());
+    assert_ne!(king.get_pawn_hash(), moved.get_pawn_hash());
+
+    // the pawn hash is independent of the side to move
+    assert_eq!(
+        king.get_pawn_hash(),
+        king.null_move().unwrap().get_pawn_hash()
+    );
+
+    // the same material with the sides swapped is a different key
+    let white = Board::from_str("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+    let black = Board::from_str("3qk3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_ne!(white.get_material_key(), black.get_material_key());
+
+    // the material key doesn't share its values with the piece-square keys, so a lone king on
+    // a1 can't cancel out the first king of the material key
+    for (index, &square) in ALL_SQUARES.iter().enumerate() {
+        assert_ne!(
+            Zobrist::material(Piece::King, index, Color::White),
+            Zobrist::piece(Piece::King, square, Color::White)
+        );
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_set_piece_and_clear_square_keys() {
+    let board = Board::default();
+    let cleared = board.clear_square(Square::D2).unwrap();
+    let rebuilt =
+        Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    assert_eq!(cleared.get_pawn_hash(), rebuilt.get_pawn_hash());
+    assert_eq!(cleared.get_material_key(), rebuilt.get_material_key());
+
+    let restored = cleared
+        .set_piece(Piece::Pawn, Color::White, Square::D2)
+        .unwrap();
+    assert_eq!(restored.get_pawn_hash(), board.get_pawn_hash());
+    assert_eq!(restored.get_material_key(), board.get_material_key());
+}
+
+#[test]
+fn test_polyglot_hash() {
+    // These are the test positions from the Polyglot book format specification
+    let tests = [
+        ("", 0x463b96181691fc9c),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+    ];
+
+    for &(moves, hash) in tests.iter() {
+        let board = moves.split_whitespace().fold(Board::default(), |board, m| {
+            board.make_move_new(ChessMove::from_str(m).unwrap())
+        });
+        assert_eq!(board.polyglot_hash(), hash);
+    }
+}
+
+#[test]
+fn test_insufficient_material() {
+    let positions = [
+        ("8/8/4k3/8/8/3K4/8/8 w - - 0 1", true, true),
+        ("8/8/4k3/8/8/3K4/8/6n1 w - - 0 1", true, true),
+        ("8/8/4k3/8/8/3K4/5B2/2B5 w - - 0 1", true, true),
+        ("8/2b5/4k3/8/8/3K4/5B2/2B5 w - - 0 1", true, true),
+        // two knights, or knights against anything, can mate with help
+        ("8/8/4k3/8/8/3K4/5N2/6N1 w - - 0 1", false, true),
+        ("8/8/4k3/8/8/3K4/5N2/6n1 w - - 0 1", false, false),
+        ("8/8/4k3/8/8/3K4/5B2/6n1 w - - 0 1", false, false),
+        ("8/3b4/4k3/8/8/3K4/5B2/8 w - - 0 1", false, false),
+        ("8/8/4k3/8/8/3K4/5B2/3B4 w - - 0 1", false, true),
+        ("8/8/4k3/8/8/3K4/5P2/8 w - - 0 1", false, true),
+        ("8/8/4k3/8/8/3K4/5R2/5n2 w - - 0 1", false, false),
+    ];
+    for &(fen, white, black) in positions.iter() {
+        let board = Board::from_str(fen).unwrap();
+        assert_eq!(
+            board.has_insufficient_material(Color::White),
+            white,
+            "{}",
+            fen
+        );
+        assert_eq!(
+            board.has_insufficient_material(Color::Black),
+            black,
+            "{}",
+            fen
+        );
+        assert_eq!(
+            board.status() == BoardStatus::InsufficientMaterial,
+            white && black,
+            "{}",
+            fen
+        );
+    }
+
+    // stalemate comes first
+    let board = Board::from_str("8/8/8/8/8/6B1/5K2/7k b - - 0 1").unwrap();
+    assert!(board.is_insufficient_material());
+    assert_eq!(board.status(), BoardStatus::Stalemate);
+
+    // promoted pieces count, but don't push the game phase past the start
+    let board = Board::from_str("4k3/8/8/8/8/8/8/KQQQ3q w - - 0 1").unwrap();
+    assert_eq!(board.material_signature(), "KQQQvKQ");
+    assert_eq!(board.piece_count(Color::White, Piece::Queen), 3);
+    assert_eq!(board.game_phase(), 16);
+    let board = Board::from_str("7k/5ppp/8/8/8/8/8/KQQQQQQQ w - - 0 1").unwrap();
+    assert_eq!(board.game_phase(), MAX_GAME_PHASE);
+}
+
+#[test]
+fn test_see() {
+    let positions = [
+        ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 100),
+        // en passant, and the recapture
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+        ("4k3/2b5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 0),
+        // promotions, and recapturing onto the last rank
+        ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q", 1120),
+        ("3rk3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", -100),
+        ("1N1rk3/P7/8/8/8/8/8/4K3 b - - 0 1", "d8b8", -980),
+        // the king only captures undefended pieces
+        ("4k3/8/8/8/8/2b5/3p4/3RK3 w - - 0 1", "d1d2", -70),
+        ("3qk3/8/8/8/8/2b5/3p4/3RK3 w - - 0 1", "d1d2", -400),
+        // quiet moves can lose the piece
+        ("4k3/8/8/8/2p5/8/5N2/7K w - - 0 1", "f2d3", -320),
+        ("4k3/8/8/8/2p5/8/5N2/7K w - - 0 1", "f2h3", 0),
+        // x-rays through both sides' sliders
+        ("1b2k3/8/8/4p3/8/2B5/1Q6/4K3 w - - 0 1", "c3e5", 100),
+        ("4k3/8/2b5/3p4/4P3/5Q2/8/4K3 w - - 0 1", "e4d5", 100),
+        ("4k3/8/2b5/3p4/8/8/Q7/4K3 w - - 0 1", "a2d5", -800),
+        // castling doesn't capture the rook, even in Chess960
+        ("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1h1", 0),
+        ("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1b1", 0),
+        ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", 0),
+    ];
+    for &(fen, m, value) in positions.iter() {
+        let board = Board::from_str(fen).unwrap();
+        let m = ChessMove::from_str(m).unwrap();
+        assert!(board.legal(m), "{} {}", fen, m);
+        assert_eq!(board.see(m), value, "{} {}", fen, m);
+        assert!(board.see_ge(m, value));
+        assert!(!board.see_ge(m, value + 1));
+    }
+}
+
+#[test]
+fn test_attack_maps() {
+    let fens = [
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "4r1k1/1b6/8/8/4N3/2n5/3R4/4K2B b - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let board = Board::from_str(fen).unwrap();
+        for &color in ALL_COLORS.iter() {
+            let control = board.control_map(color);
+            let attacked = board.attacked_squares(color);
+            for &square in ALL_SQUARES.iter() {
+                let attackers = board.attackers_to(square, color, *board.combined());
+                assert_eq!(control[square.to_index()] as u32, attackers.popcnt());
+                assert_eq!(board.is_square_attacked(square, color), attackers != EMPTY);
+                assert_eq!(
+                    attacked & BitBoard::from_square(square) != EMPTY,
+                    attackers != EMPTY
+                );
+
+                // removing the attackers only reveals x-rays
+                let xrays = board.xray_attackers_to(square, color);
+                let revealed = board.attackers_to(square, color, board.combined() ^ attackers);
+                assert_eq!(revealed & !attackers & !xrays, EMPTY, "{} {}", fen, square);
+            }
+        }
+    }
+
+    // the side to move's pinned pieces are pinned by its opponent's pinners
+    let board = Board::from_str("4r1k1/1b6/8/8/4N3/2n5/3R4/4K2B b - - 0 1").unwrap();
+    assert_eq!(
+        board.pinners(Color::White),
+        BitBoard::from_square(Square::E8)
+    );
+    assert_eq!(board.pinners(Color::Black), EMPTY);
+    let board = Board::from_str("4r1k1/1b6/8/8/4N3/2n5/3R4/4K2B w - - 0 1").unwrap();
+    assert_eq!(*board.pinned(), BitBoard::from_square(Square::E4));
+}
+
+#[cfg(test)]
+fn check_move_classification(board: &Board, depth: usize) {
+    for m in MoveGen::new_legal(board) {
+        let after = board.make_move_new(m);
+        assert_eq!(
+            board.gives_check(m),
+            *after.checkers() != EMPTY,
+            "{} {}",
+            board,
+            m
+        );
+
+        let captured = ALL_PIECES.iter().find(|&&piece| {
+            (board.pieces(piece) & board.color_combined(!board.side_to_move())).popcnt()
+                > (after.pieces(piece) & after.color_combined(!board.side_to_move())).popcnt()
+        });
+        assert_eq!(
+            board.captured_piece(m),
+            captured.copied(),
+            "{} {}",
+            board,
+            m
+        );
+        assert_eq!(board.is_capture(m), captured.is_some());
+        let ranks =
+            m.get_source().get_rank().to_index() as i8 - m.get_dest().get_rank().to_index() as i8;
+        assert_eq!(
+            board.is_double_push(m),
+            board.piece_on(m.get_source()) == Some(Piece::Pawn) && ranks.abs() == 2
+        );
+        assert!(after.en_passant().is_none() || board.is_double_push(m));
+        if board.is_castling(m) {
+            assert_eq!(board.piece_on(m.get_source()), Some(Piece::King));
+            assert_eq!(
+                after.castle_rights(board.side_to_move()),
+                CastleRights::NoRights
+            );
+        }
+
+        if depth > 1 {
+            check_move_classification(&after, depth - 1);
+        }
+    }
+}
+
+#[test]
+fn test_move_classification() {
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ];
+    for fen in fens.iter() {
+        check_move_classification(&Board::from_str(fen).unwrap(), 3);
+    }
+    // castling rooks that give check, or uncover a check by another rook
+    let fens = [
+        "1r3kr1/8/8/8/8/8/8/1R3KR1 w GBgb - 0 1",
+        "2rk2r1/8/8/8/8/8/8/R1K2R2 w FAgc - 0 1",
+        "4k3/8/8/8/8/8/8/1RKR4 w D - 0 1",
+    ];
+    for fen in fens.iter() {
+        let board = Board::from_str(fen).unwrap();
+        assert!(board.is_chess960());
+        check_move_classification(&board, 3);
+    }
+    let board = Board::from_str(fens[0]).unwrap();
+    assert!(board.gives_check(ChessMove::new(Square::F1, Square::G1, None)));
+    let board = Board::from_str(fens[1]).unwrap();
+    assert!(board.gives_check(ChessMove::new(Square::C1, Square::A1, None)));
+    assert!(!board.gives_check(ChessMove::new(Square::C1, Square::F1, None)));
+
+    // discovered checks along the rank opened by an en passant capture
+    let board = Board::from_str("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1").unwrap();
+    assert!(!board.legal(ChessMove::new(Square::E5, Square::D6, None)));
+    let board = Board::from_str("8/8/8/k2pP2R/8/8/8/7K w - d6 0 1").unwrap();
+    assert!(board.gives_check(ChessMove::new(Square::E5, Square::D6, None)));
+}
@@ -1,6 +1,6 @@
 use crate::board::Board;
 use crate::castle_rights::CastleRights;
-use crate::color::Color;
+use crate::color::{Color, ALL_COLORS};
 use crate::error::Error;
 use crate::file::{File, ALL_FILES};
 use crate::piece::Piece;
@@ -53,6 +53,11 @@ pub struct BoardBuilder {
     side_to_move: Color,
     castle_rights: [CastleRights; 2],
     en_passant: Option<File>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    chess960: bool,
+    kingside_rook_files: [File; 2],
+    queenside_rook_files: [File; 2],
 }
 
 impl BoardBuilder {
@@ -62,6 +67,7 @@ impl BoardBuilder {
     /// * `CastleRights` are empty for both sides
     /// * `en_passant` is not set
     /// * `side_to_move` is Color::White
+    /// * `halfmove_clock` is 0, and `fullmove_number` is 1
     /// ```
     /// use minorhacks_chess::{BoardBuilder, Board, Square, Color, Piece};
     /// use std::convert::TryInto;
@@ -81,11 +87,18 @@ impl BoardBuilder {
             side_to_move: Color::White,
             castle_rights: [CastleRights::NoRights, CastleRights::NoRights],
             en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            chess960: false,
+            kingside_rook_files: [File::H, File::H],
+            queenside_rook_files: [File::A, File::A],
         }
     }
 
     /// Set up a board with everything pre-loaded.
     ///
+    /// The halfmove clock starts at 0 and the fullmove number at 1.
+    ///
     /// ```
     /// use minorhacks_chess::{BoardBuilder, Board, Square, Color, Piece, CastleRights};
     /// use std::convert::TryInto;
@@ -116,6 +129,11 @@ impl BoardBuilder {
             side_to_move,
             castle_rights: [white_castle_rights, black_castle_rights],
             en_passant,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            chess960: false,
+            kingside_rook_files: [File::H, File::H],
+            queenside_rook_files: [File::A, File::A],
         };
 
         for piece in pieces.into_iter() {
@@ -167,6 +185,73 @@ impl BoardBuilder {
             .map(|f| Square::make_square((!self.get_side_to_move()).to_fourth_rank(), f))
     }
 
+    /// Get the number of halfmoves since the last capture or pawn move
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    /// use std::str::FromStr;
+    ///
+    /// let bb = BoardBuilder::from_str("4k3/8/8/8/8/8/8/4K3 w - - 37 80").unwrap();
+    /// assert_eq!(bb.get_halfmove_clock(), 37);
+    /// ```
+    pub fn get_halfmove_clock(&self) -> u16 {
+        self.halfmove_clock
+    }
+
+    /// Get the fullmove number
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    /// use std::str::FromStr;
+    ///
+    /// let bb = BoardBuilder::from_str("4k3/8/8/8/8/8/8/4K3 w - - 37 80").unwrap();
+    /// assert_eq!(bb.get_fullmove_number(), 80);
+    /// ```
+    pub fn get_fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    /// Is this position using Chess960 (Fischer Random) castling rules?
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    /// use std::str::FromStr;
+    ///
+    /// assert!(!BoardBuilder::default().is_chess960());
+    ///
+    /// let bb = BoardBuilder::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    /// assert!(bb.is_chess960());
+    /// ```
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Get the file of the rook that `color` castles with on the kingside
+    ///
+    /// ```
+    /// use minorhacks_chess::{BoardBuilder, Color, File};
+    /// use std::str::FromStr;
+    ///
+    /// let bb = BoardBuilder::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    /// assert_eq!(bb.get_kingside_rook_file(Color::White), File::H);
+    /// ```
+    pub fn get_kingside_rook_file(&self, color: Color) -> File {
+        self.kingside_rook_files[color.to_index()]
+    }
+
+    /// Get the file of the rook that `color` castles with on the queenside
+    ///
+    /// ```
+    /// use minorhacks_chess::{BoardBuilder, Color, File};
+    /// use std::str::FromStr;
+    ///
+    /// let bb = BoardBuilder::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    /// assert_eq!(bb.get_queenside_rook_file(Color::Black), File::F);
+    /// ```
+    pub fn get_queenside_rook_file(&self, color: Color) -> File {
+        self.queenside_rook_files[color.to_index()]
+    }
+
     /// Set the side to move on the position
     ///
     /// This function can be used on self directly or in a builder pattern.
@@ -255,24 +340,274 @@ impl BoardBuilder {
         self.en_passant = file;
         self
     }
-}
 
-impl Index<Square> for BoardBuilder {
-    type Output = Option<(Piece, Color)>;
+    /// Set the number of halfmoves since the last capture or pawn move.
+    ///
+    /// This function can be used directly or in a builder pattern.
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    ///
+    /// let mut bb = BoardBuilder::default();
+    /// bb.halfmove_clock(12);
+    /// assert_eq!(bb.get_halfmove_clock(), 12);
+    /// ```
+    pub fn halfmove_clock(&mut self, halfmove_clock: u16) -> &mut Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
 
-    fn index(&self, index: Square) -> &Self::Output {
-        &self.pieces[index.to_index()]
+    /// Set the fullmove number.
+    ///
+    /// This function can be used directly or in a builder pattern.
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    ///
+    /// let mut bb = BoardBuilder::default();
+    /// bb.fullmove_number(30);
+    /// assert_eq!(format!("{}", bb), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 30");
+    /// ```
+    pub fn fullmove_number(&mut self, fullmove_number: u16) -> &mut Self {
+        self.fullmove_number = fullmove_number;
+        self
     }
-}
 
-impl IndexMut<Square> for BoardBuilder {
-    fn index_mut(&mut self, index: Square) -> &mut Self::Output {
-        &mut self.pieces[index.to_index()]
+    /// Turn Chess960 (Fischer Random) castling rules on or off.
+    ///
+    /// Under Chess960 rules, castling moves are represented by the king capturing its own rook,
+    /// and the castling rooks can start on any file (see `castle_rook_files`).
+    ///
+    /// This function can be used directly or in a builder pattern.
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    ///
+    /// let mut bb = BoardBuilder::default();
+    /// bb.chess960(true);
+    /// assert!(bb.is_chess960());
+    /// ```
+    pub fn chess960(&mut self, chess960: bool) -> &mut Self {
+        self.chess960 = chess960;
+        self
     }
-}
 
-impl fmt::Display for BoardBuilder {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Set the files of the rooks that `color` castles with.  This is only meaningful for
+    /// Chess960 positions; standard chess always uses the H and A files.
+    ///
+    /// Note that this will not update your castle rights.
+    ///
+    /// This function can be used directly or in a builder pattern.
+    ///
+    /// ```
+    /// use minorhacks_chess::{BoardBuilder, Board, CastleRights, Color, File, Piece, Square};
+    /// use std::convert::TryInto;
+    ///
+    /// let board: Board = BoardBuilder::new()
+    ///     .piece(Square::B1, Piece::King, Color::White)
+    ///     .piece(Square::A1, Piece::Rook, Color::White)
+    ///     .piece(Square::G1, Piece::Rook, Color::White)
+    ///     .piece(Square::H8, Piece::King, Color::Black)
+    ///     .castle_rights(Color::White, CastleRights::Both)
+    ///     .castle_rook_files(Color::White, File::G, File::A)
+    ///     .chess960(true)
+    ///     .try_into()
+    ///     .expect("Valid Position");
+    /// assert_eq!(format!("{}", board), "7k/8/8/8/8/8/8/RK4R1 w GA - 0 1");
+    /// ```
+    pub fn castle_rook_files(
+        &mut self,
+        color: Color,
+        kingside: File,
+        queenside: File,
+    ) -> &mut Self {
+        self.kingside_rook_files[color.to_index()] = kingside;
+        self.queenside_rook_files[color.to_index()] = queenside;
+        self
+    }
+
+    /// Set up one of the 960 Chess960 starting positions, numbered using the standard
+    /// (Scharnagl) numbering scheme.  Position 518 is the normal chess starting position.
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    ///
+    /// let bb = BoardBuilder::from_chess960_index(518).expect("Valid index");
+    /// assert_eq!(format!("{}", bb), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+    /// assert!(bb.is_chess960());
+    ///
+    /// let bb = BoardBuilder::from_chess960_index(0).expect("Valid index");
+    /// assert_eq!(format!("{}", bb), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+    ///
+    /// assert!(BoardBuilder::from_chess960_index(960).is_err());
+    /// ```
+    pub fn from_chess960_index(index: usize) -> Result<BoardBuilder, Error> {
+        const KNIGHTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+
+        if index >= 960 {
+            return Err(Error::InvalidChess960Index);
+        }
+
+        let mut backrank: [Option<Piece>; 8] = [None; 8];
+        let mut n = index;
+
+        // the bishops go on opposite colored squares
+        backrank[(n % 4) * 2 + 1] = Some(Piece::Bishop);
+        n /= 4;
+        backrank[(n % 4) * 2] = Some(Piece::Bishop);
+        n /= 4;
+
+        // the queen goes on one of the 6 remaining squares, then the knights on 2 of the 5 left
+        let queen = n % 6;
+        n /= 6;
+        let (first_knight, second_knight) = KNIGHTS[n];
+
+        for (empty, square) in backrank.iter_mut().filter(|p| p.is_none()).enumerate() {
+            if empty == queen {
+                *square = Some(Piece::Queen);
+            }
+        }
+
+        for (empty, square) in backrank.iter_mut().filter(|p| p.is_none()).enumerate() {
+            if empty == first_knight || empty == second_knight {
+                *square = Some(Piece::Knight);
+            }
+        }
+
+        // the king goes between the two rooks on the remaining 3 squares
+        let mut remaining = vec![];
+        for (file, square) in backrank.iter_mut().enumerate() {
+            if square.is_none() {
+                *square = Some(if remaining.len() == 1 {
+                    Piece::King
+                } else {
+                    Piece::Rook
+                });
+                remaining.push(File::from_index(file));
+            }
+        }
+
+        let mut result = BoardBuilder::new();
+        for (file, piece) in backrank.iter().enumerate() {
+            let file = File::from_index(file);
+            let piece = piece.unwrap();
+            result
+                .piece(Square::make_square(Rank::First, file), piece, Color::White)
+                .piece(
+                    Square::make_square(Rank::Second, file),
+                    Piece::Pawn,
+                    Color::White,
+                )
+                .piece(
+                    Square::make_square(Rank::Seventh, file),
+                    Piece::Pawn,
+                    Color::Black,
+                )
+                .piece(Square::make_square(Rank::Eighth, file), piece, Color::Black);
+        }
+
+        result
+            .castle_rights(Color::White, CastleRights::Both)
+            .castle_rights(Color::Black, CastleRights::Both)
+            .castle_rook_files(Color::White, remaining[2], remaining[0])
+            .castle_rook_files(Color::Black, remaining[2], remaining[0])
+            .chess960(true);
+
+        Ok(result)
+    }
+
+    /// Convert the position to a Shredder-FEN string.  This is the same as the normal FEN
+    /// output, except that castle rights are always written as the file of the castling rook
+    /// (`HAha` instead of `KQkq`).
+    ///
+    /// ```
+    /// use minorhacks_chess::BoardBuilder;
+    ///
+    /// let bb = BoardBuilder::default();
+    /// assert_eq!(bb.to_shredder_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+    /// ```
+    pub fn to_shredder_fen(&self) -> String {
+        let mut result = String::new();
+        self.write_fen(&mut result, true).unwrap();
+        result
+    }
+
+    /// Find the file of the rook furthest from the king on a particular side of the back rank.
+    /// This is what `K` and `Q` refer to in an X-FEN string.
+    fn outermost_rook(&self, color: Color, kingside: bool) -> Option<File> {
+        let backrank = color.to_my_backrank();
+        let king = ALL_FILES
+            .iter()
+            .find(|f| self[Square::make_square(backrank, **f)] == Some((Piece::King, color)))?;
+
+        let is_rook =
+            |f: &&File| self[Square::make_square(backrank, **f)] == Some((Piece::Rook, color));
+        if kingside {
+            ALL_FILES
+                .iter()
+                .rev()
+                .take_while(|f| *f > king)
+                .find(is_rook)
+                .copied()
+        } else {
+            ALL_FILES
+                .iter()
+                .take_while(|f| *f < king)
+                .find(is_rook)
+                .copied()
+        }
+    }
+
+    /// Write the castle rights for `color`.  Standard positions use `KQkq`, while Chess960
+    /// positions and Shredder-FEN use the rook file.  `KQkq` would read back as a standard
+    /// position when the king and rooks are on their usual files.
+    fn write_castle_rights(
+        &self,
+        f: &mut dyn fmt::Write,
+        color: Color,
+        shredder: bool,
+    ) -> fmt::Result {
+        let castle_rights = self.castle_rights[color.to_index()];
+        if !shredder && !self.chess960 {
+            return write!(f, "{}", castle_rights.to_string(color));
+        }
+
+        let files = [
+            (
+                castle_rights.has_kingside(),
+                self.get_kingside_rook_file(color),
+            ),
+            (
+                castle_rights.has_queenside(),
+                self.get_queenside_rook_file(color),
+            ),
+        ];
+        for (has_rights, file) in files.iter() {
+            if !has_rights {
+                continue;
+            }
+            let c = (b'a' + file.to_index() as u8) as char;
+            if color == Color::White {
+                write!(f, "{}", c.to_ascii_uppercase())?;
+            } else {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_fen(&self, f: &mut dyn fmt::Write, shredder: bool) -> fmt::Result {
         let mut count = 0;
         for rank in ALL_RANKS.iter().rev() {
             for file in ALL_FILES.iter() {
@@ -308,16 +643,8 @@ impl fmt::Display for BoardBuilder {
             write!(f, "b ")?;
         }
 
-        write!(
-            f,
-            "{}",
-            self.castle_rights[Color::White.to_index()].to_string(Color::White)
-        )?;
-        write!(
-            f,
-            "{}",
-            self.castle_rights[Color::Black.to_index()].to_string(Color::Black)
-        )?;
+        self.write_castle_rights(f, Color::White, shredder)?;
+        self.write_castle_rights(f, Color::Black, shredder)?;
         if self.castle_rights[0] == CastleRights::NoRights
             && self.castle_rights[1] == CastleRights::NoRights
         {
@@ -326,12 +653,33 @@ impl fmt::Display for BoardBuilder {
 
         write!(f, " ")?;
         if let Some(sq) = self.get_en_passant() {
-            write!(f, "{}", sq)?;
+            // FEN holds the square the capturing pawn moves to, behind the pawn that moved
+            write!(f, "{}", sq.ubackward(!self.get_side_to_move()))?;
         } else {
             write!(f, "-")?;
         }
 
-        write!(f, " 0 1")
+        write!(f, " {} {}", self.halfmove_clock, self.fullmove_number)
+    }
+}
+
+impl Index<Square> for BoardBuilder {
+    type Output = Option<(Piece, Color)>;
+
+    fn index(&self, index: Square) -> &Self::Output {
+        &self.pieces[index.to_index()]
+    }
+}
+
+impl IndexMut<Square> for BoardBuilder {
+    fn index_mut(&mut self, index: Square) -> &mut Self::Output {
+        &mut self.pieces[index.to_index()]
+    }
+}
+
+impl fmt::Display for BoardBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_fen(f, false)
     }
 }
 
@@ -448,30 +796,97 @@ impl FromStr for BoardBuilder {
             }
         }
 
-        if castles.contains('K') && castles.contains('Q') {
-            fen.castle_rights[Color::White.to_index()] = CastleRights::Both;
-        } else if castles.contains('K') {
-            fen.castle_rights[Color::White.to_index()] = CastleRights::KingSide;
-        } else if castles.contains('Q') {
-            fen.castle_rights[Color::White.to_index()] = CastleRights::QueenSide;
-        } else {
-            fen.castle_rights[Color::White.to_index()] = CastleRights::NoRights;
-        }
+        // Castle rights can be given as standard FEN (KQkq), X-FEN (KQkq, or the rook file if
+        // the castling rook is not the outermost one), or Shredder-FEN (the rook file).
+        let mut shredder = false;
+        for c in castles.chars() {
+            if c == '-' {
+                continue;
+            }
 
-        if castles.contains('k') && castles.contains('q') {
-            fen.castle_rights[Color::Black.to_index()] = CastleRights::Both;
-        } else if castles.contains('k') {
-            fen.castle_rights[Color::Black.to_index()] = CastleRights::KingSide;
-        } else if castles.contains('q') {
-            fen.castle_rights[Color::Black.to_index()] = CastleRights::QueenSide;
-        } else {
-            fen.castle_rights[Color::Black.to_index()] = CastleRights::NoRights;
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+
+            let (side, file) = match c.to_ascii_uppercase() {
+                'K' => (
+                    CastleRights::KingSide,
+                    fen.outermost_rook(color, true).unwrap_or(File::H),
+                ),
+                'Q' => (
+                    CastleRights::QueenSide,
+                    fen.outermost_rook(color, false).unwrap_or(File::A),
+                ),
+                'A'..='H' => {
+                    shredder = true;
+                    let file = File::from_index((c.to_ascii_uppercase() as usize) - ('A' as usize));
+                    let backrank = color.to_my_backrank();
+                    let king = ALL_FILES
+                        .iter()
+                        .find(|f| {
+                            fen[Square::make_square(backrank, **f)] == Some((Piece::King, color))
+                        })
+                        .ok_or_else(|| Error::InvalidFen {
+                            fen: value.to_string(),
+                        })?;
+                    if file > *king {
+                        (CastleRights::KingSide, file)
+                    } else if file < *king {
+                        (CastleRights::QueenSide, file)
+                    } else {
+                        return Err(Error::InvalidFen {
+                            fen: value.to_string(),
+                        });
+                    }
+                }
+                _ => {
+                    return Err(Error::InvalidFen {
+                        fen: value.to_string(),
+                    })
+                }
+            };
+
+            fen.castle_rights[color.to_index()] = fen.castle_rights[color.to_index()].add(side);
+            if side == CastleRights::KingSide {
+                fen.kingside_rook_files[color.to_index()] = file;
+            } else {
+                fen.queenside_rook_files[color.to_index()] = file;
+            }
         }
 
+        // Anything other than a king on the E file castling with rooks on the A and H files can
+        // only be Chess960.
+        let standard_castling = ALL_COLORS.iter().all(|color| {
+            let castle_rights = fen.castle_rights[color.to_index()];
+            castle_rights == CastleRights::NoRights
+                || (fen[Square::make_square(color.to_my_backrank(), File::E)]
+                    == Some((Piece::King, *color))
+                    && (!castle_rights.has_kingside()
+                        || fen.kingside_rook_files[color.to_index()] == File::H)
+                    && (!castle_rights.has_queenside()
+                        || fen.queenside_rook_files[color.to_index()] == File::A))
+        });
+        fen.chess960 = shredder || !standard_castling;
+
         if let Ok(sq) = Square::from_str(&ep) {
             fen = fen.en_passant(Some(sq.get_file()));
         }
 
+        // The move counters are optional, as many tools omit them.
+        if let Some(halfmove_clock) = tokens.get(4) {
+            fen.halfmove_clock = halfmove_clock.parse().map_err(|_| Error::InvalidFen {
+                fen: value.to_string(),
+            })?;
+        }
+
+        if let Some(fullmove_number) = tokens.get(5) {
+            fen.fullmove_number = fullmove_number.parse().map_err(|_| Error::InvalidFen {
+                fen: value.to_string(),
+            })?;
+        }
+
         Ok(*fen)
     }
 }
@@ -486,13 +901,25 @@ impl From<&Board> for BoardBuilder {
             }
         }
 
-        BoardBuilder::setup(
+        let mut result = BoardBuilder::setup(
             &pieces,
             board.side_to_move(),
             board.castle_rights(Color::White),
             board.castle_rights(Color::Black),
             board.en_passant().map(|sq| sq.get_file()),
-        )
+        );
+        result
+            .halfmove_clock(board.halfmove_clock())
+            .fullmove_number(board.fullmove_number())
+            .chess960(board.is_chess960());
+        for color in ALL_COLORS.iter() {
+            result.castle_rook_files(
+                *color,
+                board.kingside_rook_file(*color),
+                board.queenside_rook_file(*color),
+            );
+        }
+        result
     }
 }
 
@@ -505,7 +932,7 @@ impl From<Board> for BoardBuilder {
 #[cfg(test)]
 use crate::bitboard::BitBoard;
 #[cfg(test)]
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 #[test]
 fn check_initial_position() {
@@ -551,3 +978,112 @@ fn test_in_check() {
     let res: Result<Board, _> = bb.try_into();
     assert!(res.is_err()); // My opponent cannot be in check when it's my move.
 }
+
+#[test]
+fn test_move_counters_round_trip() {
+    let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+    let bb = BoardBuilder::from_str(fen).unwrap();
+    assert_eq!(bb.get_halfmove_clock(), 2);
+    assert_eq!(bb.get_fullmove_number(), 3);
+    assert_eq!(format!("{}", bb), fen);
+
+    let board: Board = bb.try_into().unwrap();
+    assert_eq!(format!("{}", board), fen);
+}
+
+#[test]
+fn test_en_passant_round_trip() {
+    for fen in [
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 3",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ]
+    .iter()
+    {
+        let board = Board::from_str(fen).unwrap();
+        assert_eq!(format!("{}", board), *fen);
+    }
+}
+
+#[test]
+fn test_missing_move_counters() {
+    let bb = BoardBuilder::from_str("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(format!("{}", bb), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
+
+#[test]
+fn test_invalid_move_counters() {
+    assert!(BoardBuilder::from_str("4k3/8/8/8/8/8/8/4K3 b - - x 1").is_err());
+    assert!(BoardBuilder::from_str("4k3/8/8/8/8/8/8/4K3 b - - 0 -3").is_err());
+}
+
+#[test]
+fn test_chess960_fen() {
+    // Shredder-FEN
+    let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+    let bb = BoardBuilder::from_str(fen).unwrap();
+    assert!(bb.is_chess960());
+    assert_eq!(bb.get_castle_rights(Color::White), CastleRights::Both);
+    assert_eq!(bb.get_kingside_rook_file(Color::White), File::H);
+    assert_eq!(bb.get_queenside_rook_file(Color::White), File::F);
+    assert_eq!(bb.to_shredder_fen(), fen);
+    assert_eq!(
+        format!("{}", bb),
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"
+    );
+
+    // X-FEN, where the kingside rook is not the outermost rook
+    let fen = "rk2r1r1/8/8/8/8/8/8/RK2R1R1 w EAea - 0 1";
+    let bb = BoardBuilder::from_str(fen).unwrap();
+    assert_eq!(
+        format!("{}", bb),
+        "rk2r1r1/8/8/8/8/8/8/RK2R1R1 w EAea - 0 1"
+    );
+    assert_eq!(
+        format!("{}", BoardBuilder::from_str(&format!("{}", bb)).unwrap()),
+        format!("{}", bb)
+    );
+
+    let board: Board = bb.try_into().unwrap();
+    assert!(board.is_chess960());
+    assert_eq!(board.kingside_rook_file(Color::Black), File::E);
+}
+
+#[test]
+fn test_standard_fen_is_not_chess960() {
+    let bb = BoardBuilder::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    assert!(!bb.is_chess960());
+
+    // the castle rights can only refer to a Chess960 position
+    let bb = BoardBuilder::from_str("r3k1r1/8/8/8/8/8/8/R4KR1 w KQq - 0 1").unwrap();
+    assert!(bb.is_chess960());
+    assert_eq!(bb.get_kingside_rook_file(Color::White), File::G);
+    assert!(Board::try_from(bb).is_ok());
+
+    assert!(BoardBuilder::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkqX - 0 1").is_err());
+    assert!(BoardBuilder::from_str("r3k2r/8/8/8/8/8/8/R3K2R w E - 0 1").is_err());
+}
+
+#[test]
+fn test_chess960_start_positions() {
+    for index in 0..960 {
+        let bb = BoardBuilder::from_chess960_index(index).unwrap();
+        let board: Board = bb.try_into().unwrap();
+        assert!(board.is_chess960());
+        assert_eq!(board.castle_rights(Color::White), CastleRights::Both);
+        assert_eq!(board.castle_rights(Color::Black), CastleRights::Both);
+
+        // the FEN reads back as the same Chess960 board, even for the standard start position
+        let reparsed = Board::from_str(&board.to_string()).unwrap();
+        assert!(reparsed.is_chess960());
+        assert_eq!(reparsed, board);
+    }
+
+    assert_eq!(
+        format!("{}", BoardBuilder::from_chess960_index(959).unwrap()),
+        "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1"
+    );
+    assert_eq!(
+        format!("{}", BoardBuilder::from_chess960_index(518).unwrap()),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+    );
+}
@@ -1,6 +1,9 @@
+use crate::bitboard::{BitBoard, EMPTY};
 use crate::board::Board;
+use crate::color::Color;
 use crate::error::Error;
 use crate::file::File;
+use crate::magic::{get_bishop_moves, get_knight_moves, get_rook_moves, line};
 use crate::movegen::MoveGen;
 use crate::piece::Piece;
 use crate::rank::Rank;
@@ -61,19 +64,30 @@ impl ChessMove {
     pub fn from_san(board: &Board, move_text: &str) -> Result<ChessMove, Error> {
         // Castles first...
         if move_text.starts_with("O-O") {
-            let rank = board.side_to_move().to_my_backrank();
-            let source_file = File::E;
-            let dest_file = if move_text.starts_with("O-O-O") {
-                File::C
+            let color = board.side_to_move();
+            let rank = color.to_my_backrank();
+            let queenside = move_text.starts_with("O-O-O");
+
+            // Chess960 castles are represented as the king capturing its own rook
+            let m = if board.is_chess960() {
+                let rook_file = if queenside {
+                    board.queenside_rook_file(color)
+                } else {
+                    board.kingside_rook_file(color)
+                };
+                ChessMove::new(
+                    board.king_square(color),
+                    Square::make_square(rank, rook_file),
+                    None,
+                )
             } else {
-                File::G
+                let dest_file = if queenside { File::C } else { File::G };
+                ChessMove::new(
+                    Square::make_square(rank, File::E),
+                    Square::make_square(rank, dest_file),
+                    None,
+                )
             };
-
-            let m = ChessMove::new(
-                Square::make_square(rank, source_file),
-                Square::make_square(rank, dest_file),
-                None,
-            );
             if MoveGen::new_legal(&board).any(|l| l == m) {
                 return Ok(m);
             } else {
@@ -352,6 +366,115 @@ impl ChessMove {
 
         found_move.ok_or(error)
     }
+
+    /// Convert this move to SAN (Standard Algebraic Notation).
+    ///
+    /// Moves are disambiguated by file, then rank, then square, as needed, and end with `+` for
+    /// check or `#` for checkmate.  SAN only makes sense for a move that is legal on `board`, so
+    /// any other move is written the same way as `ChessMove`'s `Display` (such as `e1e8`).
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Piece, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(ChessMove::new(Square::G1, Square::F3, None).to_san(&board), "Nf3");
+    ///
+    /// let board = Board::from_str("4k3/1P6/8/8/8/8/8/R3K2R w KQ - 0 1").expect("Valid FEN");
+    /// assert_eq!(ChessMove::new(Square::E1, Square::C1, None).to_san(&board), "O-O-O");
+    /// assert_eq!(
+    ///     ChessMove::new(Square::B7, Square::B8, Some(Piece::Queen)).to_san(&board),
+    ///     "b8=Q+"
+    /// );
+    /// assert_eq!(ChessMove::new(Square::E1, Square::E8, None).to_san(&board), "e1e8");
+    /// ```
+    pub fn to_san(&self, board: &Board) -> String {
+        let source = self.source;
+        let dest = self.dest;
+        let color = board.side_to_move();
+        let piece = match board.piece_on(source) {
+            Some(piece) if board.legal(*self) => piece,
+            _ => return self.to_string(),
+        };
+
+        let mut result = if board.is_castling(*self) {
+            if dest.get_file() > source.get_file() {
+                "O-O".to_owned()
+            } else {
+                "O-O-O".to_owned()
+            }
+        } else if piece == Piece::Pawn {
+            let mut result = String::new();
+            if source.get_file() != dest.get_file() {
+                result.push(file_char(source.get_file()));
+                result.push('x');
+            }
+            result.push_str(&dest.to_string());
+            if let Some(promotion) = self.promotion {
+                result.push('=');
+                result.push_str(&promotion.to_string(Color::White));
+            }
+            result
+        } else {
+            let mut result = piece.to_string(Color::White);
+
+            // Which other pieces of the same type can legally move to the same square?  They all
+            // land on the same square, so they get out of check in the same way, but pinned
+            // pieces can only move along the pin.
+            let combined = *board.combined();
+            let attackers = match piece {
+                Piece::Knight => get_knight_moves(dest),
+                Piece::Bishop => get_bishop_moves(dest, combined),
+                Piece::Rook => get_rook_moves(dest, combined),
+                Piece::Queen => get_bishop_moves(dest, combined) | get_rook_moves(dest, combined),
+                _ => EMPTY,
+            };
+            let ksq = board.king_square(color);
+            let others = (attackers
+                & board.pieces(piece)
+                & board.color_combined(color)
+                & !BitBoard::from_square(source))
+            .filter(|sq| {
+                board.pinned() & BitBoard::from_square(*sq) == EMPTY
+                    || line(ksq, *sq) & BitBoard::from_square(dest) != EMPTY
+            })
+            .collect::<Vec<Square>>();
+
+            if !others.is_empty() {
+                if others.iter().all(|sq| sq.get_file() != source.get_file()) {
+                    result.push(file_char(source.get_file()));
+                } else if others.iter().all(|sq| sq.get_rank() != source.get_rank()) {
+                    result.push(rank_char(source.get_rank()));
+                } else {
+                    result.push_str(&source.to_string());
+                }
+            }
+
+            if board.is_capture(*self) {
+                result.push('x');
+            }
+            result.push_str(&dest.to_string());
+            result
+        };
+
+        if board.gives_check(*self) {
+            if MoveGen::new_legal(&board.make_move_new(*self)).len() == 0 {
+                result.push('#');
+            } else {
+                result.push('+');
+            }
+        }
+
+        result
+    }
+}
+
+fn file_char(file: File) -> char {
+    (b'a' + file.to_index() as u8) as char
+}
+
+fn rank_char(rank: Rank) -> char {
+    (b'1' + rank.to_index() as u8) as char
 }
 
 impl fmt::Display for ChessMove {
@@ -428,3 +551,47 @@ fn test_basic_moves() {
         ChessMove::new(Square::E2, Square::E4, None)
     );
 }
+
+#[test]
+fn test_chess960_castles_san() {
+    let board = Board::from_str("1r2k1r1/6p1/8/8/8/8/6P1/1R2K1R1 w GBgb - 0 1").unwrap();
+    assert_eq!(
+        ChessMove::from_san(&board, "O-O").unwrap(),
+        ChessMove::new(Square::E1, Square::G1, None)
+    );
+    assert_eq!(
+        ChessMove::from_san(&board, "O-O-O").unwrap(),
+        ChessMove::new(Square::E1, Square::B1, None)
+    );
+}
+
+#[test]
+fn test_san() {
+    let tests = [
+        // file, rank and square disambiguation
+        ("7k/8/8/8/8/8/8/R1R1K3 w - - 0 1", "a1b1", "Rab1"),
+        ("7k/8/8/8/R7/8/8/R3K3 w - - 0 1", "a1a2", "R1a2"),
+        ("6k1/8/8/8/8/8/8/Q1Q1K2Q w - - 0 1", "a1b2", "Qab2"),
+        ("7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1", "a4b3", "Qa4b3"),
+        // a pinned piece doesn't need to be told apart
+        ("k7/4r3/8/8/8/8/4N3/2N1K3 w - - 0 1", "c1d3", "Nd3"),
+        // captures, en passant and promotions
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+        ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q", "axb8=Q+"),
+        ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8n", "a8=N"),
+        // mate
+        ("7k/5Q2/6K1/8/8/8/8/8 w - - 0 1", "f7g7", "Qg7#"),
+        // Chess960 castles
+        ("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1h1", "O-O"),
+        ("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1b1", "O-O-O"),
+        // moves that aren't legal are left as they are
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", "a3a4", "a3a4"),
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", "e1e8", "e1e8"),
+        ("4k3/8/8/8/8/8/8/4K3 b - - 0 1", "e1e2", "e1e2"),
+    ];
+
+    for &(fen, uci, expected) in tests.iter() {
+        let board = Board::from_str(fen).unwrap();
+        assert_eq!(ChessMove::from_str(uci).unwrap().to_san(&board), expected);
+    }
+}
@@ -0,0 +1,425 @@
+use crate::color::{Color, NUM_COLORS};
+use crate::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of time for a `ChessClock`.
+///
+/// `now` returns the time elapsed since some fixed, arbitrary point, and must never go
+/// backwards.  Use `SystemTimeSource` to follow the real time, or `ManualTimeSource` to control
+/// the time yourself, for example in tests.
+pub trait TimeSource: fmt::Debug + Send + Sync {
+    /// How much time has passed since the starting point of this time source?
+    fn now(&self) -> Duration;
+}
+
+/// A `TimeSource` that follows the system's monotonic clock.
+#[derive(Copy, Clone, Debug)]
+pub struct SystemTimeSource {
+    start: Instant,
+}
+
+impl SystemTimeSource {
+    /// Create a time source, starting now.
+    pub fn new() -> SystemTimeSource {
+        SystemTimeSource {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        SystemTimeSource::new()
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A `TimeSource` that only moves when told to.
+///
+/// ```
+/// use minorhacks_chess::{ManualTimeSource, TimeSource};
+/// use std::time::Duration;
+///
+/// let time = ManualTimeSource::new();
+/// assert_eq!(time.now(), Duration::from_secs(0));
+/// time.advance(Duration::from_secs(5));
+/// time.advance(Duration::from_millis(500));
+/// assert_eq!(time.now(), Duration::from_millis(5500));
+/// ```
+#[derive(Debug, Default)]
+pub struct ManualTimeSource {
+    now: Mutex<Duration>,
+}
+
+impl ManualTimeSource {
+    /// Create a time source, starting at zero.
+    pub fn new() -> ManualTimeSource {
+        ManualTimeSource::default()
+    }
+
+    /// Let `time` pass.
+    pub fn advance(&self, time: Duration) {
+        *self.now.lock().unwrap() += time;
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// How a player's clock is compensated for each move.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Eq, Hash)]
+pub enum Increment {
+    /// No compensation.
+    None,
+    /// This much time is added after every move.
+    Fischer(Duration),
+    /// The time used for a move is given back after it, up to this much.
+    Bronstein(Duration),
+    /// The clock waits this long at the start of every move before it starts counting down.
+    SimpleDelay(Duration),
+}
+
+/// One period of a time control: an amount of time for a number of moves, or for the rest of
+/// the game.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Eq, Hash)]
+pub struct TimePeriod {
+    moves: Option<u32>,
+    time: Duration,
+    increment: Increment,
+}
+
+impl TimePeriod {
+    /// Create a period lasting `moves` moves, or the rest of the game if `moves` is `None`.
+    /// The `time` is added to the clock when the period starts.
+    pub fn new(moves: Option<u32>, time: Duration, increment: Increment) -> TimePeriod {
+        TimePeriod {
+            moves,
+            time,
+            increment,
+        }
+    }
+
+    /// How many moves does this period last?  `None` means the rest of the game.
+    pub fn get_moves(&self) -> Option<u32> {
+        self.moves
+    }
+
+    /// How much time is added to the clock when this period starts?
+    pub fn get_time(&self) -> Duration {
+        self.time
+    }
+
+    /// How is the clock compensated for moves made during this period?
+    pub fn get_increment(&self) -> Increment {
+        self.increment
+    }
+}
+
+/// The time control of a game, as a list of periods.
+///
+/// Once a player has made all of the moves of a period, the time of the next period is added to
+/// their clock.  If the last period is for a number of moves, it repeats for the rest of the
+/// game.
+///
+/// ```
+/// use minorhacks_chess::{Increment, TimeControl, TimePeriod};
+/// use std::time::Duration;
+///
+/// // 90 minutes for 40 moves, then 30 minutes for the rest of the game, plus 30 seconds per move
+/// let increment = Increment::Fischer(Duration::from_secs(30));
+/// let classical = TimeControl::new(vec![
+///     TimePeriod::new(Some(40), Duration::from_secs(90 * 60), increment),
+///     TimePeriod::new(None, Duration::from_secs(30 * 60), increment),
+/// ])
+/// .expect("Valid time control");
+/// assert_eq!(classical.get_periods().len(), 2);
+///
+/// // only the last period may be for the rest of the game
+/// assert!(TimeControl::new(vec![
+///     TimePeriod::new(None, Duration::from_secs(90 * 60), increment),
+///     TimePeriod::new(None, Duration::from_secs(30 * 60), increment),
+/// ])
+/// .is_err());
+/// ```
+#[derive(Clone, PartialEq, PartialOrd, Debug, Eq, Hash)]
+pub struct TimeControl {
+    periods: Vec<TimePeriod>,
+}
+
+impl TimeControl {
+    /// Create a time control from its periods.  There must be at least one period, and every
+    /// period but the last must be for a positive number of moves.
+    pub fn new(periods: Vec<TimePeriod>) -> Result<TimeControl, Error> {
+        let (last, rest) = periods.split_last().ok_or(Error::InvalidTimeControl)?;
+        if last.moves == Some(0) || rest.iter().any(|p| p.moves.unwrap_or(0) == 0) {
+            return Err(Error::InvalidTimeControl);
+        }
+        Ok(TimeControl { periods })
+    }
+
+    /// The whole game must be played in `time`.
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::None)
+    }
+
+    /// The game starts with `time` on the clock, and `increment` is added after every move.
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::Fischer(increment))
+    }
+
+    /// The game starts with `time` on the clock, and the time used for every move is given
+    /// back, up to `delay`.
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::Bronstein(delay))
+    }
+
+    /// The game starts with `time` on the clock, which only starts counting down `delay` after
+    /// the start of every move.
+    pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::SimpleDelay(delay))
+    }
+
+    fn single(time: Duration, increment: Increment) -> TimeControl {
+        TimeControl {
+            periods: vec![TimePeriod::new(None, time, increment)],
+        }
+    }
+
+    /// Get the periods of this time control.
+    pub fn get_periods(&self) -> &[TimePeriod] {
+        &self.periods
+    }
+}
+
+/// A chess clock, keeping the time of both players under a `TimeControl`.
+///
+/// Only one side's clock runs at a time.  `press` ends the turn of the side whose clock is
+/// running, and starts the clock of the other side.
+///
+/// ```
+/// use minorhacks_chess::{ChessClock, Color, ManualTimeSource, TimeControl};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let time = Arc::new(ManualTimeSource::new());
+/// let control = TimeControl::fischer(Duration::from_secs(180), Duration::from_secs(2));
+/// let mut clock = ChessClock::with_time_source(control, time.clone());
+///
+/// clock.start(Color::White);
+/// time.advance(Duration::from_secs(10));
+/// assert_eq!(clock.remaining(Color::White), Duration::from_secs(170));
+/// assert_eq!(clock.press(), Some(Duration::from_secs(10)));
+/// assert_eq!(clock.remaining(Color::White), Duration::from_secs(172));
+/// assert_eq!(clock.running(), Some(Color::Black));
+///
+/// time.advance(Duration::from_secs(200));
+/// assert_eq!(clock.remaining(Color::Black), Duration::from_secs(0));
+/// assert_eq!(clock.flagged(), Some(Color::Black));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChessClock {
+    control: TimeControl,
+    remaining: [Duration; NUM_COLORS],
+    // the period each side is in, and how many moves they made in it
+    periods: [usize; NUM_COLORS],
+    moves: [u32; NUM_COLORS],
+    // the side whose clock is running, and when its turn started
+    running: Option<(Color, Duration)>,
+    flagged: Option<Color>,
+    source: Arc<dyn TimeSource>,
+}
+
+impl ChessClock {
+    /// Create a stopped clock following the system time.
+    pub fn new(control: TimeControl) -> ChessClock {
+        ChessClock::with_time_source(control, Arc::new(SystemTimeSource::new()))
+    }
+
+    /// Create a stopped clock following `source`.
+    pub fn with_time_source(control: TimeControl, source: Arc<dyn TimeSource>) -> ChessClock {
+        let time = control.periods[0].time;
+        ChessClock {
+            control,
+            remaining: [time; NUM_COLORS],
+            periods: [0; NUM_COLORS],
+            moves: [0; NUM_COLORS],
+            running: None,
+            flagged: None,
+            source,
+        }
+    }
+
+    /// Get the time control of this clock.
+    pub fn get_time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Whose clock is running, if any?
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// How much time does `color` have left?
+    pub fn remaining(&self, color: Color) -> Duration {
+        let remaining = self.remaining[color.to_index()];
+        match self.running {
+            Some((running, start)) if running == color => {
+                remaining.saturating_sub(self.charged(color, self.source.now() - start))
+            }
+            _ => remaining,
+        }
+    }
+
+    /// Which side, if any, has run out of time?
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged.or_else(|| {
+            self.running()
+                .filter(|&color| self.remaining(color) == Duration::from_secs(0))
+        })
+    }
+
+    /// Start the turn of `color`, stopping the other clock without finishing its move.
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some((color, self.source.now()));
+    }
+
+    /// Stop the clock without finishing the move, for example to pause the game.  Returns how
+    /// long the clock ran since it was last started, if it was running.
+    pub fn stop(&mut self) -> Option<Duration> {
+        let (color, start) = self.running.take()?;
+        let used = self.source.now() - start;
+        self.charge(color, used);
+        Some(used)
+    }
+
+    /// Finish the move of the side whose clock is running, and start the clock of the other
+    /// side.  Returns the time used for the move, or `None` if the clock wasn't running.
+    pub fn press(&mut self) -> Option<Duration> {
+        let (color, start) = self.running?;
+        let used = self.source.now() - start;
+        self.running = None;
+        if self.charge(color, used) {
+            let index = color.to_index();
+            let period = self.control.periods[self.periods[index]];
+            self.remaining[index] += match period.increment {
+                Increment::Fischer(increment) => increment,
+                Increment::Bronstein(delay) => used.min(delay),
+                Increment::None | Increment::SimpleDelay(_) => Duration::from_secs(0),
+            };
+
+            self.moves[index] += 1;
+            if Some(self.moves[index]) == period.moves {
+                self.moves[index] = 0;
+                self.periods[index] = (self.periods[index] + 1).min(self.control.periods.len() - 1);
+                self.remaining[index] += self.control.periods[self.periods[index]].time;
+            }
+        }
+        self.running = Some((!color, self.source.now()));
+        Some(used)
+    }
+
+    /// How much of `used` is taken off the clock of `color`?
+    fn charged(&self, color: Color, used: Duration) -> Duration {
+        let period = self.control.periods[self.periods[color.to_index()]];
+        match period.increment {
+            Increment::SimpleDelay(delay) => used.saturating_sub(delay),
+            _ => used,
+        }
+    }
+
+    /// Take `used` off the clock of `color`.  Returns false if that made them run out of time.
+    fn charge(&mut self, color: Color, used: Duration) -> bool {
+        let charged = self.charged(color, used);
+        let remaining = &mut self.remaining[color.to_index()];
+        if charged >= *remaining {
+            *remaining = Duration::from_secs(0);
+            self.flagged = self.flagged.or(Some(color));
+            false
+        } else {
+            *remaining -= charged;
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[test]
+fn test_delays() {
+    let time = Arc::new(ManualTimeSource::new());
+    let control = TimeControl::bronstein(secs(60), secs(5));
+    let mut clock = ChessClock::with_time_source(control, time.clone());
+    clock.start(Color::White);
+    time.advance(secs(3));
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(60));
+    time.advance(secs(8));
+    assert_eq!(clock.remaining(Color::Black), secs(52));
+    clock.press();
+    assert_eq!(clock.remaining(Color::Black), secs(57));
+
+    let control = TimeControl::simple_delay(secs(60), secs(5));
+    let mut clock = ChessClock::with_time_source(control, time.clone());
+    clock.start(Color::White);
+    time.advance(secs(3));
+    assert_eq!(clock.remaining(Color::White), secs(60));
+    clock.press();
+    assert_eq!(clock.remaining(Color::White), secs(60));
+    time.advance(secs(8));
+    assert_eq!(clock.remaining(Color::Black), secs(57));
+    clock.press();
+    assert_eq!(clock.remaining(Color::Black), secs(57));
+
+    // a stopped clock doesn't count down
+    assert_eq!(clock.stop(), Some(secs(0)));
+    time.advance(secs(100));
+    assert_eq!(clock.running(), None);
+    assert_eq!(clock.press(), None);
+    assert_eq!(clock.remaining(Color::White), secs(60));
+    assert_eq!(clock.flagged(), None);
+}
+
+#[test]
+fn test_periods() {
+    let time = Arc::new(ManualTimeSource::new());
+    let control = TimeControl::new(vec![
+        TimePeriod::new(Some(2), secs(100), Increment::Fischer(secs(1))),
+        TimePeriod::new(Some(1), secs(50), Increment::None),
+    ])
+    .unwrap();
+    let mut clock = ChessClock::with_time_source(control, time.clone());
+    clock.start(Color::White);
+    let mut white = vec![];
+    for _ in 0..4 {
+        time.advance(secs(10));
+        clock.press();
+        white.push(clock.remaining(Color::White));
+        time.advance(secs(20));
+        clock.press();
+    }
+
+    // the second period starts after two moves, and repeats after every move
+    assert_eq!(white, vec![secs(91), secs(132), secs(172), secs(212)]);
+    assert_eq!(clock.remaining(Color::Black), secs(172));
+
+    // running out of time can't be undone by pressing the clock
+    time.advance(secs(300));
+    assert_eq!(clock.flagged(), Some(Color::White));
+    assert_eq!(clock.press(), Some(secs(300)));
+    assert_eq!(clock.remaining(Color::White), secs(0));
+    assert_eq!(clock.flagged(), Some(Color::White));
+}
@@ -0,0 +1,739 @@
+use crate::board::Board;
+use crate::board_builder::BoardBuilder;
+use crate::chess_move::ChessMove;
+use crate::error::Error;
+use crate::movegen::MoveGen;
+
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A position in Extended Position Description (EPD) format, along with its operations.
+///
+/// An EPD record is the first four fields of a FEN string (the pieces, side to move, castle rights
+/// and en passant square), followed by any number of operations, each of which is an opcode and a
+/// list of operands, terminated by a `;`.  The operations that are understood are:
+///
+/// * `bm`: the best moves in the position, in SAN
+/// * `am`: moves to avoid in the position, in SAN
+/// * `id`: the name of the position
+/// * `c0` through `c9`: comments
+/// * `D1`, `D2`, ...: the perft count at that depth
+/// * `hmvc` and `fmvn`: the halfmove clock and fullmove number, which are applied to the board
+///
+/// Other operations are kept as they are, and written back out.
+///
+/// ```
+/// use minorhacks_chess::{ChessMove, Epd, Square};
+/// use std::str::FromStr;
+///
+/// let epd = Epd::from_str(
+///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4 d4; id \"start\"; D2 400;",
+/// )
+/// .expect("Valid EPD");
+///
+/// assert_eq!(epd.get_id(), Some("start"));
+/// assert_eq!(
+///     epd.get_best_moves(),
+///     &[
+///         ChessMove::new(Square::E2, Square::E4, None),
+///         ChessMove::new(Square::D2, Square::D4, None)
+///     ]
+/// );
+/// assert_eq!(epd.get_perft(2), Some(400));
+/// assert_eq!(
+///     epd.to_string(),
+///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4 d4; id \"start\"; D2 400;"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Epd {
+    board: Board,
+    best_moves: Vec<ChessMove>,
+    avoid_moves: Vec<ChessMove>,
+    id: Option<String>,
+    comments: [Option<String>; 10],
+    perft: Vec<(usize, u64)>,
+    operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    /// Create an `Epd` for a position, without any operations.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, Epd};
+    ///
+    /// let epd = Epd::new(Board::default());
+    /// assert_eq!(
+    ///     epd.to_string(),
+    ///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"
+    /// );
+    /// ```
+    pub fn new(board: Board) -> Epd {
+        Epd {
+            board,
+            best_moves: vec![],
+            avoid_moves: vec![],
+            id: None,
+            comments: Default::default(),
+            perft: vec![],
+            operations: vec![],
+        }
+    }
+
+    /// Get the position.
+    pub fn get_board(&self) -> Board {
+        self.board
+    }
+
+    /// Get the best moves (`bm`) in the position.
+    pub fn get_best_moves(&self) -> &[ChessMove] {
+        &self.best_moves
+    }
+
+    /// Get the moves to avoid (`am`) in the position.
+    pub fn get_avoid_moves(&self) -> &[ChessMove] {
+        &self.avoid_moves
+    }
+
+    /// Get the name (`id`) of the position.
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Get comment `c0` through `c9`.
+    ///
+    /// Panics if `index` is not less than 10.
+    pub fn get_comment(&self, index: usize) -> Option<&str> {
+        self.comments[index].as_deref()
+    }
+
+    /// Get the expected perft count (`Dn`) at a depth.
+    pub fn get_perft(&self, depth: usize) -> Option<u64> {
+        self.perft
+            .iter()
+            .find(|&&(d, _)| d == depth)
+            .map(|&(_, count)| count)
+    }
+
+    /// Get all the expected perft counts, as `(depth, count)` pairs sorted by depth.
+    pub fn get_perft_counts(&self) -> &[(usize, u64)] {
+        &self.perft
+    }
+
+    /// Get the operands of any other operation.
+    pub fn get_operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| &operands[..])
+    }
+
+    /// Add a best move (`bm`).
+    ///
+    /// Panics if `chess_move` is not legal in the position.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Epd, Square};
+    ///
+    /// let epd = Epd::new(Board::default())
+    ///     .best_move(ChessMove::new(Square::G1, Square::F3, None))
+    ///     .id("knight")
+    ///     .comment(0, "Hypermodern")
+    ///     .perft(1, 20)
+    ///     .clone();
+    ///
+    /// assert_eq!(
+    ///     epd.to_string(),
+    ///     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm Nf3; id \"knight\"; c0 \"Hypermodern\"; D1 20;"
+    /// );
+    /// ```
+    pub fn best_move(&mut self, chess_move: ChessMove) -> &mut Self {
+        assert!(self.board.legal(chess_move), "best move must be legal");
+        self.best_moves.push(chess_move);
+        self
+    }
+
+    /// Add a move to avoid (`am`).
+    ///
+    /// Panics if `chess_move` is not legal in the position.
+    pub fn avoid_move(&mut self, chess_move: ChessMove) -> &mut Self {
+        assert!(self.board.legal(chess_move), "move to avoid must be legal");
+        self.avoid_moves.push(chess_move);
+        self
+    }
+
+    /// Set the name (`id`) of the position.
+    pub fn id(&mut self, id: &str) -> &mut Self {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    /// Set comment `c0` through `c9`.
+    ///
+    /// Panics if `index` is not less than 10.
+    pub fn comment(&mut self, index: usize, comment: &str) -> &mut Self {
+        self.comments[index] = Some(comment.to_owned());
+        self
+    }
+
+    /// Set the expected perft count (`Dn`) at a depth.
+    ///
+    /// Panics if `depth` is 0.
+    pub fn perft(&mut self, depth: usize, count: u64) -> &mut Self {
+        assert!(depth > 0, "perft depth must be at least 1");
+        self.perft.retain(|&(d, _)| d != depth);
+        self.perft.push((depth, count));
+        self.perft.sort();
+        self
+    }
+
+    /// Set any other operation.
+    pub fn operation(&mut self, opcode: &str, operands: &[&str]) -> &mut Self {
+        let operands = operands.iter().map(|s| s.to_string()).collect();
+        match self.operations.iter_mut().find(|(op, _)| op == opcode) {
+            Some(operation) => operation.1 = operands,
+            None => self.operations.push((opcode.to_owned(), operands)),
+        }
+        self
+    }
+
+    /// Would playing `chess_move` solve this position?
+    ///
+    /// That is, `chess_move` is one of the best moves, if there are any, and is not one of the
+    /// moves to avoid.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Epd, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let epd = Epd::from_str("7k/8/8/8/8/8/8/R5K1 w - - am Ra2;").expect("Valid EPD");
+    /// assert!(epd.is_solved_by(ChessMove::new(Square::A1, Square::A8, None)));
+    /// assert!(!epd.is_solved_by(ChessMove::new(Square::A1, Square::A2, None)));
+    /// ```
+    pub fn is_solved_by(&self, chess_move: ChessMove) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&chess_move))
+            && !self.avoid_moves.contains(&chess_move)
+    }
+
+    /// Check the expected perft counts of this position against `MoveGen::movegen_perft_test`,
+    /// up to and including `max_depth`.
+    ///
+    /// ```
+    /// use minorhacks_chess::Epd;
+    /// use std::str::FromStr;
+    ///
+    /// let epd = Epd::from_str("4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 66 ;D3 1197")
+    ///     .expect("Valid EPD");
+    ///
+    /// let checks = epd.check_perft(2);
+    /// assert_eq!(checks.len(), 2);
+    /// assert!(checks.iter().all(|c| c.passed()));
+    /// ```
+    pub fn check_perft(&self, max_depth: usize) -> Vec<PerftCheck> {
+        self.perft
+            .iter()
+            .filter(|&&(depth, _)| depth <= max_depth)
+            .map(|&(depth, expected)| PerftCheck {
+                board: self.board,
+                id: self.id.clone(),
+                depth,
+                expected,
+                actual: MoveGen::movegen_perft_test(&self.board, depth) as u64,
+            })
+            .collect()
+    }
+
+    fn apply_operation(
+        &mut self,
+        epd: &str,
+        opcode: &str,
+        operands: Vec<String>,
+    ) -> Result<(), Error> {
+        let invalid = || Error::InvalidEpd {
+            epd: epd.to_owned(),
+        };
+
+        match opcode {
+            "bm" | "am" => {
+                let mut moves = vec![];
+                for operand in operands.iter() {
+                    let chess_move = ChessMove::from_san(&self.board, operand)
+                        .or_else(|_| ChessMove::from_str(operand))
+                        .map_err(|_| invalid())?;
+                    if !self.board.legal(chess_move) {
+                        return Err(invalid());
+                    }
+                    moves.push(chess_move);
+                }
+                if opcode == "bm" {
+                    self.best_moves = moves;
+                } else {
+                    self.avoid_moves = moves;
+                }
+            }
+            "id" => {
+                self.id = Some(operands.join(" "));
+            }
+            "hmvc" | "fmvn" => {
+                let value = match operands.as_slice() {
+                    [value] => value.parse::<u16>().map_err(|_| invalid())?,
+                    _ => return Err(invalid()),
+                };
+                let mut builder = BoardBuilder::from(self.board);
+                if opcode == "hmvc" {
+                    builder.halfmove_clock(value);
+                } else {
+                    builder.fullmove_number(value);
+                }
+                self.board = builder.try_into()?;
+                self.operations.push((opcode.to_owned(), operands));
+            }
+            _ => {
+                if let Some(index) = opcode.strip_prefix('c') {
+                    if let Ok(index) = index.parse::<usize>() {
+                        if opcode.len() == 2 {
+                            self.comments[index] = Some(operands.join(" "));
+                            return Ok(());
+                        }
+                    }
+                }
+
+                if let Some(depth) = opcode.strip_prefix('D') {
+                    if let Ok(depth) = depth.parse::<usize>() {
+                        if depth == 0 {
+                            return Err(invalid());
+                        }
+                        let count = match operands.as_slice() {
+                            [count] => count.parse::<u64>().map_err(|_| invalid())?,
+                            _ => return Err(invalid()),
+                        };
+                        self.perft(depth, count);
+                        return Ok(());
+                    }
+                }
+
+                self.operations.push((opcode.to_owned(), operands));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the operations of an EPD record into opcodes and operands.  Operands in double quotes
+/// may contain spaces and semicolons.
+fn parse_operations(epd: &str, operations: &str) -> Result<Vec<(String, Vec<String>)>, Error> {
+    let mut result: Vec<(String, Vec<String>)> = vec![];
+    let mut tokens: Vec<String> = vec![];
+    let mut token: Option<String> = None;
+    let mut quoted = false;
+
+    let mut finish = |tokens: &mut Vec<String>| {
+        if !tokens.is_empty() {
+            let opcode = tokens.remove(0);
+            result.push((opcode, tokens.split_off(0)));
+        }
+    };
+
+    for c in operations.chars() {
+        if quoted {
+            if c == '"' {
+                quoted = false;
+                tokens.push(token.take().unwrap_or_default());
+            } else {
+                token.get_or_insert_with(String::new).push(c);
+            }
+            continue;
+        }
+
+        match c {
+            '"' if token.is_none() && !tokens.is_empty() => {
+                quoted = true;
+                token = Some(String::new());
+            }
+            ';' => {
+                tokens.extend(token.take());
+                finish(&mut tokens);
+            }
+            c if c.is_whitespace() => tokens.extend(token.take()),
+            c => token.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quoted {
+        return Err(Error::InvalidEpd {
+            epd: epd.to_owned(),
+        });
+    }
+    tokens.extend(token.take());
+    finish(&mut tokens);
+
+    Ok(result)
+}
+
+impl FromStr for Epd {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+
+        // the four FEN fields come first, separated by whitespace
+        let mut fields = vec![];
+        let mut rest = value;
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(Error::InvalidEpd {
+                    epd: value.to_owned(),
+                });
+            }
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        let board = Board::from_str(&fields.join(" "))?;
+        let mut epd = Epd::new(board);
+        for (opcode, operands) in parse_operations(value, rest)? {
+            epd.apply_operation(value, &opcode, operands)?;
+        }
+        Ok(epd)
+    }
+}
+
+/// Write operands, quoting them if they can't be written as they are.
+fn write_operands(f: &mut fmt::Formatter, operands: &[String], quote: bool) -> fmt::Result {
+    for operand in operands.iter() {
+        if quote || operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';')
+        {
+            write!(f, " \"{}\"", operand)?;
+        } else {
+            write!(f, " {}", operand)?;
+        }
+    }
+    write!(f, ";")
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fen = self.board.to_string();
+        let fields: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+
+        for &(opcode, moves) in [("bm", &self.best_moves), ("am", &self.avoid_moves)].iter() {
+            if !moves.is_empty() {
+                write!(f, " {}", opcode)?;
+                for chess_move in moves.iter() {
+                    write!(f, " {}", chess_move.to_san(&self.board))?;
+                }
+                write!(f, ";")?;
+            }
+        }
+
+        if let Some(ref id) = self.id {
+            write!(f, " id")?;
+            write_operands(f, std::slice::from_ref(id), true)?;
+        }
+
+        for (index, comment) in self.comments.iter().enumerate() {
+            if let Some(comment) = comment {
+                write!(f, " c{}", index)?;
+                write_operands(f, std::slice::from_ref(comment), true)?;
+            }
+        }
+
+        for &(depth, count) in self.perft.iter() {
+            write!(f, " D{} {};", depth, count)?;
+        }
+
+        for (opcode, operands) in self.operations.iter() {
+            write!(f, " {}", opcode)?;
+            write_operands(f, operands, false)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of checking one expected perft count of an EPD test suite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PerftCheck {
+    board: Board,
+    id: Option<String>,
+    depth: usize,
+    expected: u64,
+    actual: u64,
+}
+
+impl PerftCheck {
+    /// Get the position that was checked.
+    pub fn get_board(&self) -> Board {
+        self.board
+    }
+
+    /// Get the name (`id`) of the position that was checked.
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Get the depth that was checked.
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the perft count from the test suite.
+    pub fn get_expected(&self) -> u64 {
+        self.expected
+    }
+
+    /// Get the perft count from `MoveGen::movegen_perft_test`.
+    pub fn get_actual(&self) -> u64 {
+        self.actual
+    }
+
+    /// Did the move generator agree with the test suite?
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// How well a move-picking function did on the `bm` and `am` positions of an EPD test suite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpdScore {
+    total: usize,
+    failures: Vec<(usize, ChessMove)>,
+}
+
+impl EpdScore {
+    /// Get the number of positions with `bm` or `am` operations that were tried.
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    /// Get the number of positions that were solved.
+    pub fn get_solved(&self) -> usize {
+        self.total - self.failures.len()
+    }
+
+    /// Get the positions that were not solved, as their index in the suite along with the move
+    /// that was picked.
+    pub fn get_failures(&self) -> &[(usize, ChessMove)] {
+        &self.failures
+    }
+}
+
+/// A test suite of EPD records, one per line.
+///
+/// Blank lines, and lines starting with `#`, are ignored.
+///
+/// ```
+/// use minorhacks_chess::{EpdSuite, MoveGen};
+/// use std::str::FromStr;
+///
+/// let suite = EpdSuite::from_str(
+///     "# mate in one, and a perft test
+/// 6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"back rank\";
+/// 6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra2; id \"not that\";
+/// rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400
+/// ",
+/// )
+/// .expect("Valid suite");
+/// assert_eq!(suite.len(), 3);
+///
+/// assert!(suite.run_perft(2).iter().all(|c| c.passed()));
+///
+/// // always play the first legal move
+/// let score = suite.score(|board| MoveGen::new_legal(board).next().unwrap());
+/// assert_eq!(score.get_total(), 2);
+/// assert_eq!(score.get_solved(), 1);
+/// assert_eq!(score.get_failures()[0].0, 0);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpdSuite {
+    positions: Vec<Epd>,
+}
+
+impl EpdSuite {
+    /// Read a test suite from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<EpdSuite, Error> {
+        let file = fs::File::open(path).map_err(|e| Error::IoFailure {
+            message: e.to_string(),
+        })?;
+        EpdSuite::from_reader(BufReader::new(file))
+    }
+
+    /// Read a test suite.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<EpdSuite, Error> {
+        let mut positions = vec![];
+        for line in reader.lines() {
+            let line = line.map_err(|e| Error::IoFailure {
+                message: e.to_string(),
+            })?;
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                positions.push(Epd::from_str(line)?);
+            }
+        }
+        Ok(EpdSuite { positions })
+    }
+
+    /// Get the positions of the suite.
+    pub fn positions(&self) -> &[Epd] {
+        &self.positions
+    }
+
+    /// Add a position to the suite.
+    pub fn push(&mut self, epd: Epd) -> &mut Self {
+        self.positions.push(epd);
+        self
+    }
+
+    /// How many positions are in the suite?
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Is the suite empty?
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Check every expected perft count (`Dn`) up to and including `max_depth` against
+    /// `MoveGen::movegen_perft_test`.
+    pub fn run_perft(&self, max_depth: usize) -> Vec<PerftCheck> {
+        self.positions
+            .iter()
+            .flat_map(|epd| epd.check_perft(max_depth))
+            .collect()
+    }
+
+    /// Ask `pick_move` for a move in every position with a `bm` or `am` operation, and count how
+    /// many of them it solved.
+    pub fn score<F: FnMut(&Board) -> ChessMove>(&self, mut pick_move: F) -> EpdScore {
+        let mut score = EpdScore {
+            total: 0,
+            failures: vec![],
+        };
+
+        for (index, epd) in self.positions.iter().enumerate() {
+            if epd.best_moves.is_empty() && epd.avoid_moves.is_empty() {
+                continue;
+            }
+
+            score.total += 1;
+            let chess_move = pick_move(&epd.board);
+            if !epd.is_solved_by(chess_move) {
+                score.failures.push((index, chess_move));
+            }
+        }
+
+        score
+    }
+}
+
+impl FromStr for EpdSuite {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        EpdSuite::from_reader(value.as_bytes())
+    }
+}
+
+impl fmt::Display for EpdSuite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for epd in self.positions.iter() {
+            writeln!(f, "{}", epd)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+use crate::square::Square;
+
+#[test]
+fn test_epd_operations_round_trip() {
+    let text = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am Ng5; id \"Open game; 3rd move\"; c0 \"Ruy Lopez\"; c9 \"Italian\"; D1 27; D2 835; acd 12; pv Bb5 a6;";
+    let epd = Epd::from_str(text).unwrap();
+
+    assert_eq!(
+        epd.get_best_moves(),
+        &[
+            ChessMove::new(Square::F1, Square::B5, None),
+            ChessMove::new(Square::F1, Square::C4, None)
+        ]
+    );
+    assert_eq!(
+        epd.get_avoid_moves(),
+        &[ChessMove::new(Square::F3, Square::G5, None)]
+    );
+    assert_eq!(epd.get_id(), Some("Open game; 3rd move"));
+    assert_eq!(epd.get_comment(0), Some("Ruy Lopez"));
+    assert_eq!(epd.get_comment(1), None);
+    assert_eq!(epd.get_comment(9), Some("Italian"));
+    assert_eq!(epd.get_perft_counts(), &[(1, 27), (2, 835)]);
+    assert_eq!(epd.get_operation("acd"), Some(&["12".to_owned()][..]));
+    assert_eq!(
+        epd.get_operation("pv"),
+        Some(&["Bb5".to_owned(), "a6".to_owned()][..])
+    );
+
+    assert_eq!(epd.to_string(), text);
+    assert_eq!(Epd::from_str(&epd.to_string()).unwrap(), epd);
+    assert!(epd.check_perft(2).iter().all(|c| c.passed()));
+}
+
+#[test]
+fn test_epd_move_counters() {
+    let epd = Epd::from_str("4k3/8/8/8/8/8/8/4K3 b - - hmvc 12; fmvn 40;").unwrap();
+    assert_eq!(epd.get_board().halfmove_clock(), 12);
+    assert_eq!(epd.get_board().fullmove_number(), 40);
+    assert_eq!(
+        epd.to_string(),
+        "4k3/8/8/8/8/8/8/4K3 b - - hmvc 12; fmvn 40;"
+    );
+}
+
+#[test]
+fn test_invalid_epd() {
+    for text in [
+        "4k3/8/8/8/8/8/8/4K3 w -",
+        "4k3/8/8/8/8/8/8/4K3 w - - bm Ke3;",
+        "4k3/8/8/8/8/8/8/4K3 w - - id \"unterminated;",
+        "4k3/8/8/8/8/8/8/4K3 w - - D1 many;",
+        "4k3/8/8/8/8/8/8/4K3 w - - D0 1;",
+        "4k3/8/8/8/8/8/8/4K3 w - - bm a3a4;",
+        "4k3/8/8/8/8/8/8/4K3 w - - am e1e8;",
+        "4k3/8/8/8/8/8/8/4K3 w - - hmvc;",
+    ]
+    .iter()
+    {
+        assert!(Epd::from_str(text).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn test_perft_suite() {
+    let suite = EpdSuite::from_str(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - ;D1 48 ;D2 2039 ;D3 97862
+8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 14 ;D2 191 ;D3 2812 ;D4 43238
+r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - ;D1 6 ;D2 264 ;D3 9467
+bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - ;D1 21 ;D2 528 ;D3 12189
+4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 67
+",
+    )
+    .unwrap();
+
+    let checks = suite.run_perft(3);
+    assert_eq!(checks.len(), 14);
+
+    let failures: Vec<&PerftCheck> = checks.iter().filter(|c| !c.passed()).collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].get_depth(), 2);
+    assert_eq!(failures[0].get_expected(), 67);
+    assert_eq!(failures[0].get_actual(), 66);
+}
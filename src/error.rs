@@ -32,4 +32,40 @@ pub enum Error {
     /// An attempt was made to convert a string not equal to "a"-"h" to a file
     #[fail(display = "The string specified does not contain a valid file")]
     InvalidFile,
+
+    /// An attempt was made to create a Chess960 starting position from a number not in 0-959
+    #[fail(display = "The index specified is not a valid Chess960 starting position")]
+    InvalidChess960Index,
+
+    /// Reading or writing a file failed
+    #[fail(display = "I/O error: {}", message)]
+    IoFailure { message: String },
+
+    /// The data specified is not a Polyglot opening book
+    #[fail(display = "The data specified is not a valid Polyglot opening book")]
+    InvalidPolyglotBook,
+
+    /// The EPD string is invalid
+    #[fail(display = "Invalid EPD string: {}", epd)]
+    InvalidEpd { epd: String },
+
+    /// An attempt was made to create a PGN comment command from an invalid string
+    #[fail(display = "Invalid PGN command: {}", command)]
+    InvalidCommand { command: String },
+
+    /// An attempt was made to create a time control without periods, or with a period for the
+    /// rest of the game before the last one
+    #[fail(display = "The periods specified do not make a valid time control")]
+    InvalidTimeControl,
+
+    /// The PGN text could not be read
+    #[fail(
+        display = "Invalid PGN at line {}, column {}: {}",
+        line, column, message
+    )]
+    InvalidPgn {
+        line: usize,
+        column: usize,
+        message: String,
+    },
 }
@@ -0,0 +1,385 @@
+use crate::board::{Board, MAX_GAME_PHASE};
+use crate::color::{Color, ALL_COLORS};
+use crate::piece::{Piece, ALL_PIECES, NUM_PIECES};
+use crate::square::NUM_SQUARES;
+
+#[cfg(feature = "search")]
+use crate::search::Evaluator;
+
+// The default tables are PeSTO's, laid out the way a board is printed: from a8 to h1, from
+// White's point of view.
+
+#[rustfmt::skip]
+const MG_PAWN: [i32; NUM_SQUARES] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     98, 134,  61,  95,  68, 126,  34, -11,
+     -6,   7,  26,  31,  65,  56,  25, -20,
+    -14,  13,   6,  21,  23,  12,  17, -23,
+    -27,  -2,  -5,  12,  17,   6,  10, -25,
+    -26,  -4,  -4, -10,   3,   3,  33, -12,
+    -35,  -1, -20, -23, -15,  24,  38, -22,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_PAWN: [i32; NUM_SQUARES] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+    178, 173, 158, 134, 147, 132, 165, 187,
+     94, 100,  85,  67,  56,  53,  82,  84,
+     32,  24,  13,   5,  -2,   4,  17,  17,
+     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+      4,   7,  -6,   1,   0,  -5,  -1,  -8,
+     13,   8,   8,  10,  13,   0,   2,  -7,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const MG_KNIGHT: [i32; NUM_SQUARES] = [
+    -167,  -89,  -34,  -49,   61,  -97,  -15, -107,
+     -73,  -41,   72,   36,   23,   62,    7,  -17,
+     -47,   60,   37,   65,   84,  129,   73,   44,
+      -9,   17,   19,   53,   37,   69,   18,   22,
+     -13,    4,   16,   13,   28,   19,   21,   -8,
+     -23,   -9,   12,   10,   19,   17,   25,  -16,
+     -29,  -53,  -12,   -3,   -1,   18,  -14,  -19,
+    -105,  -21,  -58,  -33,  -17,  -28,  -19,  -23,
+];
+
+#[rustfmt::skip]
+const EG_KNIGHT: [i32; NUM_SQUARES] = [
+    -58, -38, -13, -28, -31, -27, -63, -99,
+    -25,  -8, -25,  -2,  -9, -25, -24, -52,
+    -24, -20,  10,   9,  -1,  -9, -19, -41,
+    -17,   3,  22,  22,  22,  11,   8, -18,
+    -18,  -6,  16,  25,  16,  17,   4, -18,
+    -23,  -3,  -1,  15,  10,  -3, -20, -22,
+    -42, -20, -10,  -5,  -2, -20, -23, -44,
+    -29, -51, -23, -15, -22, -18, -50, -64,
+];
+
+#[rustfmt::skip]
+const MG_BISHOP: [i32; NUM_SQUARES] = [
+    -29,   4, -82, -37, -25, -42,   7,  -8,
+    -26,  16, -18, -13,  30,  59,  18, -47,
+    -16,  37,  43,  40,  35,  50,  37,  -2,
+     -4,   5,  19,  50,  37,  37,   7,  -2,
+     -6,  13,  13,  26,  34,  12,  10,   4,
+      0,  15,  15,  15,  14,  27,  18,  10,
+      4,  15,  16,   0,   7,  21,  33,   1,
+    -33,  -3, -14, -21, -13, -12, -39, -21,
+];
+
+#[rustfmt::skip]
+const EG_BISHOP: [i32; NUM_SQUARES] = [
+    -14, -21, -11,  -8,  -7,  -9, -17, -24,
+     -8,  -4,   7, -12,  -3, -13,  -4, -14,
+      2,  -8,   0,  -1,  -2,   6,   0,   4,
+     -3,   9,  12,   9,  14,  10,   3,   2,
+     -6,   3,  13,  19,   7,  10,  -3,  -9,
+    -12,  -3,   8,  10,  13,   3,  -7, -15,
+    -14, -18,  -7,  -1,   4,  -9, -15, -27,
+    -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+];
+
+#[rustfmt::skip]
+const MG_ROOK: [i32; NUM_SQUARES] = [
+     32,  42,  32,  51,  63,   9,  31,  43,
+     27,  32,  58,  62,  80,  67,  26,  44,
+     -5,  19,  26,  36,  17,  45,  61,  16,
+    -24, -11,   7,  26,  24,  35,  -8, -20,
+    -36, -26, -12,  -1,   9,  -7,   6, -23,
+    -45, -25, -16, -17,   3,   0,  -5, -33,
+    -44, -16, -20,  -9,  -1,  11,  -6, -71,
+    -19, -13,   1,  17,  16,   7, -37, -26,
+];
+
+#[rustfmt::skip]
+const EG_ROOK: [i32; NUM_SQUARES] = [
+     13,  10,  18,  15,  12,  12,   8,   5,
+     11,  13,  13,  11,  -3,   3,   8,   3,
+      7,   7,   7,   5,   4,  -3,  -5,  -3,
+      4,   3,  13,   1,   2,   1,  -1,   2,
+      3,   5,   8,   4,  -5,  -6,  -8, -11,
+     -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+     -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+     -9,   2,   3,  -1,  -5, -13,   4, -20,
+];
+
+#[rustfmt::skip]
+const MG_QUEEN: [i32; NUM_SQUARES] = [
+    -28,   0,  29,  12,  59,  44,  43,  45,
+    -24, -39,  -5,   1, -16,  57,  28,  54,
+    -13, -17,   7,   8,  29,  56,  47,  57,
+    -27, -27, -16, -16,  -1,  17,  -2,   1,
+     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+    -14,   2, -11,  -2,  -5,   2,  14,   5,
+    -35,  -8,  11,   2,   8,  15,  -3,   1,
+     -1, -18,  -9,  10, -15, -25, -31, -50,
+];
+
+#[rustfmt::skip]
+const EG_QUEEN: [i32; NUM_SQUARES] = [
+     -9,  22,  22,  27,  27,  19,  10,  20,
+    -17,  20,  32,  41,  58,  25,  30,   0,
+    -20,   6,   9,  49,  47,  35,  19,   9,
+      3,  22,  24,  45,  57,  40,  57,  36,
+    -18,  28,  19,  47,  31,  34,  39,  23,
+    -16, -27,  15,   6,   9,  17,  10,   5,
+    -22, -23, -30, -16, -16, -23, -36, -32,
+    -33, -28, -22, -43,  -5, -32, -20, -41,
+];
+
+#[rustfmt::skip]
+const MG_KING: [i32; NUM_SQUARES] = [
+    -65,  23,  16, -15, -56, -34,   2,  13,
+     29,  -1, -20,  -7,  -8,  -4, -38, -29,
+     -9,  24,   2, -16, -20,   6,  22, -22,
+    -17, -20, -12, -27, -30, -25, -14, -36,
+    -49,  -1, -27, -39, -46, -44, -33, -51,
+    -14, -14, -22, -46, -44, -30, -15, -27,
+      1,   7,  -8, -64, -43, -16,   9,   8,
+    -15,  36,  12, -54,   8, -28,  24,  14,
+];
+
+#[rustfmt::skip]
+const EG_KING: [i32; NUM_SQUARES] = [
+    -74, -35, -18, -18, -11,  15,   4, -17,
+    -12,  17,  14,  17,  17,  38,  23,  11,
+     10,  17,  23,  15,  20,  45,  44,  13,
+     -8,  22,  24,  27,  26,  33,  26,   3,
+    -18,  -4,  21,  24,  27,  23,   9, -11,
+    -19,  -3,  11,  21,  23,  16,   7,  -9,
+    -27, -11,   4,  13,  14,   4,  -5, -17,
+    -53, -34, -21, -11, -28, -14, -24, -43,
+];
+
+/// The parameters of the built-in evaluation, in centipawns.
+///
+/// Each parameter has a middlegame and an endgame value, which are blended by
+/// `Board::game_phase`.  The piece-square tables are laid out the way a board is printed, from
+/// a8 to h1, from White's point of view, and are mirrored for Black.  The fields can be
+/// overridden one by one, or filled in from tuned values.
+///
+/// ```
+/// use minorhacks_chess::{Board, EvalParams, Piece};
+/// use std::str::FromStr;
+///
+/// let board = Board::from_str("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1").expect("Valid FEN");
+///
+/// let mut params = EvalParams::default();
+/// let before = params.evaluate_white(&board);
+/// params.mg_values[Piece::Knight.to_index()] += 100;
+/// params.eg_values[Piece::Knight.to_index()] += 100;
+/// assert_eq!(params.evaluate_white(&board), before + 100);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalParams {
+    /// The middlegame value of each piece, indexed by `Piece::to_index`.
+    pub mg_values: [i32; NUM_PIECES],
+    /// The endgame value of each piece, indexed by `Piece::to_index`.
+    pub eg_values: [i32; NUM_PIECES],
+    /// The middlegame piece-square table of each piece, indexed by `Piece::to_index`.
+    pub mg_tables: [[i32; NUM_SQUARES]; NUM_PIECES],
+    /// The endgame piece-square table of each piece, indexed by `Piece::to_index`.
+    pub eg_tables: [[i32; NUM_SQUARES]; NUM_PIECES],
+    /// The middlegame bonus for having both bishops.
+    pub mg_bishop_pair: i32,
+    /// The endgame bonus for having both bishops.
+    pub eg_bishop_pair: i32,
+    /// The bonus for being the side to move.
+    pub tempo: i32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            mg_values: [82, 337, 365, 477, 1025, 0],
+            eg_values: [94, 281, 297, 512, 936, 0],
+            mg_tables: [MG_PAWN, MG_KNIGHT, MG_BISHOP, MG_ROOK, MG_QUEEN, MG_KING],
+            eg_tables: [EG_PAWN, EG_KNIGHT, EG_BISHOP, EG_ROOK, EG_QUEEN, EG_KING],
+            mg_bishop_pair: 30,
+            eg_bishop_pair: 50,
+            tempo: 10,
+        }
+    }
+}
+
+impl EvalParams {
+    /// Score `board` in centipawns, from White's point of view.  Mirroring the board and
+    /// swapping the colors negates the score.  See `evaluate_for_side_to_move` for the point of
+    /// view of the side to move, which the search uses.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, EvalParams};
+    /// use std::str::FromStr;
+    ///
+    /// let params = EvalParams::default();
+    ///
+    /// // only the tempo bonus is left in the starting position
+    /// assert_eq!(params.evaluate_white(&Board::default()), params.tempo);
+    ///
+    /// let board = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 b Qkq - 0 1")
+    ///     .expect("Valid FEN");
+    /// assert!(params.evaluate_white(&board) < -400);
+    /// ```
+    pub fn evaluate_white(&self, board: &Board) -> i32 {
+        let mut mg = 0;
+        let mut eg = 0;
+
+        for &color in ALL_COLORS.iter() {
+            let sign = if color == Color::White { 1 } else { -1 };
+            // the tables are printed from a8, so White's squares are the ones that get mirrored
+            let flip = if color == Color::White { 56 } else { 0 };
+            let pieces = board.color_combined(color);
+
+            for &piece in ALL_PIECES.iter() {
+                let index = piece.to_index();
+                for square in board.pieces(piece) & pieces {
+                    let square = square.to_index() ^ flip;
+                    mg += sign * (self.mg_values[index] + self.mg_tables[index][square]);
+                    eg += sign * (self.eg_values[index] + self.eg_tables[index][square]);
+                }
+            }
+
+            if board.piece_count(color, Piece::Bishop) >= 2 {
+                mg += sign * self.mg_bishop_pair;
+                eg += sign * self.eg_bishop_pair;
+            }
+        }
+
+        let phase = board.game_phase() as i32;
+        let max_phase = MAX_GAME_PHASE as i32;
+        let score = (mg * phase + eg * (max_phase - phase)) / max_phase;
+
+        if board.side_to_move() == Color::White {
+            score + self.tempo
+        } else {
+            score - self.tempo
+        }
+    }
+
+    /// Score `board` in centipawns, from the point of view of the side to move.
+    pub fn evaluate_for_side_to_move(&self, board: &Board) -> i32 {
+        if board.side_to_move() == Color::White {
+            self.evaluate_white(board)
+        } else {
+            -self.evaluate_white(board)
+        }
+    }
+}
+
+/// The search scores positions from the point of view of the side to move.
+#[cfg(feature = "search")]
+impl Evaluator for EvalParams {
+    fn evaluate(&self, board: &Board) -> i32 {
+        self.evaluate_for_side_to_move(board)
+    }
+}
+
+#[cfg(test)]
+use std::str::FromStr;
+
+/// Mirror a FEN vertically and swap the colors, so that the position is the same for the other
+/// side.
+#[cfg(test)]
+fn mirror_fen(fen: &str) -> String {
+    let swap_case = |s: &str| {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                }
+            })
+            .collect::<String>()
+    };
+
+    let fields = fen.split(' ').collect::<Vec<_>>();
+    let pieces = fields[0].split('/').rev().collect::<Vec<_>>().join("/");
+    let side = if fields[1] == "w" { "b" } else { "w" };
+    let mut castling = swap_case(fields[2]).chars().collect::<Vec<_>>();
+    castling.sort_unstable();
+    let castling = castling.into_iter().collect::<String>();
+    let ep = match fields[3].as_bytes() {
+        [file, b'3'] => format!("{}6", *file as char),
+        [file, b'6'] => format!("{}3", *file as char),
+        _ => fields[3].to_owned(),
+    };
+
+    format!(
+        "{} {} {} {} {} {}",
+        swap_case(&pieces),
+        side,
+        castling,
+        ep,
+        fields[4],
+        fields[5]
+    )
+}
+
+#[test]
+fn test_eval_symmetry() {
+    assert_eq!(
+        mirror_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 0 1"),
+        "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b Qk - 0 1"
+    );
+
+    // positions where the two sides' pieces, phases and bishops differ
+    let params = EvalParams::default();
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+        "4k3/8/8/8/8/8/8/2B1KB2 b - - 0 1",
+        "2r3k1/5ppp/8/8/3N4/8/5PPP/6K1 w - - 0 1",
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+    ]
+    .iter()
+    {
+        let board = Board::from_str(fen).unwrap();
+        let mirrored = Board::from_str(&mirror_fen(fen)).unwrap();
+        assert_eq!(
+            params.evaluate_white(&mirrored),
+            -params.evaluate_white(&board),
+            "{}",
+            fen
+        );
+        assert_eq!(
+            params.evaluate_for_side_to_move(&mirrored),
+            params.evaluate_for_side_to_move(&board),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn test_eval_terms() {
+    let params = EvalParams::default();
+
+    // with only kings and pawns, the endgame tables are used.  e2 and e1 are printed on the
+    // second-to-last and last rows, and e8 on the last row from Black's point of view.
+    let board = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+    let expected = params.eg_values[Piece::Pawn.to_index()]
+        + params.eg_tables[Piece::Pawn.to_index()][52]
+        + params.eg_tables[Piece::King.to_index()][60]
+        - params.eg_tables[Piece::King.to_index()][60]
+        - params.tempo;
+    assert_eq!(params.evaluate_white(&board), expected);
+    assert_eq!(params.evaluate_for_side_to_move(&board), -expected);
+
+    // the bishop pair
+    let pair = Board::from_str("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+    let mut no_pair = params.clone();
+    no_pair.mg_bishop_pair = 0;
+    no_pair.eg_bishop_pair = 0;
+    let phase = pair.game_phase() as i32;
+    let bonus = (params.mg_bishop_pair * phase
+        + params.eg_bishop_pair * (MAX_GAME_PHASE as i32 - phase))
+        / MAX_GAME_PHASE as i32;
+    assert_eq!(
+        params.evaluate_white(&pair),
+        no_pair.evaluate_white(&pair) + bonus
+    );
+}
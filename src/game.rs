@@ -1,19 +1,28 @@
 use crate::board::{Board, BoardStatus};
 use crate::chess_move::ChessMove;
+use crate::clock::ChessClock;
 use crate::color::Color;
 use crate::error::Error;
+use crate::move_tree::{self, Command, MoveNode};
 use crate::movegen::MoveGen;
+use crate::pgn;
 use crate::piece::Piece;
+use crate::zobrist::Zobrist;
 use std::str::FromStr;
+use std::time::Duration;
 
 /// Contains all actions supported within the game
+///
+/// `MakeMove` records the time the player used for the move, if the game has a clock.
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Eq)]
 pub enum Action {
-    MakeMove(ChessMove),
+    MakeMove(ChessMove, Option<Duration>),
     OfferDraw(Color),
     AcceptDraw,
     DeclareDraw,
     Resign(Color),
+    RequestTakeback(Color),
+    AcceptTakeback,
 }
 
 /// What was the result of this game?
@@ -26,6 +35,40 @@ pub enum GameResult {
     Stalemate,
     DrawAccepted,
     DrawDeclared,
+    InsufficientMaterial,
+    FivefoldRepetition,
+    SeventyFiveMoveRule,
+    WhiteTimeForfeit,
+    BlackTimeForfeit,
+    DrawOnTime,
+}
+
+impl GameResult {
+    /// Which side won the game?  Returns `None` if the game was drawn.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Color, GameResult};
+    ///
+    /// assert_eq!(GameResult::WhiteResigns.winner(), Some(Color::Black));
+    /// assert_eq!(GameResult::Stalemate.winner(), None);
+    /// ```
+    pub fn winner(&self) -> Option<Color> {
+        match *self {
+            GameResult::WhiteCheckmates
+            | GameResult::BlackResigns
+            | GameResult::BlackTimeForfeit => Some(Color::White),
+            GameResult::BlackCheckmates
+            | GameResult::WhiteResigns
+            | GameResult::WhiteTimeForfeit => Some(Color::Black),
+            GameResult::Stalemate
+            | GameResult::DrawAccepted
+            | GameResult::DrawDeclared
+            | GameResult::InsufficientMaterial
+            | GameResult::FivefoldRepetition
+            | GameResult::SeventyFiveMoveRule
+            | GameResult::DrawOnTime => None,
+        }
+    }
 }
 
 /// For UI/UCI Servers, store a game object which allows you to determine
@@ -35,8 +78,12 @@ pub enum GameResult {
 /// not recommended for engines.
 #[derive(Clone, Debug)]
 pub struct Game {
-    start_pos: Board,
     moves: Vec<Action>,
+    tags: Vec<(String, String)>,
+    nodes: Vec<MoveNode>,
+    // the position before each move of the mainline, and the current position
+    positions: Vec<Board>,
+    clock: Option<ChessClock>,
 }
 
 impl Default for Game {
@@ -56,8 +103,11 @@ impl Game {
     /// ```
     pub fn new() -> Game {
         Game {
-            start_pos: Board::default(),
             moves: vec![],
+            tags: vec![],
+            nodes: vec![],
+            positions: vec![Board::default()],
+            clock: None,
         }
     }
 
@@ -71,11 +121,119 @@ impl Game {
     /// ```
     pub fn new_with_board(board: Board) -> Game {
         Game {
-            start_pos: board,
             moves: vec![],
+            tags: vec![],
+            nodes: vec![],
+            positions: vec![board],
+            clock: None,
         }
     }
 
+    /// Get the position the game started from.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Game, Board, MoveGen};
+    ///
+    /// let mut game = Game::new();
+    /// let mut movegen = MoveGen::new_legal(&game.current_position());
+    ///
+    /// game.make_move(movegen.next().expect("At least one valid move"));
+    /// assert_eq!(game.start_position(), Board::default());
+    /// ```
+    pub fn start_position(&self) -> Board {
+        self.positions[0]
+    }
+
+    /// Get the position after `ply` moves of the mainline, where 0 is the starting position.
+    /// Returns `None` if fewer moves have been made.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Game, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    ///
+    /// assert_eq!(game.position_at(0), Some(Board::default()));
+    /// assert_eq!(game.position_at(1), Some(Board::default().make_move_new(e4)));
+    /// assert_eq!(game.position_at(2), None);
+    /// ```
+    pub fn position_at(&self, ply: usize) -> Option<Board> {
+        self.positions.get(ply).copied()
+    }
+
+    /// Iterate over the moves of the mainline, along with the position each one was played in.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Game, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    /// let e5 = ChessMove::new(Square::E7, Square::E5, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    /// game.make_move(e5);
+    ///
+    /// let history = game.history().collect::<Vec<(Board, ChessMove)>>();
+    /// assert_eq!(history, vec![
+    ///     (Board::default(), e4),
+    ///     (Board::default().make_move_new(e4), e5),
+    /// ]);
+    /// ```
+    pub fn history(&self) -> impl Iterator<Item = (Board, ChessMove)> + '_ {
+        self.positions
+            .iter()
+            .zip(self.nodes.iter())
+            .map(|(board, node)| (*board, node.get_move()))
+    }
+
+    /// How many times has the current position occurred in the mainline, including now?
+    ///
+    /// Positions are the same when the same pieces are on the same squares, with the same side
+    /// to move, the same castle rights and the same en passant captures available.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Game, Square};
+    ///
+    /// let mut game = Game::new();
+    /// assert_eq!(game.repetitions(), 1);
+    ///
+    /// for &(source, dest) in [
+    ///     (Square::G1, Square::F3),
+    ///     (Square::G8, Square::F6),
+    ///     (Square::F3, Square::G1),
+    ///     (Square::F6, Square::G8),
+    /// ].iter() {
+    ///     game.make_move(ChessMove::new(source, dest, None));
+    /// }
+    /// assert_eq!(game.repetitions(), 2);
+    /// ```
+    pub fn repetitions(&self) -> usize {
+        self.repetitions_at(self.nodes.len()).unwrap_or(1)
+    }
+
+    /// How many times had the position after `ply` moves of the mainline occurred, including
+    /// then?  Returns `None` if fewer moves have been made.
+    pub fn repetitions_at(&self, ply: usize) -> Option<usize> {
+        Some(self.count_repetitions(self.positions.get(ply)?, ply))
+    }
+
+    /// How many times has `board`, the position after `ply` moves, occurred by then?  `board`
+    /// may also be the position after a move that hasn't been made yet.
+    fn count_repetitions(&self, board: &Board, ply: usize) -> usize {
+        let key = repetition_key(board);
+
+        // positions can't repeat across a capture or pawn move, and the same side must be to move
+        let reversible = (board.halfmove_clock() as usize).min(ply);
+        1 + (ply - reversible..ply)
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .filter(|&i| repetition_key(&self.positions[i]) == key)
+            .count()
+    }
+
     /// Get all actions made in this game (moves, draw offers, resignations, etc.)
     ///
     /// ```
@@ -92,16 +250,340 @@ impl Game {
         &self.moves
     }
 
-    /// What is the status of this game?
+    /// Get the tags (such as "Event" or "White") of this game, in the order they were added.
     ///
     /// ```
     /// use minorhacks_chess::Game;
     ///
+    /// let mut game = Game::new();
+    /// game.set_tag("Event", "Casual game");
+    /// game.set_tag("White", "Alice");
+    ///
+    /// assert_eq!(game.tags().len(), 2);
+    /// assert_eq!(game.tags()[1], ("White".to_owned(), "Alice".to_owned()));
+    /// ```
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Get the value of a tag, if it is set.
+    ///
+    /// ```
+    /// use minorhacks_chess::Game;
+    ///
+    /// let mut game = Game::new();
+    /// game.set_tag("Event", "Casual game");
+    ///
+    /// assert_eq!(game.tag("Event"), Some("Casual game"));
+    /// assert_eq!(game.tag("Site"), None);
+    /// ```
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the value of a tag, replacing any previous value.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_owned(),
+            None => self.tags.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    /// Get the moves of the mainline, along with their annotations and variations.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Color, Game, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    /// game.offer_draw(Color::White);
+    ///
+    /// assert_eq!(game.actions().len(), 2);
+    /// assert_eq!(game.mainline().len(), 1);
+    /// assert_eq!(game.mainline()[0].get_move(), e4);
+    /// ```
+    pub fn mainline(&self) -> &[MoveNode] {
+        &self.nodes
+    }
+
+    /// Get the move at `path` in the tree of moves.
+    ///
+    /// The first element of `path` is the index of a move in the mainline, so `&[0]` is the first
+    /// move of the game.  To go into a variation, add the index of the variation of that move,
+    /// and then the index of a move in the variation, and so on.  So `&[4, 1, 0]` is the first
+    /// move of the second variation of the fifth move of the game.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Game, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    /// let d4 = ChessMove::new(Square::D2, Square::D4, None);
+    /// let c4 = ChessMove::new(Square::C2, Square::C4, None);
+    /// let d5 = ChessMove::new(Square::D7, Square::D5, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    /// assert!(game.add_variation(&[0], &[d4, d5]));
+    /// assert!(game.add_variation(&[0, 0, 0], &[c4]));
+    ///
+    /// assert_eq!(game.node(&[0]).unwrap().get_move(), e4);
+    /// assert_eq!(game.node(&[0, 0, 1]).unwrap().get_move(), d5);
+    /// assert_eq!(game.node(&[0, 0, 0, 0, 0]).unwrap().get_move(), c4);
+    /// assert!(game.node(&[1]).is_none());
+    /// ```
+    pub fn node(&self, path: &[usize]) -> Option<&MoveNode> {
+        move_tree::find_node(&self.nodes, path)
+    }
+
+    /// Get the move at `path` in the tree of moves, to edit its annotations.  See `Game::node`
+    /// for the format of `path`.
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut MoveNode> {
+        move_tree::find_node_mut(&mut self.nodes, path)
+    }
+
+    /// Get the position before the move at `path` in the tree of moves.  See `Game::node` for
+    /// the format of `path`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Game, Square};
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+    ///
+    /// assert_eq!(game.position_before(&[0]), Some(Board::default()));
+    /// assert_eq!(game.position_before(&[1]), None);
+    /// ```
+    pub fn position_before(&self, path: &[usize]) -> Option<Board> {
+        // positions in the mainline are already known
+        let mut index = *path.first()?;
+        self.nodes.get(index)?;
+        let mut board = self.positions[index];
+        let mut line = &self.nodes[..];
+
+        let mut path = &path[1..];
+        while let Some(&variation) = path.first() {
+            line = line[index].variations().get(variation)?;
+            index = *path.get(1)?;
+            for node in line.get(..index + 1)?[..index].iter() {
+                board = board.make_move_new(node.get_move());
+            }
+            path = &path[2..];
+        }
+        Some(board)
+    }
+
+    /// Add a variation to the move at `path`: a line of moves that could have been played
+    /// instead of it.  See `Game::node` for the format of `path`.
+    ///
+    /// Returns false, and does nothing, if there is no move at `path`, or the moves are not legal.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Game, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    /// let e5 = ChessMove::new(Square::E7, Square::E5, None);
+    /// let d4 = ChessMove::new(Square::D2, Square::D4, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    /// game.make_move(e5);
+    ///
+    /// assert!(game.add_variation(&[1], &[ChessMove::new(Square::C7, Square::C5, None)]));
+    /// assert!(!game.add_variation(&[1], &[d4]));
+    /// assert!(!game.add_variation(&[1], &[]));
+    /// assert_eq!(game.node(&[1]).unwrap().variations().len(), 1);
+    /// ```
+    pub fn add_variation(&mut self, path: &[usize], moves: &[ChessMove]) -> bool {
+        let mut board = match self.position_before(path) {
+            Some(board) => board,
+            None => return false,
+        };
+        if moves.is_empty() {
+            return false;
+        }
+
+        let mut variation = vec![];
+        for &chess_move in moves.iter() {
+            if !board.legal(chess_move) {
+                return false;
+            }
+            board = board.make_move_new(chess_move);
+            variation.push(MoveNode::new(chess_move));
+        }
+
+        match self.node_mut(path) {
+            Some(node) => {
+                node.push_variation(variation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove variation `index` of the move at `path`.  See `Game::node` for the format of
+    /// `path`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Game, Square};
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+    /// game.add_variation(&[0], &[ChessMove::new(Square::D2, Square::D4, None)]);
+    ///
+    /// assert!(!game.delete_variation(&[0], 1));
+    /// assert!(game.delete_variation(&[0], 0));
+    /// assert!(game.node(&[0]).unwrap().variations().is_empty());
+    /// ```
+    pub fn delete_variation(&mut self, path: &[usize], index: usize) -> bool {
+        match self.node_mut(path) {
+            Some(node) if index < node.variations().len() => {
+                node.variations_mut().remove(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Make variation `index` of the move at `path` the main line, in place of the moves from
+    /// `path` onwards.  Those moves become variation `index` instead, so promoting it again
+    /// switches them back.  See `Game::node` for the format of `path`.
+    ///
+    /// When the variation replaces moves of the game itself, the actions in between those moves
+    /// are removed along with them.  The draw offers, draws and resignations after the last move
+    /// are kept, if they can still be made after the new last move.  The moves keep the time
+    /// used for them, and if the game has a clock, it is started for the new side to move, or
+    /// stopped if the game is over.  The time used for the replaced moves is not given back,
+    /// though.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Color, Game, GameResult, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    /// let e5 = ChessMove::new(Square::E7, Square::E5, None);
+    /// let d4 = ChessMove::new(Square::D2, Square::D4, None);
+    /// let d5 = ChessMove::new(Square::D7, Square::D5, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    /// game.make_move(e5);
+    /// game.resign(Color::Black);
+    /// game.add_variation(&[0], &[d4, d5]);
+    ///
+    /// assert!(game.promote_variation(&[0], 0));
+    /// assert_eq!(game.mainline()[0].get_move(), d4);
+    /// assert_eq!(game.mainline()[1].get_move(), d5);
+    /// assert_eq!(game.node(&[0, 0, 1]).unwrap().get_move(), e5);
+    /// assert_eq!(game.actions().len(), 3);
+    /// assert_eq!(game.result(), Some(GameResult::BlackResigns));
+    /// ```
+    pub fn promote_variation(&mut self, path: &[usize], index: usize) -> bool {
+        match self.node(path) {
+            Some(node) if index < node.variations().len() => {}
+            _ => return false,
+        }
+
+        let ply = path[path.len() - 1];
+        if path.len() > 1 {
+            if let Some(line) = move_tree::find_line_mut(&mut self.nodes, path) {
+                move_tree::promote_variation(line, ply, index);
+            }
+            return true;
+        }
+
+        // the actions of the game change from this move onwards, except for the draw offers,
+        // draws and resignations after the last move, which are made again if they still can be
+        let mainline_actions = self.mainline_actions();
+        let last_action = mainline_actions[mainline_actions.len() - 1];
+        let final_actions = self.moves[last_action + 1..]
+            .iter()
+            .filter(|action| match action {
+                Action::OfferDraw(_)
+                | Action::AcceptDraw
+                | Action::DeclareDraw
+                | Action::Resign(_) => true,
+                Action::MakeMove(..) | Action::RequestTakeback(_) | Action::AcceptTakeback => false,
+            })
+            .copied()
+            .collect::<Vec<Action>>();
+        self.moves.truncate(mainline_actions[ply]);
+
+        move_tree::promote_variation(&mut self.nodes, ply, index);
+        self.positions.truncate(ply + 1);
+        for node in self.nodes[ply..].iter() {
+            let board = self.positions[self.positions.len() - 1].make_move_new(node.get_move());
+            self.moves
+                .push(Action::MakeMove(node.get_move(), node.time_used()));
+            self.positions.push(board);
+        }
+
+        for action in final_actions {
+            match action {
+                Action::OfferDraw(color) => self.offer_draw(color),
+                Action::AcceptDraw => self.accept_draw(),
+                Action::DeclareDraw => self.declare_draw(),
+                Action::Resign(color) => self.resign(color),
+                _ => false,
+            };
+        }
+        self.update_clock();
+        true
+    }
+
+    /// Convert this game to PGN.  See `PgnWriter` for details.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Game, Square};
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(ChessMove::new(Square::G1, Square::F3, None));
+    ///
+    /// assert!(game.to_pgn().ends_with("\n\n1. Nf3 *\n"));
+    /// ```
+    pub fn to_pgn(&self) -> String {
+        pgn::game_to_pgn(self)
+    }
+
+    /// What is the status of this game?
+    ///
+    /// Besides checkmate and stalemate, the game is drawn automatically, as the FIDE Laws of
+    /// Chess require, when neither side has enough material left to checkmate, when the same
+    /// position occurs for the fifth time, or after 75 moves by each side without a capture or
+    /// pawn move.  Checkmate on the last of those 75 moves still counts.
+    ///
+    /// If the game has a clock, a player who runs out of time loses, unless their opponent can't
+    /// possibly checkmate them, in which case the game is drawn.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Game, GameResult, Square};
+    /// use std::str::FromStr;
+    ///
     /// let game = Game::new();
     /// assert!(game.result().is_none());
+    ///
+    /// let mut game = Game::from_str("4k3/8/8/8/8/8/8/3rK3 w - - 0 1").expect("Valid FEN");
+    /// game.make_move(ChessMove::new(Square::E1, Square::D1, None));
+    /// assert_eq!(game.result(), Some(GameResult::InsufficientMaterial));
+    ///
+    /// let game = Game::from_str("4k3/8/8/8/8/8/8/3RK3 w - - 150 100").expect("Valid FEN");
+    /// assert_eq!(game.result(), Some(GameResult::SeventyFiveMoveRule));
     /// ```
     pub fn result(&self) -> Option<GameResult> {
-        match self.current_position().status() {
+        let board = self.current_position();
+        let status = board.status();
+        if status == BoardStatus::Ongoing {
+            if board.halfmove_clock() >= 150 {
+                return Some(GameResult::SeventyFiveMoveRule);
+            } else if self.repetitions() >= 5 {
+                return Some(GameResult::FivefoldRepetition);
+            }
+        }
+
+        match status {
             BoardStatus::Checkmate => {
                 if self.side_to_move() == Color::White {
                     Some(GameResult::BlackCheckmates)
@@ -110,9 +592,10 @@ impl Game {
                 }
             }
             BoardStatus::Stalemate => Some(GameResult::Stalemate),
+            BoardStatus::InsufficientMaterial => Some(GameResult::InsufficientMaterial),
             BoardStatus::Ongoing => {
                 if self.moves.is_empty() {
-                    None
+                    self.time_forfeit()
                 } else if self.moves[self.moves.len() - 1] == Action::AcceptDraw {
                     Some(GameResult::DrawAccepted)
                 } else if self.moves[self.moves.len() - 1] == Action::DeclareDraw {
@@ -122,7 +605,7 @@ impl Game {
                 } else if self.moves[self.moves.len() - 1] == Action::Resign(Color::Black) {
                     Some(GameResult::BlackResigns)
                 } else {
-                    None
+                    self.time_forfeit()
                 }
             }
         }
@@ -162,15 +645,7 @@ impl Game {
     /// assert_eq!(game.current_position(), Board::default());
     /// ```
     pub fn current_position(&self) -> Board {
-        let mut copy = self.start_pos;
-
-        for x in self.moves.iter() {
-            if let Action::MakeMove(m) = *x {
-                copy = copy.make_move_new(m);
-            }
-        }
-
-        copy
+        self.positions[self.positions.len() - 1]
     }
 
     /// Determine if a player can legally declare a draw by 3-fold repetition or 50-move rule.
@@ -205,54 +680,7 @@ impl Game {
             return false;
         }
 
-        let mut legal_moves_per_turn: Vec<(u64, Vec<ChessMove>)> = vec![];
-
-        let mut board = self.start_pos;
-        let mut reversible_moves = 0;
-
-        // Loop over each move, counting the reversible_moves for draw by 50 move rule,
-        // and filling a list of legal_moves_per_turn list for 3-fold repitition
-        legal_moves_per_turn.push((board.get_hash(), MoveGen::new_legal(&board).collect()));
-        for x in self.moves.iter() {
-            if let Action::MakeMove(m) = *x {
-                let white_castle_rights = board.castle_rights(Color::White);
-                let black_castle_rights = board.castle_rights(Color::Black);
-                if board.piece_on(m.get_source()) == Some(Piece::Pawn)
-                    || board.piece_on(m.get_dest()).is_some()
-                {
-                    reversible_moves = 0;
-                    legal_moves_per_turn.clear();
-                } else {
-                    reversible_moves += 1;
-                }
-                board = board.make_move_new(m);
-
-                if board.castle_rights(Color::White) != white_castle_rights
-                    || board.castle_rights(Color::Black) != black_castle_rights
-                {
-                    reversible_moves = 0;
-                    legal_moves_per_turn.clear();
-                }
-                legal_moves_per_turn.push((board.get_hash(), MoveGen::new_legal(&board).collect()));
-            }
-        }
-
-        if reversible_moves >= 100 {
-            return true;
-        }
-
-        // Detect possible draw by 3 fold repitition
-        let last_moves = legal_moves_per_turn[legal_moves_per_turn.len() - 1].clone();
-
-        for i in 1..(legal_moves_per_turn.len() - 1) {
-            for j in 0..i {
-                if legal_moves_per_turn[i] == last_moves && legal_moves_per_turn[j] == last_moves {
-                    return true;
-                }
-            }
-        }
-
-        false
+        self.current_position().halfmove_clock() >= 100 || self.repetitions() >= 3
     }
 
     /// Declare a draw by 3-fold repitition or 50-move rule.
@@ -286,6 +714,94 @@ impl Game {
     pub fn declare_draw(&mut self) -> bool {
         if self.can_declare_draw() {
             self.moves.push(Action::DeclareDraw);
+            self.update_clock();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Determine if a player can declare a draw by 3-fold repetition or 50-move rule together
+    /// with the move they intend to make, as article 9.2 of the FIDE Laws of Chess allows.  This
+    /// is the case if the draw could be declared now, or after `chess_move`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Game, Square, ChessMove};
+    ///
+    /// let b1c3 = ChessMove::new(Square::B1, Square::C3, None);
+    /// let c3b1 = ChessMove::new(Square::C3, Square::B1, None);
+    ///
+    /// let b8c6 = ChessMove::new(Square::B8, Square::C6, None);
+    /// let c6b8 = ChessMove::new(Square::C6, Square::B8, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(b1c3);
+    /// game.make_move(b8c6);
+    /// game.make_move(c3b1);
+    /// game.make_move(c6b8);
+    /// game.make_move(b1c3);
+    /// game.make_move(b8c6);
+    /// game.make_move(c3b1);
+    ///
+    /// // the position after c6b8 would be the third repetition of the starting position
+    /// assert!(!game.can_declare_draw());
+    /// assert!(game.can_declare_draw_with_move(c6b8));
+    /// assert!(!game.can_declare_draw_with_move(ChessMove::new(Square::C6, Square::E5, None)));
+    /// ```
+    pub fn can_declare_draw_with_move(&self, chess_move: ChessMove) -> bool {
+        if self.can_declare_draw() {
+            return true;
+        }
+
+        let board = self.current_position();
+        if self.result().is_some() || !board.legal(chess_move) {
+            return false;
+        }
+
+        let after = board.make_move_new(chess_move);
+        after.halfmove_clock() >= 100 || self.count_repetitions(&after, self.positions.len()) >= 3
+    }
+
+    /// Declare a draw by 3-fold repetition or 50-move rule together with the move the player
+    /// intends to make, as article 9.2 of the FIDE Laws of Chess allows.  If the claim is
+    /// correct, the move is made, and the game is drawn.  If the draw can already be declared
+    /// in the current position, it is declared without making the move.
+    ///
+    /// If the claim is not correct, nothing happens.  Article 9.5 then requires the player to
+    /// make the move they wrote down anyway, with `Game::make_move`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Action, Game, GameResult, Square, ChessMove};
+    ///
+    /// let b1c3 = ChessMove::new(Square::B1, Square::C3, None);
+    /// let c3b1 = ChessMove::new(Square::C3, Square::B1, None);
+    ///
+    /// let b8c6 = ChessMove::new(Square::B8, Square::C6, None);
+    /// let c6b8 = ChessMove::new(Square::C6, Square::B8, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(b1c3);
+    /// game.make_move(b8c6);
+    /// game.make_move(c3b1);
+    /// game.make_move(c6b8);
+    /// game.make_move(b1c3);
+    /// game.make_move(b8c6);
+    /// game.make_move(c3b1);
+    ///
+    /// assert!(!game.declare_draw_with_move(ChessMove::new(Square::C6, Square::E5, None)));
+    /// assert!(game.declare_draw_with_move(c6b8));
+    /// assert_eq!(game.result(), Some(GameResult::DrawDeclared));
+    /// assert_eq!(game.actions()[game.actions().len() - 2], Action::MakeMove(c6b8, None));
+    /// ```
+    pub fn declare_draw_with_move(&mut self, chess_move: ChessMove) -> bool {
+        if self.can_declare_draw() {
+            return self.declare_draw();
+        }
+
+        if self.can_declare_draw_with_move(chess_move) {
+            self.make_move(chess_move);
+            self.moves.push(Action::DeclareDraw);
+            self.update_clock();
             true
         } else {
             false
@@ -294,6 +810,10 @@ impl Game {
 
     /// Make a chess move on the board
     ///
+    /// If the game has a clock, it is pressed: the time used is recorded with the move, the
+    /// clock time left is added to the move as a `Command::Clock`, and the opponent's clock
+    /// starts.  Once the game is over, the clock stops.
+    ///
     /// ```
     /// use minorhacks_chess::{Game, MoveGen};
     ///
@@ -308,13 +828,98 @@ impl Game {
             return false;
         }
         if self.current_position().legal(chess_move) {
-            self.moves.push(Action::MakeMove(chess_move));
+            let board = self.current_position().make_move_new(chess_move);
+            let mut node = MoveNode::new(chess_move);
+            let time_used = match self.clock {
+                Some(ref mut clock) => {
+                    let time_used = clock.press();
+                    let remaining = clock.remaining(!board.side_to_move());
+                    node.commands_mut().push(Command::Clock(remaining));
+                    node.set_time_used(time_used);
+                    time_used
+                }
+                None => None,
+            };
+            self.moves.push(Action::MakeMove(chess_move, time_used));
+            self.nodes.push(node);
+            self.positions.push(board);
+            self.update_clock();
             true
         } else {
             false
         }
     }
 
+    /// Make a move, keeping the annotations and variations of `node`.  The variations must
+    /// already have been checked for legality.
+    ///
+    /// Unlike `make_move`, this doesn't stop at automatic draws, so that recorded games played
+    /// on past after one (or under older rules) are read in full.
+    pub(crate) fn make_move_node(&mut self, node: MoveNode) -> bool {
+        let board = self.current_position();
+        let over = match board.status() {
+            BoardStatus::Checkmate | BoardStatus::Stalemate => true,
+            BoardStatus::Ongoing | BoardStatus::InsufficientMaterial => false,
+        };
+        if !over && board.legal(node.get_move()) {
+            self.moves.push(Action::MakeMove(node.get_move(), None));
+            self.positions.push(board.make_move_new(node.get_move()));
+            self.nodes.push(node);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Play this game with `clock`.  Unless the clock is already running, or the game is over,
+    /// the clock of the side to move is started.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessClock, ChessMove, Color, Game, GameResult};
+    /// use minorhacks_chess::{ManualTimeSource, Square, TimeControl};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let time = Arc::new(ManualTimeSource::new());
+    /// let control = TimeControl::sudden_death(Duration::from_secs(60));
+    /// let mut game = Game::new();
+    /// game.set_clock(ChessClock::with_time_source(control, time.clone()));
+    ///
+    /// time.advance(Duration::from_secs(5));
+    /// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+    /// assert_eq!(game.time_used(0), Some(Duration::from_secs(5)));
+    ///
+    /// time.advance(Duration::from_secs(61));
+    /// assert_eq!(game.result(), Some(GameResult::BlackTimeForfeit));
+    /// assert!(!game.make_move(ChessMove::new(Square::E7, Square::E5, None)));
+    /// ```
+    pub fn set_clock(&mut self, mut clock: ChessClock) {
+        if clock.running().is_none() {
+            clock.start(self.side_to_move());
+        }
+        self.clock = Some(clock);
+        self.update_clock();
+    }
+
+    /// Get the clock of this game, if it has one.
+    pub fn clock(&self) -> Option<&ChessClock> {
+        self.clock.as_ref()
+    }
+
+    /// Get the clock of this game mutably, for example to pause it.
+    pub fn clock_mut(&mut self) -> Option<&mut ChessClock> {
+        self.clock.as_mut()
+    }
+
+    /// How much time was used for the move after `ply` moves of the mainline?  Returns `None`
+    /// if there is no such move, or it wasn't made on the clock.
+    pub fn time_used(&self, ply: usize) -> Option<Duration> {
+        match self.moves[*self.mainline_actions().get(ply)?] {
+            Action::MakeMove(_, time_used) => time_used,
+            _ => None,
+        }
+    }
+
     /// Who's turn is it to move?
     ///
     /// ```
@@ -324,21 +929,15 @@ impl Game {
     /// assert_eq!(game.side_to_move(), Color::White);
     /// ```
     pub fn side_to_move(&self) -> Color {
-        let move_count = self
-            .moves
-            .iter()
-            .filter(|m| matches!(*m, Action::MakeMove(_)))
-            .count()
-            + if self.start_pos.side_to_move() == Color::White {
-                0
-            } else {
-                1
-            };
+        self.side_to_move_after(self.nodes.len())
+    }
 
-        if move_count % 2 == 0 {
-            Color::White
+    /// Who's turn is it after `plies` moves of the mainline?
+    fn side_to_move_after(&self, plies: usize) -> Color {
+        if plies % 2 == 1 {
+            !self.start_position().side_to_move()
         } else {
-            Color::Black
+            self.start_position().side_to_move()
         }
     }
 
@@ -383,6 +982,7 @@ impl Game {
                 || self.moves[self.moves.len() - 1] == Action::OfferDraw(Color::Black))
         {
             self.moves.push(Action::AcceptDraw);
+            self.update_clock();
             return true;
         }
 
@@ -390,6 +990,7 @@ impl Game {
             && self.moves[self.moves.len() - 2] == Action::OfferDraw(!self.side_to_move())
         {
             self.moves.push(Action::AcceptDraw);
+            self.update_clock();
             return true;
         }
 
@@ -409,8 +1010,198 @@ impl Game {
             return false;
         }
         self.moves.push(Action::Resign(color));
+        self.update_clock();
+        true
+    }
+
+    /// Take back the last move, as if it had never been played.  This is meant for analysis; to
+    /// take back a move during play, see `Game::request_takeback`.
+    ///
+    /// Everything that happened after the move is removed along with it, so any draw offer,
+    /// resignation or other result after it is cleared.  The move's annotations and variations
+    /// are removed, too.  Returns the move, or `None` if there are no moves to take back.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Color, Game, Square};
+    ///
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    /// let e5 = ChessMove::new(Square::E7, Square::E5, None);
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(e4);
+    /// game.make_move(e5);
+    /// game.resign(Color::White);
+    ///
+    /// assert_eq!(game.undo_move(), Some(e5));
+    /// assert_eq!(game.result(), None);
+    /// assert_eq!(game.side_to_move(), Color::Black);
+    /// assert_eq!(game.actions().len(), 1);
+    ///
+    /// assert_eq!(game.undo_move(), Some(e4));
+    /// assert_eq!(game.undo_move(), None);
+    /// ```
+    pub fn undo_move(&mut self) -> Option<ChessMove> {
+        let last_action = *self.mainline_actions().last()?;
+        self.moves.truncate(last_action);
+        self.positions.pop();
+        let chess_move = self.nodes.pop().map(|node| node.get_move());
+        self.update_clock();
+        chess_move
+    }
+
+    /// `color` asks to take back their last move.  The request must be accepted, with
+    /// `Game::accept_takeback`, before anything else happens in the game.
+    ///
+    /// A player can only ask to take back a move they have made, and not after the game has been
+    /// resigned or drawn by agreement or claim.  It is possible after checkmate or stalemate,
+    /// though.  Only one takeback can be requested at a time.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, Color, Game, Square};
+    ///
+    /// let mut game = Game::new();
+    /// assert!(!game.request_takeback(Color::White));
+    ///
+    /// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+    /// assert!(!game.request_takeback(Color::Black));
+    /// assert!(game.request_takeback(Color::White));
+    /// assert!(!game.request_takeback(Color::White));
+    /// ```
+    pub fn request_takeback(&mut self, color: Color) -> bool {
+        match self.result() {
+            None
+            | Some(GameResult::WhiteCheckmates)
+            | Some(GameResult::BlackCheckmates)
+            | Some(GameResult::Stalemate)
+            | Some(GameResult::InsufficientMaterial)
+            | Some(GameResult::FivefoldRepetition)
+            | Some(GameResult::SeventyFiveMoveRule) => {}
+            Some(_) => return false,
+        }
+
+        if let Some(Action::RequestTakeback(_)) = self.moves.last() {
+            return false;
+        }
+
+        if self.takeback_plies(self.nodes.len(), color) > self.nodes.len() {
+            return false;
+        }
+
+        self.moves.push(Action::RequestTakeback(color));
+        true
+    }
+
+    /// Accept a takeback request from my opponent.  This takes back the last move of the player
+    /// who asked, along with my reply if I have already made one, so that it is their turn again.
+    ///
+    /// Unlike `Game::undo_move`, the moves taken back stay in `Game::actions`, followed by the
+    /// request and `Action::AcceptTakeback`.  Any draw offer made before the request is cleared.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Color, Game, Square};
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+    /// game.make_move(ChessMove::new(Square::E7, Square::E5, None));
+    /// assert!(!game.accept_takeback());
+    ///
+    /// game.offer_draw(Color::Black);
+    /// game.request_takeback(Color::White);
+    /// assert!(game.accept_takeback());
+    ///
+    /// assert_eq!(game.current_position(), Board::default());
+    /// assert_eq!(game.side_to_move(), Color::White);
+    /// assert_eq!(game.actions().len(), 5);
+    /// assert!(!game.accept_draw());
+    /// ```
+    pub fn accept_takeback(&mut self) -> bool {
+        let color = match self.moves.last() {
+            Some(&Action::RequestTakeback(color)) => color,
+            _ => return false,
+        };
+
+        let plies = self.takeback_plies(self.nodes.len(), color);
+        self.nodes.truncate(self.nodes.len() - plies);
+        self.positions.truncate(self.nodes.len() + 1);
+        self.moves.push(Action::AcceptTakeback);
+        self.update_clock();
         true
     }
+
+    /// How many moves have to be taken back, after `plies` moves, until `color` can play their
+    /// last move again?
+    fn takeback_plies(&self, plies: usize, color: Color) -> usize {
+        if self.side_to_move_after(plies) == color {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// The result if a player has run out of time.
+    fn time_forfeit(&self) -> Option<GameResult> {
+        let flagged = self.clock.as_ref()?.flagged()?;
+        Some(
+            if self.current_position().has_insufficient_material(!flagged) {
+                GameResult::DrawOnTime
+            } else if flagged == Color::White {
+                GameResult::WhiteTimeForfeit
+            } else {
+                GameResult::BlackTimeForfeit
+            },
+        )
+    }
+
+    /// Stop the clock if the game is over, or make sure it is running for the side to move.
+    fn update_clock(&mut self) {
+        let over = self.result().is_some();
+        let side_to_move = self.side_to_move();
+        if let Some(ref mut clock) = self.clock {
+            if over {
+                clock.stop();
+            } else if clock.running().is_some() && clock.running() != Some(side_to_move) {
+                clock.start(side_to_move);
+            }
+        }
+    }
+
+    /// Get the index in `self.moves` of each move of the mainline, skipping moves that were taken
+    /// back.
+    fn mainline_actions(&self) -> Vec<usize> {
+        let mut result = vec![];
+        for (index, action) in self.moves.iter().enumerate() {
+            match *action {
+                Action::MakeMove(..) => result.push(index),
+                Action::AcceptTakeback => {
+                    if let Action::RequestTakeback(color) = self.moves[index - 1] {
+                        let plies = self.takeback_plies(result.len(), color);
+                        result.truncate(result.len() - plies);
+                    }
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+/// A hash of `board` for finding repeated positions.  Unlike `Board::get_hash`, it ignores an en
+/// passant square if the capture isn't legal.
+fn repetition_key(board: &Board) -> u64 {
+    match board.en_passant() {
+        Some(ep) => {
+            let dest = ep.uforward(board.side_to_move());
+            let can_capture = MoveGen::new_legal(board).any(|m| {
+                m.get_dest() == dest && board.piece_on(m.get_source()) == Some(Piece::Pawn)
+            });
+            if can_capture {
+                board.get_hash()
+            } else {
+                board.get_hash() ^ Zobrist::en_passant(ep.get_file(), !board.side_to_move())
+            }
+        }
+        None => board.get_hash(),
+    }
 }
 
 impl FromStr for Game {
@@ -469,3 +1260,323 @@ pub fn test_can_declare_draw() {
     let game = fake_pgn_parser("1. d4 Nf6 2. c4 g6 3. Nc3 Bg7 4. e4 d6 5. Nf3 O-O 6. Be2 e5 7. O-O Nc6 8. d5 Ne7 9. Nd2 a5 10. Rb1 Nd7 11. a3 f5 12. b4 Kh8 13. f3 Ng8 14. Qc2 Ngf6 15. Nb5 axb4 16. axb4 Nh5 17. g3 Ndf6 18. c5 Bd7 19. Rb3 Nxg3 20. hxg3 Nh5 21. f4 exf4 22. c6 bxc6 23. dxc6 Nxg3 24. Rxg3 fxg3 25. cxd7 g2 26. Rf3 Qxd7 27. Bb2 fxe4 28. Rxf8+ Rxf8 29. Bxg7+ Qxg7 30. Qxe4 Qf6 31. Nf3 Qf4 32. Qe7 Rf7 33. Qe6 Rf6 34. Qe8+ Rf8 35. Qe7 Rf7 36. Qe6 Rf6 37. Qb3 g5 38. Nxc7 g4 39. Nd5 Qc1+ 40. Qd1 Qxd1+ 41. Bxd1 Rf5 42. Ne3 Rf4 43. Ne1 Rxb4 44. Bxg4 h5 45. Bf3 d5 46. N3xg2 h4 47. Nd3 Ra4 48. Ngf4 Kg7 49. Kg2 Kf6 50. Bxd5 Ra5 51. Bc6 Ra6 52. Bb7 Ra3 53. Be4 Ra4 54. Bd5 Ra5 55. Bc6 Ra6 56. Bf3 Kg5 57. Bb7 Ra1 58. Bc8 Ra4 59. Kf3 Rc4 60. Bd7 Kf6 61. Kg4 Rd4 62. Bc6 Rd8 63. Kxh4 Rg8 64. Be4 Rg1 65. Nh5+ Ke6 66. Ng3 Kf6 67. Kg4 Ra1 68. Bd5 Ra5 69. Bf3 Ra1 70. Kf4 Ke6 71. Nc5+ Kd6 72. Nge4+ Ke7 73. Ke5 Rf1 74. Bg4 Rg1 75. Be6 Re1 76. Bc8 Rc1 77. Kd4 Rd1+ 78. Nd3 Kf7 79. Ke3 Ra1 80. Kf4 Ke7 81. Nb4 Rc1 82. Nd5+ Kf7 83. Bd7 Rf1+ 84. Ke5 Ra1 85. Ng5+ Kg6 86. Nf3 Kg7 87. Bg4 Kg6 88. Nf4+ Kg7 89. Nd4 Re1+ 90. Kf5 Rc1 91. Be2 Re1 92. Bh5 Ra1 93. Nfe6+ Kh6 94. Be8 Ra8 95. Bc6 Ra1 96. Kf6 Kh7 97. Ng5+ Kh8 98. Nde6 Ra6 99. Be8 Ra8 100. Bh5 Ra1 101. Bg6 Rf1+ 102. Ke7 Ra1 103. Nf7+ Kg8 104. Nh6+ Kh8 105. Nf5 Ra7+ 106. Kf6 Ra1 107. Ne3 Re1 108. Nd5 Rg1 109. Bf5 Rf1 110. Ndf4 Ra1 111. Ng6+ Kg8 112. Ne7+ Kh8");
     assert!(!game.can_declare_draw());
 }
+
+#[test]
+fn test_promote_and_delete_variations() {
+    let mut game = fake_pgn_parser("1. e4 e5 2. Nf3 Nc6");
+    let moves = |s: &str| -> Vec<ChessMove> {
+        s.split_whitespace()
+            .map(|m| ChessMove::from_str(m).unwrap())
+            .collect()
+    };
+
+    assert!(game.add_variation(&[2], &moves("f2f4 e5f4 g1f3")));
+    assert!(game.add_variation(&[2, 0, 1], &moves("d7d5")));
+    assert!(game.add_variation(&[2, 0, 1], &moves("f8c5")));
+    assert!(!game.add_variation(&[2, 0, 1], &moves("g1f3")));
+    assert!(!game.add_variation(&[2, 1, 0], &moves("f2f4")));
+    let original = game.clone();
+
+    // promote inside a variation, and back again
+    assert!(game.promote_variation(&[2, 0, 1], 1));
+    assert_eq!(
+        game.node(&[2]).unwrap().variations()[0]
+            .iter()
+            .map(|n| n.get_move())
+            .collect::<Vec<_>>(),
+        moves("f2f4 f8c5")
+    );
+    assert_eq!(
+        game.node(&[2, 0, 1, 1, 1]).unwrap().get_move(),
+        ChessMove::from_str("g1f3").unwrap()
+    );
+    assert!(game.promote_variation(&[2, 0, 1], 1));
+    assert_eq!(game.mainline(), original.mainline());
+
+    // promote to the mainline
+    assert!(game.promote_variation(&[2], 0));
+    assert_eq!(
+        game.current_position(),
+        fake_pgn_parser("1. e4 e5 2. f4 exf4 3. Nf3").current_position()
+    );
+    assert_eq!(
+        game.node(&[2, 0, 1]).unwrap().get_move().to_string(),
+        "b8c6"
+    );
+    assert_eq!(game.node(&[3]).unwrap().variations().len(), 2);
+    assert!(!game.promote_variation(&[2], 1));
+
+    // a resignation is kept, but not a draw declared in a position that was replaced
+    let mut resigned = fake_pgn_parser("1. e4 e5");
+    resigned.add_variation(&[1], &moves("c7c5"));
+    resigned.offer_draw(Color::White);
+    resigned.resign(Color::Black);
+    assert!(resigned.promote_variation(&[1], 0));
+    assert_eq!(
+        &resigned.actions()[2..],
+        &[
+            Action::OfferDraw(Color::White),
+            Action::Resign(Color::Black)
+        ]
+    );
+    let mut drawn = fake_pgn_parser("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8");
+    drawn.add_variation(&[7], &moves("b8c6"));
+    assert!(drawn.declare_draw());
+    assert!(drawn.promote_variation(&[7], 0));
+    assert_eq!(drawn.result(), None);
+    assert_eq!(drawn.actions().len(), 8);
+
+    assert!(game.delete_variation(&[3], 0));
+    assert_eq!(
+        game.node(&[3]).unwrap().variations()[0][0]
+            .get_move()
+            .to_string(),
+        "f8c5"
+    );
+    assert!(game.delete_variation(&[2], 0));
+    assert!(game.node(&[2]).unwrap().variations().is_empty());
+    assert!(!game.delete_variation(&[2], 0));
+}
+
+#[test]
+fn test_takebacks() {
+    // fool's mate, then the mated side takes back their blunder and White's reply
+    let mut game = fake_pgn_parser("1. f3 e5 2. g4 Qh4#");
+    assert_eq!(game.result(), Some(GameResult::BlackCheckmates));
+    assert!(game.request_takeback(Color::White));
+    assert!(game.accept_takeback());
+    assert_eq!(game.result(), None);
+    assert_eq!(
+        game.current_position(),
+        fake_pgn_parser("1. f3 e5").current_position()
+    );
+    assert_eq!(game.mainline().len(), 2);
+
+    // the taken back moves are skipped when undoing
+    assert!(game.make_move(ChessMove::from_str("d2d4").unwrap()));
+    assert_eq!(game.undo_move(), Some(ChessMove::from_str("d2d4").unwrap()));
+    assert_eq!(game.undo_move(), Some(ChessMove::from_str("e7e5").unwrap()));
+    assert_eq!(game.actions().len(), 1);
+
+    // a request lapses as soon as anything else happens
+    let mut game = fake_pgn_parser("1. e4");
+    assert!(game.request_takeback(Color::White));
+    assert!(game.make_move(ChessMove::from_str("e7e5").unwrap()));
+    assert!(!game.accept_takeback());
+
+    // no takebacks after a resignation or an agreed draw
+    game.resign(Color::Black);
+    assert!(!game.request_takeback(Color::White));
+    let mut game = fake_pgn_parser("1. e4");
+    game.offer_draw(Color::White);
+    game.accept_draw();
+    assert!(!game.request_takeback(Color::White));
+
+    // black can't take back a move from before the game started
+    let mut game = Game::from_str("4k3/8/8/8/8/8/P7/4K3 b - - 0 1").unwrap();
+    assert!(game.make_move(ChessMove::from_str("e8d8").unwrap()));
+    assert!(game.make_move(ChessMove::from_str("e1d1").unwrap()));
+    assert!(game.request_takeback(Color::Black));
+    assert!(game.accept_takeback());
+    assert_eq!(game.current_position(), game.start_position());
+    assert!(!game.request_takeback(Color::White));
+}
+
+#[test]
+fn test_history_and_repetitions() {
+    let mut game = fake_pgn_parser("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6");
+    game.add_variation(&[4], &[ChessMove::from_str("f1c4").unwrap()]);
+    game.promote_variation(&[4], 0);
+    game.make_move(ChessMove::from_str("f8c5").unwrap());
+    game.undo_move();
+    game.make_move(ChessMove::from_str("g8f6").unwrap());
+    game.request_takeback(Color::Black);
+    game.accept_takeback();
+    game.make_move(ChessMove::from_str("f8e7").unwrap());
+
+    // the stored positions match replaying the mainline
+    let mut board = game.start_position();
+    for (ply, (position, chess_move)) in game.history().enumerate() {
+        assert_eq!(position, board);
+        assert_eq!(game.position_at(ply), Some(board));
+        board = board.make_move_new(chess_move);
+    }
+    assert_eq!(board, game.current_position());
+    assert_eq!(
+        board,
+        fake_pgn_parser("1. e4 e5 2. Nf3 Nc6 3. Bc4 Be7").current_position()
+    );
+
+    // the b5 pawn is pinned, so a7-a5 doesn't give white an en passant capture
+    let mut game = Game::from_str("1r5k/p7/8/1P6/8/8/8/1K6 b - - 0 1").unwrap();
+    for m in ["a7a5", "b1c1", "h8h7", "c1b1", "h7h8"].iter() {
+        assert!(game.make_move(ChessMove::from_str(m).unwrap()));
+    }
+    assert!(game.current_position().en_passant().is_none());
+    assert!(game.position_at(1).unwrap().en_passant().is_some());
+    assert_eq!(game.repetitions(), 2);
+    assert_eq!(game.repetitions_at(1), Some(1));
+    assert_eq!(game.repetitions_at(3), Some(1));
+    assert_eq!(game.repetitions_at(6), None);
+}
+
+#[test]
+fn test_automatic_draws() {
+    let game = Game::from_str("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap();
+    assert_eq!(game.result(), Some(GameResult::InsufficientMaterial));
+    let game = Game::from_str("8/8/4k3/8/8/3K4/5N2/8 w - - 0 1").unwrap();
+    assert_eq!(game.result(), Some(GameResult::InsufficientMaterial));
+    let game = Game::from_str("8/2b5/4k3/8/8/3K4/5B2/8 w - - 0 1").unwrap();
+    assert_eq!(game.result(), Some(GameResult::InsufficientMaterial));
+
+    // bishops on opposite colors or two knights can still mate with help
+    let game = Game::from_str("8/3b4/4k3/8/8/3K4/5B2/8 w - - 0 1").unwrap();
+    assert!(game.result().is_none());
+    let game = Game::from_str("8/8/4k3/8/8/3K4/5N2/6N1 w - - 0 1").unwrap();
+    assert!(game.result().is_none());
+    let game = Game::from_str("8/8/4k3/8/8/3K4/5N2/6b1 w - - 0 1").unwrap();
+    assert!(game.result().is_none());
+    let game = Game::from_str("8/8/4k3/8/8/3K4/5P2/8 w - - 0 1").unwrap();
+    assert!(game.result().is_none());
+
+    // checkmate on the 75th move counts
+    let mut game = Game::from_str("7k/8/6K1/8/8/8/8/R7 w - - 149 100").unwrap();
+    assert!(game.result().is_none());
+    assert!(game.make_move(ChessMove::from_str("a1a8").unwrap()));
+    assert_eq!(game.result(), Some(GameResult::WhiteCheckmates));
+    let mut game = Game::from_str("7k/8/6K1/8/8/8/8/R7 w - - 149 100").unwrap();
+    assert!(game.make_move(ChessMove::from_str("a1a7").unwrap()));
+    assert_eq!(game.result(), Some(GameResult::SeventyFiveMoveRule));
+    assert!(!game.make_move(ChessMove::from_str("h8g8").unwrap()));
+
+    // nobody claims the draw, so the game goes on until the fifth repetition
+    let mut game = Game::new();
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    for (i, m) in shuffle.iter().cycle().take(16).enumerate() {
+        assert!(game.result().is_none());
+        assert_eq!(game.repetitions(), i / 4 + 1);
+        assert!(game.make_move(ChessMove::from_str(m).unwrap()));
+    }
+    assert_eq!(game.repetitions(), 5);
+    assert_eq!(game.result(), Some(GameResult::FivefoldRepetition));
+    assert_eq!(game.result().unwrap().winner(), None);
+    assert!(!game.declare_draw());
+}
+
+#[test]
+fn test_declare_draw_with_move() {
+    // the 100th half-move without a capture or pawn move
+    let mut game = Game::from_str("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+    assert!(!game.can_declare_draw());
+    assert!(game.can_declare_draw_with_move(ChessMove::from_str("a1a2").unwrap()));
+    assert!(!game.can_declare_draw_with_move(ChessMove::from_str("e2e4").unwrap()));
+    assert!(!game.can_declare_draw_with_move(ChessMove::from_str("a1h1").unwrap()));
+    assert!(!game.declare_draw_with_move(ChessMove::from_str("e2e4").unwrap()));
+    assert_eq!(game.actions().len(), 0);
+    assert!(game.declare_draw_with_move(ChessMove::from_str("a1a2").unwrap()));
+    assert_eq!(game.result(), Some(GameResult::DrawDeclared));
+    assert_eq!(game.current_position().halfmove_clock(), 100);
+    assert!(!game.declare_draw_with_move(ChessMove::from_str("e8e7").unwrap()));
+
+    // a claimable position is drawn straight away, without making the move
+    let mut game = fake_pgn_parser("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8");
+    assert!(game.can_declare_draw());
+    assert!(game.declare_draw_with_move(ChessMove::from_str("e2e4").unwrap()));
+    assert_eq!(game.mainline().len(), 8);
+    assert_eq!(game.result(), Some(GameResult::DrawDeclared));
+}
+
+#[test]
+fn test_clock() {
+    use crate::clock::{ManualTimeSource, TimeControl};
+    use std::sync::Arc;
+
+    let time = Arc::new(ManualTimeSource::new());
+    let clock = |secs| {
+        let control = TimeControl::fischer(Duration::from_secs(secs), Duration::from_secs(1));
+        ChessClock::with_time_source(control, time.clone())
+    };
+
+    let mut game = Game::new();
+    game.set_clock(clock(60));
+    for (m, secs) in [("e2e4", 10), ("e7e5", 20), ("g1f3", 5)].iter() {
+        time.advance(Duration::from_secs(*secs));
+        assert!(game.make_move(ChessMove::from_str(m).unwrap()));
+    }
+    assert_eq!(game.time_used(1), Some(Duration::from_secs(20)));
+    assert_eq!(game.time_used(3), None);
+    assert_eq!(
+        game.mainline()[2].commands(),
+        &[Command::Clock(Duration::from_secs(47))]
+    );
+
+    // taking back white's move gives the turn, and the clock, back to white
+    game.request_takeback(Color::White);
+    game.accept_takeback();
+    assert_eq!(game.clock().unwrap().running(), Some(Color::White));
+    time.advance(Duration::from_secs(47));
+    assert_eq!(game.result(), Some(GameResult::WhiteTimeForfeit));
+    assert_eq!(game.result().unwrap().winner(), Some(Color::Black));
+    assert!(!game.request_takeback(Color::Black));
+
+    // the clock stops when the game ends
+    let mut game = fake_pgn_parser("1. f3 e5 2. g4");
+    game.set_clock(clock(60));
+    assert!(game.make_move(ChessMove::from_str("d8h4").unwrap()));
+    assert_eq!(game.clock().unwrap().running(), None);
+    time.advance(Duration::from_secs(100));
+    assert_eq!(game.result(), Some(GameResult::BlackCheckmates));
+    let mut game = Game::new();
+    game.set_clock(clock(60));
+    game.resign(Color::Black);
+    time.advance(Duration::from_secs(100));
+    assert_eq!(game.result(), Some(GameResult::BlackResigns));
+
+    // promoting a variation keeps the time used for each move, and the clock runs for the side
+    // to move in the new mainline
+    let mut game = Game::new();
+    game.set_clock(clock(60));
+    for (m, secs) in [("e2e4", 3), ("e7e5", 4)].iter() {
+        time.advance(Duration::from_secs(*secs));
+        assert!(game.make_move(ChessMove::from_str(m).unwrap()));
+    }
+    assert!(game.add_variation(&[0], &[ChessMove::from_str("d2d4").unwrap()]));
+    assert!(game.promote_variation(&[0], 0));
+    assert_eq!(game.time_used(0), None);
+    assert_eq!(game.clock().unwrap().running(), Some(Color::Black));
+    assert_eq!(
+        game.node(&[0, 0, 1]).unwrap().time_used(),
+        Some(Duration::from_secs(4))
+    );
+    assert!(game.promote_variation(&[0], 0));
+    assert_eq!(game.time_used(0), Some(Duration::from_secs(3)));
+    assert_eq!(game.time_used(1), Some(Duration::from_secs(4)));
+    assert_eq!(game.clock().unwrap().running(), Some(Color::White));
+
+    // a lone king can't win on time
+    for fen in [
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/3RP3/4K3 w - - 0 1",
+    ]
+    .iter()
+    {
+        let mut game = Game::from_str(fen).unwrap();
+        game.set_clock(clock(60));
+        time.advance(Duration::from_secs(60));
+        assert_eq!(game.result(), Some(GameResult::DrawOnTime));
+        assert_eq!(game.result().unwrap().winner(), None);
+    }
+    // but a knight or bishop can, if the opponent has something to help mate themselves with
+    for fen in [
+        "4kn2/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4kb2/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4kb2/8/8/8/8/8/8/3BK3 w - - 0 1",
+    ]
+    .iter()
+    {
+        let mut game = Game::from_str(fen).unwrap();
+        game.set_clock(clock(60));
+        time.advance(Duration::from_secs(60));
+        assert_eq!(game.result(), Some(GameResult::WhiteTimeForfeit));
+    }
+}
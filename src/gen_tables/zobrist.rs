@@ -56,4 +56,24 @@ pub fn write_zobrist(f: &mut File) {
         }
     }
     write!(f, "]];\n\n").unwrap();
+
+    writeln!(
+        f,
+        "const ZOBRIST_MATERIAL: [[[u64; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS] = [[["
+    )
+    .unwrap();
+    for i in 0..NUM_COLORS {
+        for j in 0..NUM_PIECES {
+            for _ in 0..NUM_SQUARES {
+                writeln!(f, "    {},", rng.next_u64()).unwrap();
+            }
+            if j != NUM_PIECES - 1 {
+                writeln!(f, "   ], [").unwrap();
+            }
+        }
+        if i != NUM_COLORS - 1 {
+            writeln!(f, "  ]], [[").unwrap();
+        }
+    }
+    write!(f, "]]];\n\n").unwrap();
 }
@@ -62,13 +62,43 @@ mod square;
 pub use crate::square::*;
 
 mod movegen;
-pub use crate::movegen::MoveGen;
+pub use crate::movegen::{
+    mvv_lva, MoveGen, MovePicker, ScoredMove, ScoredMoveList, MAX_KILLERS, MAX_MOVES,
+};
+
+mod perft;
+pub use crate::perft::{perft, perft_divide, perft_hashed, perft_parallel, PerftStats};
+
+mod eval;
+pub use crate::eval::EvalParams;
+
+#[cfg(feature = "search")]
+mod search;
+#[cfg(feature = "search")]
+pub use crate::search::{Evaluator, Search, SearchResult, MATE_SCORE, MAX_PLY};
+
+mod epd;
+pub use crate::epd::{Epd, EpdScore, EpdSuite, PerftCheck};
+
+mod pgn;
+pub use crate::pgn::{PgnReader, PgnWriter};
+
+mod polyglot;
+pub use crate::polyglot::{PolyglotBook, PolyglotBookBuilder, PolyglotEntry};
 
 mod zobrist;
 
+mod clock;
+pub use crate::clock::{
+    ChessClock, Increment, ManualTimeSource, SystemTimeSource, TimeControl, TimePeriod, TimeSource,
+};
+
 mod game;
 pub use crate::game::{Action, Game, GameResult};
 
+mod move_tree;
+pub use crate::move_tree::{Command, Evaluation, MarkColor, MoveNode};
+
 mod board_builder;
 pub use crate::board_builder::BoardBuilder;
 
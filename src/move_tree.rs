@@ -0,0 +1,525 @@
+use crate::chess_move::ChessMove;
+use crate::error::Error;
+use crate::square::Square;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A move in a `Game`, along with its comments, NAGs and commands, and the variations that were
+/// considered instead of it.
+///
+/// The moves of the game itself are the *mainline*.  Each variation is a list of moves that could
+/// have been played instead of the move it is attached to, and may in turn have variations of its
+/// own.
+///
+/// ```
+/// use minorhacks_chess::{ChessMove, Game, Square};
+///
+/// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+/// let d4 = ChessMove::new(Square::D2, Square::D4, None);
+/// let d5 = ChessMove::new(Square::D7, Square::D5, None);
+///
+/// let mut game = Game::new();
+/// game.make_move(e4);
+/// game.add_variation(&[0], &[d4, d5]);
+/// game.node_mut(&[0]).unwrap().nags_mut().push(1);
+///
+/// let node = game.node(&[0]).unwrap();
+/// assert_eq!(node.get_move(), e4);
+/// assert_eq!(node.nags(), &[1]);
+/// assert_eq!(node.variations()[0][1].get_move(), d5);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MoveNode {
+    chess_move: ChessMove,
+    comments_before: Vec<String>,
+    comments_after: Vec<String>,
+    nags: Vec<u8>,
+    commands: Vec<Command>,
+    variations: Vec<Vec<MoveNode>>,
+    time_used: Option<Duration>,
+}
+
+impl MoveNode {
+    /// Create a node for a move, without any annotations or variations.
+    pub(crate) fn new(chess_move: ChessMove) -> MoveNode {
+        MoveNode {
+            chess_move,
+            comments_before: vec![],
+            comments_after: vec![],
+            nags: vec![],
+            commands: vec![],
+            variations: vec![],
+            time_used: None,
+        }
+    }
+
+    /// Get the move.
+    pub fn get_move(&self) -> ChessMove {
+        self.chess_move
+    }
+
+    /// Get the comments that come before the move.
+    pub fn comments_before(&self) -> &[String] {
+        &self.comments_before
+    }
+
+    /// Get the comments that come after the move.
+    pub fn comments_after(&self) -> &[String] {
+        &self.comments_after
+    }
+
+    /// Get the Numeric Annotation Glyphs of the move, such as 1 for a good move (`$1` or `!` in
+    /// PGN).
+    pub fn nags(&self) -> &[u8] {
+        &self.nags
+    }
+
+    /// Get the commands (such as `[%clk 0:03:00]`) in the comments after the move.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Get the variations that could have been played instead of this move.  The first move of
+    /// each variation is played from the same position as this move.
+    pub fn variations(&self) -> &[Vec<MoveNode>] {
+        &self.variations
+    }
+
+    /// Get the time used for the move, if it was made on the clock of a `Game`.  Unlike
+    /// `Game::time_used`, this also works for moves that are no longer in the mainline.
+    pub fn time_used(&self) -> Option<Duration> {
+        self.time_used
+    }
+
+    /// Edit the comments that come before the move.
+    pub fn comments_before_mut(&mut self) -> &mut Vec<String> {
+        &mut self.comments_before
+    }
+
+    /// Edit the comments that come after the move.
+    pub fn comments_after_mut(&mut self) -> &mut Vec<String> {
+        &mut self.comments_after
+    }
+
+    /// Edit the Numeric Annotation Glyphs of the move.
+    pub fn nags_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.nags
+    }
+
+    /// Edit the commands of the move.
+    pub fn commands_mut(&mut self) -> &mut Vec<Command> {
+        &mut self.commands
+    }
+
+    /// Add a variation, which must already have been checked for legality.
+    pub(crate) fn push_variation(&mut self, variation: Vec<MoveNode>) {
+        self.variations.push(variation);
+    }
+
+    pub(crate) fn variations_mut(&mut self) -> &mut Vec<Vec<MoveNode>> {
+        &mut self.variations
+    }
+
+    pub(crate) fn set_time_used(&mut self, time_used: Option<Duration>) {
+        self.time_used = time_used;
+    }
+}
+
+/// Find the node at `path` in a line of moves.  See `Game::node` for the format of `path`.
+pub(crate) fn find_node<'a>(line: &'a [MoveNode], path: &[usize]) -> Option<&'a MoveNode> {
+    let node = line.get(*path.first()?)?;
+    match path.get(1) {
+        None => Some(node),
+        Some(&variation) => find_node(node.variations.get(variation)?, path.get(2..)?),
+    }
+}
+
+/// Find the node at `path` in a line of moves, for editing.
+pub(crate) fn find_node_mut<'a>(
+    line: &'a mut [MoveNode],
+    path: &[usize],
+) -> Option<&'a mut MoveNode> {
+    let node = line.get_mut(*path.first()?)?;
+    match path.get(1) {
+        None => Some(node),
+        Some(&variation) => find_node_mut(node.variations.get_mut(variation)?, path.get(2..)?),
+    }
+}
+
+/// Find the line of moves that contains the node at `path`, for editing.
+pub(crate) fn find_line_mut<'a>(
+    line: &'a mut Vec<MoveNode>,
+    path: &[usize],
+) -> Option<&'a mut Vec<MoveNode>> {
+    if path.len() == 1 {
+        return Some(line);
+    }
+    let variation = line.get_mut(path[0])?.variations.get_mut(*path.get(1)?)?;
+    find_line_mut(variation, path.get(2..)?)
+}
+
+/// Swap the moves of `line` from `index` onwards with variation `variation` of the move at
+/// `index`.  The old moves become that variation instead, so promoting the same variation again
+/// undoes it.
+pub(crate) fn promote_variation(line: &mut Vec<MoveNode>, index: usize, variation: usize) {
+    let mut variations = std::mem::take(&mut line[index].variations);
+    let mut promoted = variations.remove(variation);
+    let demoted = line.split_off(index);
+    variations.insert(variation, demoted);
+
+    // any alternatives to the promoted move are alternatives to the new mainline move, too
+    variations.append(&mut promoted[0].variations);
+    promoted[0].variations = variations;
+    line.append(&mut promoted);
+}
+
+/// The color of an arrow or square highlight, drawn by a `Command`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum MarkColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl MarkColor {
+    fn from_char(c: char) -> Option<MarkColor> {
+        match c {
+            'R' => Some(MarkColor::Red),
+            'G' => Some(MarkColor::Green),
+            'B' => Some(MarkColor::Blue),
+            'Y' => Some(MarkColor::Yellow),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            MarkColor::Red => 'R',
+            MarkColor::Green => 'G',
+            MarkColor::Blue => 'B',
+            MarkColor::Yellow => 'Y',
+        }
+    }
+}
+
+/// An engine evaluation of a position, from White's point of view.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Evaluation {
+    /// An advantage in hundredths of a pawn, such as 25 for `[%eval 0.25]`
+    Centipawns(i32),
+    /// Mate in this many moves, or by the opponent if negative, such as -3 for `[%eval #-3]`
+    Mate(i32),
+}
+
+/// A command embedded in a PGN comment, such as `[%clk 0:03:00]`.
+///
+/// ```
+/// use minorhacks_chess::{Command, Evaluation, MarkColor, Square};
+/// use std::str::FromStr;
+/// use std::time::Duration;
+///
+/// assert_eq!(
+///     Command::from_str("[%clk 1:02:03.5]").unwrap(),
+///     Command::Clock(Duration::from_millis(3_723_500))
+/// );
+/// assert_eq!(
+///     Command::from_str("[%eval -1.05]").unwrap(),
+///     Command::Eval(Evaluation::Centipawns(-105))
+/// );
+/// assert_eq!(
+///     Command::from_str("[%cal Ge2e4,Rd8h4]").unwrap(),
+///     Command::Arrows(vec![
+///         (MarkColor::Green, Square::E2, Square::E4),
+///         (MarkColor::Red, Square::D8, Square::H4),
+///     ])
+/// );
+/// assert_eq!(Command::Eval(Evaluation::Mate(-3)).to_string(), "[%eval #-3]");
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Command {
+    /// `[%clk]`: the time left on the clock of the side that just moved
+    Clock(Duration),
+    /// `[%eval]`: an engine evaluation of the position after the move
+    Eval(Evaluation),
+    /// `[%cal]`: arrows drawn on the board, from one square to another
+    Arrows(Vec<(MarkColor, Square, Square)>),
+    /// `[%csl]`: highlighted squares
+    Squares(Vec<(MarkColor, Square)>),
+    /// Any other command, as its name and the text after it
+    Other(String, String),
+}
+
+/// Parse a clock time such as `1:02:03` or `0:00:09.5`.
+fn parse_clock(value: &str) -> Option<Duration> {
+    let (time, fraction) = match value.find('.') {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    };
+
+    let mut seconds: u64 = 0;
+    let parts = time.split(':').collect::<Vec<&str>>();
+    if parts.len() > 3 {
+        return None;
+    }
+    for part in parts {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        seconds = seconds
+            .checked_mul(60)?
+            .checked_add(part.parse::<u64>().ok()?)?;
+    }
+
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut millis = 0;
+    for (i, c) in fraction.chars().take(3).enumerate() {
+        millis += (c as u64 - '0' as u64) * [100, 10, 1][i];
+    }
+
+    Duration::from_secs(seconds).checked_add(Duration::from_millis(millis))
+}
+
+/// Parse an evaluation such as `0.25`, `-1.5` or `#-3`.
+fn parse_eval(value: &str) -> Option<Evaluation> {
+    if let Some(mate) = value.strip_prefix('#') {
+        return mate.parse::<i32>().ok().map(Evaluation::Mate);
+    }
+
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (pawns, fraction) = match value.find('.') {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    };
+    if pawns.is_empty() && fraction.is_empty()
+        || !pawns
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut centipawns = if pawns.is_empty() {
+        0
+    } else {
+        pawns.parse::<i32>().ok()?.checked_mul(100)?
+    };
+    for (i, c) in fraction.chars().take(2).enumerate() {
+        centipawns = centipawns.checked_add((c as i32 - '0' as i32) * [10, 1][i])?;
+    }
+
+    Some(Evaluation::Centipawns(if negative {
+        -centipawns
+    } else {
+        centipawns
+    }))
+}
+
+fn parse_arrows(value: &str) -> Option<Vec<(MarkColor, Square, Square)>> {
+    value
+        .split(',')
+        .map(|arrow| {
+            let mut chars = arrow.trim().chars();
+            let color = MarkColor::from_char(chars.next()?)?;
+            let squares = chars.as_str();
+            if squares.len() != 4 {
+                return None;
+            }
+            Some((
+                color,
+                Square::from_str(squares.get(..2)?).ok()?,
+                Square::from_str(squares.get(2..)?).ok()?,
+            ))
+        })
+        .collect()
+}
+
+fn parse_squares(value: &str) -> Option<Vec<(MarkColor, Square)>> {
+    value
+        .split(',')
+        .map(|square| {
+            let mut chars = square.trim().chars();
+            let color = MarkColor::from_char(chars.next()?)?;
+            Some((color, Square::from_str(chars.as_str()).ok()?))
+        })
+        .collect()
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidCommand {
+            command: s.to_owned(),
+        };
+
+        let body = s
+            .trim()
+            .strip_prefix("[%")
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or_else(invalid)?;
+        let (name, value) = match body.find(char::is_whitespace) {
+            Some(index) => (&body[..index], body[index..].trim()),
+            None => (body, ""),
+        };
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        let command = match name {
+            "clk" => parse_clock(value).map(Command::Clock),
+            "eval" => parse_eval(value).map(Command::Eval),
+            "cal" => parse_arrows(value).map(Command::Arrows),
+            "csl" => parse_squares(value).map(Command::Squares),
+            _ => None,
+        };
+
+        // commands that can't be understood are kept as they are
+        Ok(command.unwrap_or_else(|| Command::Other(name.to_owned(), value.to_owned())))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Clock(time) => {
+                let seconds = time.as_secs();
+                write!(
+                    f,
+                    "[%clk {}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )?;
+                let millis = time.subsec_millis();
+                if millis > 0 {
+                    let fraction = format!("{:03}", millis);
+                    write!(f, ".{}", fraction.trim_end_matches('0'))?;
+                }
+                write!(f, "]")
+            }
+            Command::Eval(Evaluation::Centipawns(centipawns)) => {
+                let sign = if *centipawns < 0 { "-" } else { "" };
+                let centipawns = centipawns.unsigned_abs();
+                write!(
+                    f,
+                    "[%eval {}{}.{:02}]",
+                    sign,
+                    centipawns / 100,
+                    centipawns % 100
+                )
+            }
+            Command::Eval(Evaluation::Mate(moves)) => write!(f, "[%eval #{}]", moves),
+            Command::Arrows(arrows) => {
+                let arrows = arrows
+                    .iter()
+                    .map(|(color, from, to)| format!("{}{}{}", color.to_char(), from, to))
+                    .collect::<Vec<String>>();
+                write!(f, "[%cal {}]", arrows.join(","))
+            }
+            Command::Squares(squares) => {
+                let squares = squares
+                    .iter()
+                    .map(|(color, square)| format!("{}{}", color.to_char(), square))
+                    .collect::<Vec<String>>();
+                write!(f, "[%csl {}]", squares.join(","))
+            }
+            Command::Other(name, value) if value.is_empty() => write!(f, "[%{}]", name),
+            Command::Other(name, value) => write!(f, "[%{} {}]", name, value),
+        }
+    }
+}
+
+/// Split the text of a PGN comment into the commands in it and the text around them.
+pub(crate) fn split_commands(comment: &str) -> (String, Vec<Command>) {
+    let mut text = String::new();
+    let mut commands = vec![];
+    let mut rest = comment;
+
+    while let Some(start) = rest.find("[%") {
+        let end = match rest[start..].find(']') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        match Command::from_str(&rest[start..end]) {
+            Ok(command) => {
+                text.push_str(&rest[..start]);
+                commands.push(command);
+            }
+            Err(_) => text.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+
+    (
+        text.split_whitespace().collect::<Vec<&str>>().join(" "),
+        commands,
+    )
+}
+
+#[test]
+fn test_commands_round_trip() {
+    for text in [
+        "[%clk 0:03:00]",
+        "[%clk 10:00:09.25]",
+        "[%eval 0.00]",
+        "[%eval 0.07]",
+        "[%eval -12.50]",
+        "[%eval #4]",
+        "[%eval #-1]",
+        "[%cal Ge2e4,Rd8h4,Bb1c3,Ya1a8]",
+        "[%csl Rd4]",
+        "[%emt 0:00:05]",
+        "[%eval 0.17,20]",
+    ]
+    .iter()
+    {
+        assert_eq!(Command::from_str(text).unwrap().to_string(), *text);
+    }
+
+    assert_eq!(
+        Command::from_str("[%eval .5]").unwrap(),
+        Command::Eval(Evaluation::Centipawns(50))
+    );
+    assert_eq!(
+        Command::from_str("[%emt 0:00:05]").unwrap(),
+        Command::Other("emt".to_owned(), "0:00:05".to_owned())
+    );
+    assert!(Command::from_str("[clk 0:03:00]").is_err());
+    assert!(Command::from_str("[%]").is_err());
+    assert_eq!(
+        Command::from_str("[%eval 21474836.48]").unwrap(),
+        Command::Other("eval".to_owned(), "21474836.48".to_owned())
+    );
+    assert_eq!(
+        Command::from_str("[%clk 1000000000000000000:00]").unwrap(),
+        Command::Other("clk".to_owned(), "1000000000000000000:00".to_owned())
+    );
+}
+
+#[test]
+fn test_split_commands() {
+    assert_eq!(
+        split_commands("Good move [%eval 0.50]  [%csl Gd5] here"),
+        (
+            "Good move here".to_owned(),
+            vec![
+                Command::Eval(Evaluation::Centipawns(50)),
+                Command::Squares(vec![(MarkColor::Green, Square::D5)]),
+            ]
+        )
+    );
+    assert_eq!(
+        split_commands("no [%commands here"),
+        ("no [%commands here".to_owned(), vec![])
+    );
+}
@@ -4,3 +4,8 @@ pub use self::movegen::*;
 
 mod piece_type;
 pub use self::piece_type::*;
+
+mod pseudo_legals;
+
+mod scored_moves;
+pub use self::scored_moves::*;
@@ -1,9 +1,13 @@
 use crate::bitboard::{BitBoard, EMPTY};
 use crate::board::Board;
 use crate::chess_move::ChessMove;
-use crate::magic::between;
+use crate::magic::{
+    between, get_bishop_moves, get_bishop_rays, get_knight_moves, get_pawn_attacks, get_rank,
+    get_rook_moves, get_rook_rays,
+};
 use crate::movegen::piece_type::*;
-use crate::piece::{Piece, NUM_PROMOTION_PIECES, PROMOTION_PIECES};
+use crate::movegen::pseudo_legals::pseudo_legal_moves;
+use crate::piece::{Piece, NUM_PIECES, NUM_PROMOTION_PIECES, PROMOTION_PIECES};
 use crate::square::Square;
 use arrayvec::ArrayVec;
 use nodrop::NoDrop;
@@ -93,42 +97,287 @@ pub struct MoveGen {
 impl MoveGen {
     #[inline(always)]
     fn enumerate_moves(board: &Board) -> MoveList {
-        let checkers = *board.checkers();
         let mask = !board.color_combined(board.side_to_move());
+        MoveGen::enumerate_masked(board, &[mask; NUM_PIECES], !EMPTY)
+    }
+
+    /// Enumerate the legal moves of the pieces on `sources`, where each type of piece may only
+    /// land on its mask.  Castling is not restricted by the king's mask, and en passant is only
+    /// generated if the pawns' mask contains the destination square.
+    #[inline(always)]
+    fn enumerate_masked(
+        board: &Board,
+        masks: &[BitBoard; NUM_PIECES],
+        sources: BitBoard,
+    ) -> MoveList {
+        let checkers = *board.checkers();
         let mut movelist = NoDrop::new(ArrayVec::<[SquareAndBitBoard; 18]>::new());
+        let mask = |piece: Piece| masks[piece.to_index()];
 
         if checkers == EMPTY {
-            PawnType::legals::<NotInCheckType>(&mut movelist, &board, mask);
-            KnightType::legals::<NotInCheckType>(&mut movelist, &board, mask);
-            BishopType::legals::<NotInCheckType>(&mut movelist, &board, mask);
-            RookType::legals::<NotInCheckType>(&mut movelist, &board, mask);
-            QueenType::legals::<NotInCheckType>(&mut movelist, &board, mask);
-            KingType::legals::<NotInCheckType>(&mut movelist, &board, mask);
+            PawnType::legals::<NotInCheckType>(&mut movelist, &board, mask(Piece::Pawn), sources);
+            KnightType::legals::<NotInCheckType>(
+                &mut movelist,
+                &board,
+                mask(Piece::Knight),
+                sources,
+            );
+            BishopType::legals::<NotInCheckType>(
+                &mut movelist,
+                &board,
+                mask(Piece::Bishop),
+                sources,
+            );
+            RookType::legals::<NotInCheckType>(&mut movelist, &board, mask(Piece::Rook), sources);
+            QueenType::legals::<NotInCheckType>(&mut movelist, &board, mask(Piece::Queen), sources);
+            KingType::legals::<NotInCheckType>(&mut movelist, &board, mask(Piece::King), sources);
         } else if checkers.popcnt() == 1 {
-            PawnType::legals::<InCheckType>(&mut movelist, &board, mask);
-            KnightType::legals::<InCheckType>(&mut movelist, &board, mask);
-            BishopType::legals::<InCheckType>(&mut movelist, &board, mask);
-            RookType::legals::<InCheckType>(&mut movelist, &board, mask);
-            QueenType::legals::<InCheckType>(&mut movelist, &board, mask);
-            KingType::legals::<InCheckType>(&mut movelist, &board, mask);
+            PawnType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::Pawn), sources);
+            KnightType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::Knight), sources);
+            BishopType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::Bishop), sources);
+            RookType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::Rook), sources);
+            QueenType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::Queen), sources);
+            KingType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::King), sources);
         } else {
-            KingType::legals::<InCheckType>(&mut movelist, &board, mask);
+            KingType::legals::<InCheckType>(&mut movelist, &board, mask(Piece::King), sources);
         }
 
         movelist
     }
 
-    /// Create a new `MoveGen` structure, only generating legal moves
-    #[inline(always)]
-    pub fn new_legal(board: &Board) -> MoveGen {
+    fn from_moves(moves: MoveList) -> MoveGen {
         MoveGen {
-            moves: MoveGen::enumerate_moves(board),
+            moves,
             promotion_index: 0,
             iterator_mask: !EMPTY,
             index: 0,
         }
     }
 
+    /// Create a new `MoveGen` structure, only generating legal moves
+    #[inline(always)]
+    pub fn new_legal(board: &Board) -> MoveGen {
+        MoveGen::from_moves(MoveGen::enumerate_moves(board))
+    }
+
+    /// Create a new `MoveGen` structure, generating pseudo-legal moves: moves that follow the
+    /// rules for how each piece moves, but that may leave the king in check.  This skips the
+    /// pin and check filtering, so engines can defer it with `Board::is_legal_after_pseudo`
+    /// until a move is actually searched.  Castling is only generated when it is legal.
+    ///
+    /// This allows king-capture semantics: if the opponent's king is left in check, capturing
+    /// it is one of the moves.  Don't make that move, as `Board` panics when a king is captured;
+    /// use `Board::can_capture_king` to detect the position instead.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    /// use std::str::FromStr;
+    ///
+    /// // the bishop on d2 is pinned, and may not move
+    /// let board = Board::from_str("4k3/8/8/8/1q6/8/3B4/4K3 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(MoveGen::new_pseudo_legal(&board).len(), 11);
+    ///
+    /// let legal = MoveGen::new_pseudo_legal(&board)
+    ///     .filter(|m| board.is_legal_after_pseudo(*m))
+    ///     .count();
+    /// assert_eq!(legal, MoveGen::new_legal(&board).len());
+    /// assert_eq!(legal, 6);
+    /// ```
+    pub fn new_pseudo_legal(board: &Board) -> MoveGen {
+        let mask = !board.color_combined(board.side_to_move());
+        let mut movelist = NoDrop::new(ArrayVec::<[SquareAndBitBoard; 18]>::new());
+
+        pseudo_legal_moves::<PawnType>(&mut movelist, board, mask);
+        pseudo_legal_moves::<KnightType>(&mut movelist, board, mask);
+        pseudo_legal_moves::<BishopType>(&mut movelist, board, mask);
+        pseudo_legal_moves::<RookType>(&mut movelist, board, mask);
+        pseudo_legal_moves::<QueenType>(&mut movelist, board, mask);
+        pseudo_legal_moves::<KingType>(&mut movelist, board, mask);
+
+        MoveGen::from_moves(movelist)
+    }
+
+    /// Get the masks of the squares each piece type lands on with a quiet move: one that
+    /// doesn't capture or promote.
+    fn quiet_masks(board: &Board) -> [BitBoard; NUM_PIECES] {
+        let color = board.side_to_move();
+        let mut masks = [!board.combined(); NUM_PIECES];
+        masks[Piece::Pawn.to_index()] &= !get_rank(color.to_their_backrank());
+        if let Some(ep) = board.en_passant() {
+            masks[Piece::Pawn.to_index()] &= !BitBoard::from_square(ep.uforward(color));
+        }
+        masks
+    }
+
+    /// Create a new `MoveGen` structure, only generating legal captures and promotions.  This
+    /// includes en passant captures and underpromotions, but not castling.  These are the moves
+    /// a quiescence search looks at.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("3qk3/2P5/8/3pP3/8/8/8/4K2R w K d6 0 1").expect("Valid FEN");
+    /// let captures = MoveGen::new_legal_captures(&board)
+    ///     .map(|m| m.to_string())
+    ///     .collect::<Vec<String>>();
+    /// assert_eq!(captures.len(), 9);
+    /// assert!(captures.contains(&"e5d6".to_owned()));
+    /// assert!(captures.contains(&"c7d8q".to_owned()));
+    /// assert!(captures.contains(&"c7c8n".to_owned()));
+    /// ```
+    pub fn new_legal_captures(board: &Board) -> MoveGen {
+        let color = board.side_to_move();
+        let targets = *board.color_combined(!color);
+        let mut masks = [targets; NUM_PIECES];
+        masks[Piece::Pawn.to_index()] |= get_rank(color.to_their_backrank()) & !board.combined();
+        if let Some(ep) = board.en_passant() {
+            masks[Piece::Pawn.to_index()] |= BitBoard::from_square(ep.uforward(color));
+        }
+
+        // castling ignores the king's mask, so take it out
+        let mut result = MoveGen::from_moves(MoveGen::enumerate_masked(board, &masks, !EMPTY));
+        result.remove_mask(!masks[Piece::Pawn.to_index()]);
+        result
+    }
+
+    /// Create a new `MoveGen` structure, only generating legal quiet moves: moves that don't
+    /// capture or promote.  Castling is a quiet move.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(MoveGen::new_legal_quiets(&board).len(), 20);
+    /// ```
+    pub fn new_legal_quiets(board: &Board) -> MoveGen {
+        MoveGen::from_moves(MoveGen::enumerate_masked(
+            board,
+            &MoveGen::quiet_masks(board),
+            !EMPTY,
+        ))
+    }
+
+    /// Create a new `MoveGen` structure, only generating the legal moves that get out of check.
+    /// If the side to move is not in check, there are none.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(MoveGen::new_legal_evasions(&board).len(), 2);
+    /// assert_eq!(MoveGen::new_legal_evasions(&Board::default()).len(), 0);
+    /// ```
+    pub fn new_legal_evasions(board: &Board) -> MoveGen {
+        if *board.checkers() == EMPTY {
+            MoveGen::from_moves(NoDrop::new(ArrayVec::new()))
+        } else {
+            MoveGen::new_legal(board)
+        }
+    }
+
+    /// Create a new `MoveGen` structure, only generating legal quiet moves that give check.
+    ///
+    /// Direct checks are found by only generating moves to the squares where each piece type
+    /// attacks the opponent's king.  Only the moves of pieces that may uncover a check, and of
+    /// the king, are tested one by one.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    /// use std::str::FromStr;
+    ///
+    /// // every knight move uncovers the rook, and the rook checks from a8
+    /// let board = Board::from_str("4k3/8/8/8/8/8/4N3/R3R1K1 w - - 0 1").expect("Valid FEN");
+    /// assert_eq!(MoveGen::new_legal_quiet_checks(&board).len(), 6);
+    /// ```
+    pub fn new_legal_quiet_checks(board: &Board) -> MoveGen {
+        let color = board.side_to_move();
+        let combined = *board.combined();
+        let their_king = board.king_square(!color);
+        let rooks = get_rook_moves(their_king, combined);
+        let bishops = get_bishop_moves(their_king, combined);
+
+        // the squares each piece type gives check from
+        let mut masks = MoveGen::quiet_masks(board);
+        masks[Piece::Pawn.to_index()] &= get_pawn_attacks(their_king, !color, !EMPTY);
+        masks[Piece::Knight.to_index()] &= get_knight_moves(their_king);
+        masks[Piece::Bishop.to_index()] &= bishops;
+        masks[Piece::Rook.to_index()] &= rooks;
+        masks[Piece::Queen.to_index()] &= rooks | bishops;
+        masks[Piece::King.to_index()] = EMPTY;
+
+        // my pieces that are all that stand between one of my sliders and their king
+        let mut discoverers = EMPTY;
+        let sliders = board.color_combined(color)
+            & ((get_bishop_rays(their_king)
+                & (board.pieces(Piece::Bishop) | board.pieces(Piece::Queen)))
+                | (get_rook_rays(their_king)
+                    & (board.pieces(Piece::Rook) | board.pieces(Piece::Queen))));
+        for sq in sliders {
+            let between = between(sq, their_king) & combined;
+            if between.popcnt() == 1 {
+                discoverers |= between & board.color_combined(color);
+            }
+        }
+        let others = discoverers | board.pieces(Piece::King) & board.color_combined(color);
+
+        let mut moves = MoveGen::enumerate_masked(board, &masks, !others);
+        let others = MoveGen::enumerate_masked(board, &MoveGen::quiet_masks(board), others);
+        for mut moves_from in others.iter().copied() {
+            for dest in moves_from.bitboard {
+                if !board.gives_check(ChessMove::new(moves_from.square, dest, None)) {
+                    moves_from.bitboard ^= BitBoard::from_square(dest);
+                }
+            }
+            if moves_from.bitboard != EMPTY {
+                unsafe {
+                    moves.push_unchecked(moves_from);
+                }
+            }
+        }
+        MoveGen::from_moves(moves)
+    }
+
+    /// Create a new `MoveGen` structure, only generating the legal moves of one type of piece.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen, Piece};
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(MoveGen::new_legal_for_piece(&board, Piece::Knight).len(), 4);
+    /// assert_eq!(MoveGen::new_legal_for_piece(&board, Piece::Queen).len(), 0);
+    /// ```
+    pub fn new_legal_for_piece(board: &Board, piece: Piece) -> MoveGen {
+        let mask = !board.color_combined(board.side_to_move());
+        let mut masks = [EMPTY; NUM_PIECES];
+        masks[piece.to_index()] = mask;
+        MoveGen::from_moves(MoveGen::enumerate_masked(
+            board,
+            &masks,
+            *board.pieces(piece),
+        ))
+    }
+
+    /// Create a new `MoveGen` structure, only generating the legal moves of the piece on
+    /// `square`.  If there is no piece of the side to move there, there are none.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen, Square};
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(MoveGen::new_legal_from_square(&board, Square::E2).len(), 2);
+    /// assert_eq!(MoveGen::new_legal_from_square(&board, Square::E7).len(), 0);
+    /// ```
+    pub fn new_legal_from_square(board: &Board, square: Square) -> MoveGen {
+        let mask = !board.color_combined(board.side_to_move());
+        MoveGen::from_moves(MoveGen::enumerate_masked(
+            board,
+            &[mask; NUM_PIECES],
+            BitBoard::from_square(square),
+        ))
+    }
+
     /// Never, ever, iterate any moves that land on the following squares
     pub fn remove_mask(&mut self, mask: BitBoard) {
         for x in 0..self.moves.len() {
@@ -175,6 +424,29 @@ impl MoveGen {
         }
     }
 
+    /// Convert each remaining move of this generator to SAN, paired with the move itself.
+    ///
+    /// Like `ChessMove::to_san`, this works out disambiguation from the board's bitboards instead
+    /// of generating the moves again, so only moves that give check need any extra work.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, MoveGen};
+    ///
+    /// let board = Board::default();
+    /// let san = MoveGen::new_legal(&board)
+    ///     .to_san(&board)
+    ///     .into_iter()
+    ///     .map(|(_, san)| san)
+    ///     .collect::<Vec<String>>();
+    ///
+    /// assert_eq!(san.len(), 20);
+    /// assert!(san.contains(&"Nf3".to_owned()));
+    /// assert!(san.contains(&"e4".to_owned()));
+    /// ```
+    pub fn to_san(self, board: &Board) -> Vec<(ChessMove, String)> {
+        self.map(|m| (m, m.to_san(board))).collect()
+    }
+
     /// This function checks the legality *only for moves generated by `MoveGen`*.
     ///
     /// Calling this function for moves not generated by `MoveGen` will result in possibly
@@ -201,7 +473,12 @@ impl MoveGen {
             }
             Piece::King => {
                 let bb = between(chess_move.get_source(), chess_move.get_dest());
-                if bb.popcnt() == 1 {
+                if board.is_chess960()
+                    && board.color_on(chess_move.get_dest()) == Some(board.side_to_move())
+                {
+                    // Chess960 castles, which are fully checked by `MoveGen`
+                    true
+                } else if bb.popcnt() == 1 {
                     // castles
                     if !KingType::legal_king_move(board, bb.to_square()) {
                         false
@@ -234,6 +511,62 @@ impl MoveGen {
         }
     }
 
+    #[cfg(test)]
+    /// Do a perft test by playing and taking back moves on a single `Board`, checking that every
+    /// position is restored exactly
+    pub fn movegen_perft_test_play_undo(board: &mut Board, depth: usize) -> usize {
+        let iterable = MoveGen::new_legal(board);
+
+        let mut result: usize = 0;
+        if depth == 1 {
+            iterable.len()
+        } else {
+            for m in iterable {
+                let before = *board;
+                let undo = board.play(m);
+                result += MoveGen::movegen_perft_test_play_undo(board, depth - 1);
+                board.undo(m, undo);
+                assert_eq!(*board, before);
+            }
+            result
+        }
+    }
+
+    #[cfg(test)]
+    /// Do a perft test on the pseudo-legal moves, filtering out the illegal ones
+    pub fn movegen_perft_test_pseudo_legal(board: &Board, depth: usize) -> usize {
+        let iterable = MoveGen::new_pseudo_legal(board).filter(|m| board.is_legal_after_pseudo(*m));
+
+        if depth == 1 {
+            iterable.count()
+        } else {
+            iterable
+                .map(|m| {
+                    MoveGen::movegen_perft_test_pseudo_legal(&board.make_move_new(m), depth - 1)
+                })
+                .sum()
+        }
+    }
+
+    #[cfg(test)]
+    /// Do a perft test with king-capture semantics: every pseudo-legal move is made, and the ones
+    /// that let the opponent capture the king are thrown away
+    pub fn movegen_perft_test_king_capture(board: &Board, depth: usize) -> usize {
+        let mut result = 0;
+        for m in MoveGen::new_pseudo_legal(board) {
+            let after = board.make_move_new(m);
+            if after.can_capture_king() {
+                continue;
+            }
+            if depth == 1 {
+                result += 1;
+            } else {
+                result += MoveGen::movegen_perft_test_king_capture(&after, depth - 1);
+            }
+        }
+        result
+    }
+
     #[cfg(test)]
     /// Do a perft test after splitting the moves up into two groups
     pub fn movegen_perft_test_piecewise(board: &Board, depth: usize) -> usize {
@@ -342,6 +675,10 @@ impl Iterator for MoveGen {
 #[cfg(test)]
 use crate::board_builder::BoardBuilder;
 #[cfg(test)]
+use crate::piece::ALL_PIECES;
+#[cfg(test)]
+use crate::square::ALL_SQUARES;
+#[cfg(test)]
 use std::collections::HashSet;
 #[cfg(test)]
 use std::convert::TryInto;
@@ -354,6 +691,20 @@ fn movegen_perft_test(fen: String, depth: usize, result: usize) {
 
     assert_eq!(MoveGen::movegen_perft_test(&board, depth), result);
     assert_eq!(MoveGen::movegen_perft_test_piecewise(&board, depth), result);
+    assert_eq!(
+        MoveGen::movegen_perft_test_pseudo_legal(&board, depth),
+        result
+    );
+    assert_eq!(
+        MoveGen::movegen_perft_test_king_capture(&board, depth),
+        result
+    );
+    let mut played = board;
+    assert_eq!(
+        MoveGen::movegen_perft_test_play_undo(&mut played, depth),
+        result
+    );
+    assert_eq!(played, board);
 }
 
 #[test]
@@ -513,6 +864,95 @@ fn movegen_perft_26() {
     movegen_perft_test("8/5k2/8/5N2/5Q2/2K5/8/8 w - - 0 1".to_owned(), 4, 23527);
 }
 
+#[test]
+fn movegen_perft_chess960_1() {
+    movegen_perft_test(
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9".to_owned(),
+        4,
+        326672,
+    );
+}
+
+#[test]
+fn movegen_perft_chess960_2() {
+    movegen_perft_test(
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9".to_owned(),
+        4,
+        667366,
+    );
+}
+
+#[test]
+fn movegen_perft_chess960_3() {
+    movegen_perft_test(
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9".to_owned(),
+        4,
+        273318,
+    );
+}
+
+#[test]
+fn movegen_perft_chess960_4() {
+    // The king and the castling rook start next to each other
+    movegen_perft_test(
+        "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9".to_owned(),
+        4,
+        382958,
+    );
+}
+
+#[test]
+fn movegen_perft_chess960_start_positions() {
+    // the standard starting position, with Chess960 castling
+    let board = Board::from_chess960_index(518).unwrap();
+    assert_eq!(MoveGen::movegen_perft_test(&board, 4), 197281);
+
+    let board = Board::from_chess960_index(0).unwrap();
+    assert_eq!(MoveGen::movegen_perft_test(&board, 4), 201143);
+}
+
+#[test]
+fn movegen_to_san() {
+    // every move in a busy position round trips through SAN
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1",
+        "7k/8/8/8/Q1Q5/8/Q1Q5/4K3 w - - 0 1",
+    ]
+    .iter()
+    {
+        let board = Board::from_str(fen).unwrap();
+        let moves = MoveGen::new_legal(&board).to_san(&board);
+        assert_eq!(moves.len(), MoveGen::new_legal(&board).len());
+        for (m, san) in moves {
+            assert_eq!(ChessMove::from_san(&board, &san).unwrap(), m);
+        }
+    }
+}
+
+#[test]
+fn movegen_chess960_castles() {
+    let board = Board::from_str("7k/8/8/8/8/8/8/1RK1R3 w EB - 0 1").unwrap();
+    let castles = MoveGen::new_legal(&board)
+        .filter(|m| board.piece_on(m.get_dest()) == Some(Piece::Rook))
+        .filter(|m| board.piece_on(m.get_source()) == Some(Piece::King))
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        castles,
+        vec![move_of("c1e1"), move_of("c1b1")].into_iter().collect()
+    );
+
+    // After O-O-O, the king is on c1 and the rook is on d1
+    let board = board.make_move_new(move_of("c1b1"));
+    assert_eq!(format!("{}", board), "7k/8/8/8/8/8/8/2KRR3 b - - 1 1");
+
+    // The castling rook on b1 hides an attack from the queen on a1 along the back rank.
+    let board = Board::from_str("7k/8/8/8/8/8/8/qRK1R3 w EB - 0 1").unwrap();
+    assert!(!board.legal(move_of("c1b1")));
+    assert!(board.legal(move_of("c1e1")));
+}
+
 #[test]
 fn movegen_issue_15() {
     let board =
@@ -568,3 +1008,73 @@ fn test_masked_move_gen() {
         expected.into_iter().collect()
     );
 }
+
+#[cfg(test)]
+fn check_staged_move_gen(board: &Board, depth: usize) {
+    let legal = MoveGen::new_legal(board).collect::<HashSet<_>>();
+    let captures = MoveGen::new_legal_captures(board).collect::<HashSet<_>>();
+    let quiets = MoveGen::new_legal_quiets(board).collect::<HashSet<_>>();
+    assert!(captures.is_disjoint(&quiets));
+    assert_eq!(&captures | &quiets, legal);
+    assert!(captures
+        .iter()
+        .all(|m| board.is_capture(*m) || m.get_promotion().is_some()));
+
+    let checks = quiets
+        .iter()
+        .copied()
+        .filter(|m| board.gives_check(*m))
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        MoveGen::new_legal_quiet_checks(board).collect::<HashSet<_>>(),
+        checks
+    );
+
+    let evasions = MoveGen::new_legal_evasions(board).collect::<HashSet<_>>();
+    if *board.checkers() == EMPTY {
+        assert!(evasions.is_empty());
+    } else {
+        assert_eq!(evasions, legal);
+    }
+
+    let mut by_piece = HashSet::new();
+    for piece in ALL_PIECES.iter() {
+        for m in MoveGen::new_legal_for_piece(board, *piece) {
+            assert_eq!(board.piece_on(m.get_source()), Some(*piece));
+            assert!(by_piece.insert(m));
+        }
+    }
+    assert_eq!(by_piece, legal);
+
+    let mut by_square = HashSet::new();
+    for square in ALL_SQUARES.iter() {
+        for m in MoveGen::new_legal_from_square(board, *square) {
+            assert_eq!(m.get_source(), *square);
+            assert!(by_square.insert(m));
+        }
+    }
+    assert_eq!(by_square, legal);
+
+    if depth > 0 {
+        for m in legal {
+            check_staged_move_gen(&board.make_move_new(m), depth - 1);
+        }
+    }
+}
+
+#[test]
+fn test_staged_move_gen() {
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1",
+        "4k3/8/8/8/8/8/4N3/R3R1K1 w - - 0 1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ]
+    .iter()
+    {
+        check_staged_move_gen(&Board::from_str(fen).unwrap(), 2);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::bitboard::{BitBoard, EMPTY};
 use crate::board::Board;
 use crate::color::Color;
+use crate::file::File;
 use crate::movegen::{MoveList, SquareAndBitBoard};
 use crate::piece::Piece;
 use crate::square::Square;
@@ -15,8 +16,9 @@ pub trait PieceType {
     fn is(piece: Piece) -> bool;
     fn into_piece() -> Piece;
     fn pseudo_legals(src: Square, color: Color, combined: BitBoard, mask: BitBoard) -> BitBoard;
+    /// Add the legal moves of the pieces of this type on `sources` that land on `mask`.
     #[inline(always)]
-    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard)
+    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard, sources: BitBoard)
     where
         T: CheckType,
     {
@@ -25,7 +27,7 @@ pub trait PieceType {
         let my_pieces = board.color_combined(color);
         let ksq = board.king_square(color);
 
-        let pieces = board.pieces(Self::into_piece()) & my_pieces;
+        let pieces = board.pieces(Self::into_piece()) & my_pieces & sources;
         let pinned = board.pinned();
         let checkers = board.checkers();
 
@@ -126,7 +128,7 @@ impl PieceType for PawnType {
     }
 
     #[inline(always)]
-    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard)
+    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard, sources: BitBoard)
     where
         T: CheckType,
     {
@@ -135,7 +137,7 @@ impl PieceType for PawnType {
         let my_pieces = board.color_combined(color);
         let ksq = board.king_square(color);
 
-        let pieces = board.pieces(Self::into_piece()) & my_pieces;
+        let pieces = board.pieces(Self::into_piece()) & my_pieces & sources;
         let pinned = board.pinned();
         let checkers = board.checkers();
 
@@ -177,9 +179,11 @@ impl PieceType for PawnType {
             let ep_sq = board.en_passant().unwrap();
             let rank = get_rank(ep_sq.get_rank());
             let files = get_adjacent_files(ep_sq.get_file());
+            let dest = ep_sq.uforward(color);
             for src in rank & files & pieces {
-                let dest = ep_sq.uforward(color);
-                if PawnType::legal_ep_move(board, src, dest) {
+                if mask & BitBoard::from_square(dest) != EMPTY
+                    && PawnType::legal_ep_move(board, src, dest)
+                {
                     unsafe {
                         movelist.push_unchecked(SquareAndBitBoard::new(
                             src,
@@ -223,7 +227,7 @@ impl PieceType for KnightType {
     }
 
     #[inline(always)]
-    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard)
+    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard, sources: BitBoard)
     where
         T: CheckType,
     {
@@ -232,7 +236,7 @@ impl PieceType for KnightType {
         let my_pieces = board.color_combined(color);
         let ksq = board.king_square(color);
 
-        let pieces = board.pieces(Self::into_piece()) & my_pieces;
+        let pieces = board.pieces(Self::into_piece()) & my_pieces & sources;
         let pinned = board.pinned();
         let checkers = board.checkers();
 
@@ -298,6 +302,12 @@ impl KingType {
             ^ (board.pieces(Piece::King) & board.color_combined(board.side_to_move()))
             | BitBoard::from_square(dest);
 
+        KingType::safe_square(board, dest, combined)
+    }
+
+    /// Is `dest` free from enemy attacks, given the occupancy `combined`?
+    #[inline(always)]
+    fn safe_square(board: &Board, dest: Square, combined: BitBoard) -> bool {
         let mut attackers = EMPTY;
 
         let rooks = (board.pieces(Piece::Rook) | board.pieces(Piece::Queen))
@@ -326,6 +336,109 @@ impl KingType {
 
         attackers == EMPTY
     }
+
+    /// If the side to move may castle with the rook on `rook_file` under Chess960 rules, return
+    /// the square of that rook.  The caller must verify that we are not in check.
+    ///
+    /// We may castle iff:
+    ///  * every square the king and rook travel over (including their destinations) is empty,
+    ///    ignoring the king and rook themselves.
+    ///  * no square the king travels over is attacked, even with the castling rook removed.
+    #[inline(always)]
+    fn legal_castle_960(board: &Board, rook_file: File, kingside: bool) -> Option<Square> {
+        let color = board.side_to_move();
+        let my_backrank = color.to_my_backrank();
+        let ksq = board.king_square(color);
+        let rook = Square::make_square(my_backrank, rook_file);
+        let (king_dest, rook_dest) = if kingside {
+            (
+                Square::make_square(my_backrank, File::G),
+                Square::make_square(my_backrank, File::F),
+            )
+        } else {
+            (
+                Square::make_square(my_backrank, File::C),
+                Square::make_square(my_backrank, File::D),
+            )
+        };
+
+        let combined = board.combined() ^ BitBoard::from_square(ksq) ^ BitBoard::from_square(rook);
+        let king_path = between(ksq, king_dest) | BitBoard::from_square(king_dest);
+        let rook_path = between(rook, rook_dest) | BitBoard::from_square(rook_dest);
+
+        if (king_path | rook_path) & combined != EMPTY {
+            return None;
+        }
+
+        for sq in king_path {
+            if !KingType::safe_square(board, sq, combined) {
+                return None;
+            }
+        }
+
+        Some(rook)
+    }
+
+    /// Get the castling moves of the side to move, which must not be in check.
+    ///
+    /// We may castle iff:
+    ///  * the `Board` structure says we can.
+    ///  * the squares between my king and my rook are empty.
+    ///  * no enemy pieces are attacking the squares between the king, and the kings
+    ///    destination square.  This is determined by going to the left or right, and calling
+    ///    `legal_king_move` for that square.
+    #[inline(always)]
+    pub fn castles(board: &Board) -> BitBoard {
+        let combined = board.combined();
+        let color = board.side_to_move();
+        let ksq = board.king_square(color);
+        let mut moves = EMPTY;
+
+        if board.is_chess960() {
+            // In Chess960, castling is represented as the king capturing its own rook.
+            if board.my_castle_rights().has_kingside() {
+                if let Some(rook) =
+                    KingType::legal_castle_960(board, board.kingside_rook_file(color), true)
+                {
+                    moves |= BitBoard::from_square(rook);
+                }
+            }
+
+            if board.my_castle_rights().has_queenside() {
+                if let Some(rook) =
+                    KingType::legal_castle_960(board, board.queenside_rook_file(color), false)
+                {
+                    moves |= BitBoard::from_square(rook);
+                }
+            }
+        } else {
+            if board.my_castle_rights().has_kingside()
+                && (combined & board.my_castle_rights().kingside_squares(color)) == EMPTY
+            {
+                let middle = ksq.uright();
+                let right = middle.uright();
+                if KingType::legal_king_move(board, middle)
+                    && KingType::legal_king_move(board, right)
+                {
+                    moves |= BitBoard::from_square(right);
+                }
+            }
+
+            if board.my_castle_rights().has_queenside()
+                && (combined & board.my_castle_rights().queenside_squares(color)) == EMPTY
+            {
+                let middle = ksq.uleft();
+                let left = middle.uleft();
+                if KingType::legal_king_move(board, middle)
+                    && KingType::legal_king_move(board, left)
+                {
+                    moves |= BitBoard::from_square(left);
+                }
+            }
+        }
+
+        moves
+    }
 }
 
 impl PieceType for KingType {
@@ -343,13 +456,16 @@ impl PieceType for KingType {
     }
 
     #[inline(always)]
-    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard)
+    fn legals<T>(movelist: &mut MoveList, board: &Board, mask: BitBoard, sources: BitBoard)
     where
         T: CheckType,
     {
         let combined = board.combined();
         let color = board.side_to_move();
         let ksq = board.king_square(color);
+        if sources & BitBoard::from_square(ksq) == EMPTY {
+            return;
+        }
 
         let mut moves = Self::pseudo_legals(ksq, color, *combined, mask);
 
@@ -360,39 +476,10 @@ impl PieceType for KingType {
             }
         }
 
-        // If we are not in check, we may be able to castle.
-        // We can do so iff:
-        //  * the `Board` structure says we can.
-        //  * the squares between my king and my rook are empty.
-        //  * no enemy pieces are attacking the squares between the king, and the kings
-        //    destination square.
-        //  ** This is determined by going to the left or right, and calling
-        //     'legal_king_move' for that square.
         if !T::IN_CHECK {
-            if board.my_castle_rights().has_kingside()
-                && (combined & board.my_castle_rights().kingside_squares(color)) == EMPTY
-            {
-                let middle = ksq.uright();
-                let right = middle.uright();
-                if KingType::legal_king_move(board, middle)
-                    && KingType::legal_king_move(board, right)
-                {
-                    moves ^= BitBoard::from_square(right);
-                }
-            }
-
-            if board.my_castle_rights().has_queenside()
-                && (combined & board.my_castle_rights().queenside_squares(color)) == EMPTY
-            {
-                let middle = ksq.uleft();
-                let left = middle.uleft();
-                if KingType::legal_king_move(board, middle)
-                    && KingType::legal_king_move(board, left)
-                {
-                    moves ^= BitBoard::from_square(left);
-                }
-            }
+            moves |= KingType::castles(board);
         }
+
         if moves != EMPTY {
             unsafe {
                 movelist.push_unchecked(SquareAndBitBoard::new(ksq, moves, false));
@@ -1,3 +1,44 @@
-pub fn pseudo_legal_moves<T: PieceType>(src: Square, color: Color, combined: BitBoard, mask: BitBoard) {
+use crate::bitboard::{BitBoard, EMPTY};
+use crate::board::Board;
+use crate::magic::{get_adjacent_files, get_rank};
+use crate::movegen::piece_type::{KingType, PieceType};
+use crate::movegen::{MoveList, SquareAndBitBoard};
+use crate::piece::Piece;
 
+/// Add the pseudo-legal moves of the pieces of type `T` that land on `mask`.
+///
+/// Pseudo-legal moves obey the way each piece moves, but may leave the king in check: pins and
+/// checks are ignored.  Castling is the exception, and is only added when it is legal.
+#[inline(always)]
+pub fn pseudo_legal_moves<T: PieceType>(movelist: &mut MoveList, board: &Board, mask: BitBoard) {
+    let combined = *board.combined();
+    let color = board.side_to_move();
+    let pieces = board.pieces(T::into_piece()) & board.color_combined(color);
+    let promotion = T::is(Piece::Pawn);
+
+    for src in pieces {
+        let mut moves = T::pseudo_legals(src, color, combined, mask);
+
+        if promotion {
+            if let Some(ep_sq) = board.en_passant() {
+                let dest = ep_sq.uforward(color);
+                let sources = get_rank(ep_sq.get_rank()) & get_adjacent_files(ep_sq.get_file());
+                if sources & BitBoard::from_square(src) != EMPTY {
+                    moves |= mask & BitBoard::from_square(dest);
+                }
+            }
+        } else if T::is(Piece::King) && *board.checkers() == EMPTY {
+            moves |= KingType::castles(board);
+        }
+
+        if moves != EMPTY {
+            unsafe {
+                movelist.push_unchecked(SquareAndBitBoard::new(
+                    src,
+                    moves,
+                    promotion && src.get_rank() == color.to_seventh_rank(),
+                ));
+            }
+        }
+    }
 }
@@ -0,0 +1,400 @@
+use crate::board::Board;
+use crate::chess_move::ChessMove;
+use crate::movegen::MoveGen;
+use crate::piece::Piece;
+use arrayvec::ArrayVec;
+use std::iter::ExactSizeIterator;
+
+/// The most moves a `ScoredMoveList` can hold, which is more than any legal position has.
+pub const MAX_MOVES: usize = 256;
+
+/// The most killer moves a `MovePicker` tries ahead of generating moves.
+pub const MAX_KILLERS: usize = 4;
+
+/// The MVV-LVA score of captures, which puts every capture ahead of every quiet move.
+const CAPTURE_SCORE: i32 = 1000;
+
+/// Score a move by MVV-LVA: most valuable victim, least valuable attacker.
+///
+/// Captures come first, ordered by the piece captured and then by the piece capturing it.
+/// Promotions add the value of the new piece, and other moves score 0.
+///
+/// ```
+/// use minorhacks_chess::{mvv_lva, Board, ChessMove, Square};
+/// use std::str::FromStr;
+///
+/// let board = Board::from_str("4k3/8/3q4/2P3r1/8/5N2/8/4K3 w - - 0 1").expect("Valid FEN");
+/// let pxq = mvv_lva(&board, ChessMove::new(Square::C5, Square::D6, None));
+/// let nxr = mvv_lva(&board, ChessMove::new(Square::F3, Square::G5, None));
+/// let quiet = mvv_lva(&board, ChessMove::new(Square::F3, Square::H4, None));
+///
+/// assert!(pxq > nxr);
+/// assert!(nxr > quiet);
+/// assert_eq!(quiet, 0);
+/// ```
+pub fn mvv_lva(board: &Board, m: ChessMove) -> i32 {
+    let mut score = match board.captured_piece(m) {
+        Some(victim) => {
+            let attacker = board.piece_on(m.get_source()).unwrap_or(Piece::Pawn);
+            CAPTURE_SCORE + 10 * victim.to_index() as i32 - attacker.to_index() as i32
+        }
+        None => 0,
+    };
+    if let Some(promotion) = m.get_promotion() {
+        score += CAPTURE_SCORE + 10 * promotion.to_index() as i32;
+    }
+    score
+}
+
+/// A move along with the score used to order it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Hash)]
+pub struct ScoredMove {
+    chess_move: ChessMove,
+    score: i32,
+}
+
+impl ScoredMove {
+    /// Create a new `ScoredMove`.
+    pub fn new(chess_move: ChessMove, score: i32) -> ScoredMove {
+        ScoredMove { chess_move, score }
+    }
+
+    /// Get the move.
+    pub fn get_move(&self) -> ChessMove {
+        self.chess_move
+    }
+
+    /// Get the score of the move.
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+}
+
+/// A stack-allocated list of scored moves, which iterates from the best score to the worst.
+///
+/// Each call to `next()` picks the best of the remaining moves, so the list is never fully
+/// sorted.  That is cheaper when a search cuts off after the first few moves.  Moves with equal
+/// scores come out in no particular order.
+///
+/// ```
+/// use minorhacks_chess::{Board, MoveGen, ScoredMoveList, Square};
+///
+/// let board = Board::default();
+///
+/// // prefer moves that go far up the board
+/// let moves = ScoredMoveList::from_moves(MoveGen::new_legal(&board), |m| {
+///     m.get_dest().get_rank().to_index() as i32
+/// });
+/// assert_eq!(moves.len(), 20);
+///
+/// let scores = moves.map(|m| m.get_score()).collect::<Vec<i32>>();
+/// assert_eq!(scores[..8], [3; 8]);
+/// assert_eq!(scores[8..], [2; 12]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScoredMoveList {
+    moves: ArrayVec<[ScoredMove; MAX_MOVES]>,
+    index: usize,
+}
+
+impl Default for ScoredMoveList {
+    fn default() -> ScoredMoveList {
+        ScoredMoveList::new()
+    }
+}
+
+impl ScoredMoveList {
+    /// Create an empty `ScoredMoveList`.
+    pub fn new() -> ScoredMoveList {
+        ScoredMoveList {
+            moves: ArrayVec::new(),
+            index: 0,
+        }
+    }
+
+    /// Collect `moves`, scoring each of them with `score`.
+    ///
+    /// panic!() if there are more than `MAX_MOVES` moves.
+    pub fn from_moves<I, F>(moves: I, mut score: F) -> ScoredMoveList
+    where
+        I: IntoIterator<Item = ChessMove>,
+        F: FnMut(ChessMove) -> i32,
+    {
+        let mut result = ScoredMoveList::new();
+        for m in moves {
+            result.push(m, score(m));
+        }
+        result
+    }
+
+    /// Collect `moves`, ordering them by MVV-LVA.  See `mvv_lva`.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, MoveGen, ScoredMoveList, Square};
+    /// use std::str::FromStr;
+    ///
+    /// let board = Board::from_str("4k3/8/3q4/2P3r1/8/5N2/8/4K3 w - - 0 1").expect("Valid FEN");
+    /// let mut moves = ScoredMoveList::new_mvv_lva(&board, MoveGen::new_legal(&board));
+    ///
+    /// let pxq = ChessMove::new(Square::C5, Square::D6, None);
+    /// let nxr = ChessMove::new(Square::F3, Square::G5, None);
+    /// assert_eq!(moves.next().map(|m| m.get_move()), Some(pxq));
+    /// assert_eq!(moves.next().map(|m| m.get_move()), Some(nxr));
+    /// ```
+    pub fn new_mvv_lva<I>(board: &Board, moves: I) -> ScoredMoveList
+    where
+        I: IntoIterator<Item = ChessMove>,
+    {
+        ScoredMoveList::from_moves(moves, |m| mvv_lva(board, m))
+    }
+
+    /// Add a move with its score.
+    ///
+    /// panic!() if the list already holds `MAX_MOVES` moves.
+    pub fn push(&mut self, chess_move: ChessMove, score: i32) {
+        self.moves.push(ScoredMove::new(chess_move, score));
+    }
+
+    /// Get the moves that have not been picked yet, in no particular order.
+    pub fn remaining(&self) -> &[ScoredMove] {
+        &self.moves[self.index..]
+    }
+}
+
+impl ExactSizeIterator for ScoredMoveList {
+    fn len(&self) -> usize {
+        self.moves.len() - self.index
+    }
+}
+
+impl Iterator for ScoredMoveList {
+    type Item = ScoredMove;
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+
+    fn next(&mut self) -> Option<ScoredMove> {
+        if self.index >= self.moves.len() {
+            return None;
+        }
+
+        // selection sort, one step at a time
+        let mut best = self.index;
+        for i in (self.index + 1)..self.moves.len() {
+            if self.moves[i].score > self.moves[best].score {
+                best = i;
+            }
+        }
+        self.moves.swap(self.index, best);
+        self.index += 1;
+        Some(self.moves[self.index - 1])
+    }
+}
+
+/// Iterate over the legal moves of a position best-first, trying a hash move and killer moves
+/// before generating anything else.
+///
+/// The injected moves are checked for legality, so stale moves from a transposition table or
+/// from another position are skipped.  Every legal move is returned exactly once.
+///
+/// ```
+/// use minorhacks_chess::{Board, ChessMove, MoveGen, MovePicker, Square};
+///
+/// let board = Board::default();
+/// let hash_move = ChessMove::new(Square::E2, Square::E4, None);
+/// let killer = ChessMove::new(Square::G1, Square::F3, None);
+/// let stale = ChessMove::new(Square::E7, Square::E5, None);
+///
+/// let mut picker = MovePicker::new_mvv_lva(&board);
+/// picker.set_hash_move(hash_move).add_killer(stale).add_killer(killer);
+///
+/// assert_eq!(picker.next(), Some(hash_move));
+/// assert_eq!(picker.next(), Some(killer));
+/// assert_eq!(picker.count(), 18);
+/// ```
+pub struct MovePicker<'a, F>
+where
+    F: FnMut(&Board, ChessMove) -> i32,
+{
+    board: &'a Board,
+    hash_move: Option<ChessMove>,
+    killers: ArrayVec<[ChessMove; MAX_KILLERS]>,
+    tried: ArrayVec<[ChessMove; MAX_KILLERS + 1]>,
+    stage: usize,
+    score: F,
+    moves: Option<ScoredMoveList>,
+}
+
+impl<'a, F> MovePicker<'a, F>
+where
+    F: FnMut(&Board, ChessMove) -> i32,
+{
+    /// Create a new `MovePicker`, which orders the generated moves by `score`.  Higher scores
+    /// are tried first.
+    pub fn new(board: &'a Board, score: F) -> MovePicker<'a, F> {
+        MovePicker {
+            board,
+            hash_move: None,
+            killers: ArrayVec::new(),
+            tried: ArrayVec::new(),
+            stage: 0,
+            score,
+            moves: None,
+        }
+    }
+
+    /// Try `m` before any other move.
+    pub fn set_hash_move(&mut self, m: ChessMove) -> &mut Self {
+        self.hash_move = Some(m);
+        self
+    }
+
+    /// Try `m` after the hash move, but before the generated moves.  Killer moves are tried in
+    /// the order they are added, and only the first `MAX_KILLERS` of them are kept.
+    pub fn add_killer(&mut self, m: ChessMove) -> &mut Self {
+        let _ = self.killers.try_push(m);
+        self
+    }
+
+    /// Is `m` a legal move on this board, that has not been returned yet?
+    fn is_fresh(&self, m: ChessMove) -> bool {
+        !self.tried.contains(&m)
+            && MoveGen::new_legal_from_square(self.board, m.get_source()).any(|x| x == m)
+    }
+
+    /// Generate and score the moves that were not injected.
+    fn generate(&mut self) {
+        if self.moves.is_none() {
+            let board = self.board;
+            let tried = &self.tried;
+            let score = &mut self.score;
+            self.moves = Some(ScoredMoveList::from_moves(
+                MoveGen::new_legal(board).filter(|m| !tried.contains(m)),
+                |m| score(board, m),
+            ));
+        }
+    }
+}
+
+impl<'a> MovePicker<'a, fn(&Board, ChessMove) -> i32> {
+    /// Create a new `MovePicker`, which orders the generated moves by MVV-LVA.  See `mvv_lva`.
+    pub fn new_mvv_lva(board: &'a Board) -> Self {
+        MovePicker::new(board, mvv_lva)
+    }
+}
+
+impl<'a, F> Iterator for MovePicker<'a, F>
+where
+    F: FnMut(&Board, ChessMove) -> i32,
+{
+    type Item = ChessMove;
+
+    fn next(&mut self) -> Option<ChessMove> {
+        while self.moves.is_none() && self.stage <= self.killers.len() {
+            let candidate = if self.stage == 0 {
+                self.hash_move
+            } else {
+                Some(self.killers[self.stage - 1])
+            };
+            self.stage += 1;
+
+            if let Some(m) = candidate {
+                if self.is_fresh(m) {
+                    self.tried.push(m);
+                    return Some(m);
+                }
+            }
+        }
+
+        self.generate();
+        self.moves
+            .as_mut()
+            .and_then(|moves| moves.next())
+            .map(|m| m.get_move())
+    }
+}
+
+#[cfg(test)]
+use crate::square::Square;
+#[cfg(test)]
+use std::collections::HashSet;
+#[cfg(test)]
+use std::str::FromStr;
+
+#[test]
+fn test_scored_move_list() {
+    let board =
+        Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    let legal = MoveGen::new_legal(&board).collect::<HashSet<_>>();
+
+    let moves = ScoredMoveList::new_mvv_lva(&board, MoveGen::new_legal(&board));
+    assert_eq!(moves.len(), legal.len());
+    let moves = moves.collect::<Vec<_>>();
+    assert!(moves
+        .windows(2)
+        .all(|pair| pair[0].get_score() >= pair[1].get_score()));
+    assert_eq!(
+        moves.iter().map(|m| m.get_move()).collect::<HashSet<_>>(),
+        legal
+    );
+
+    // all 8 captures come before the quiet moves
+    assert!(moves[..8].iter().all(|m| board.is_capture(m.get_move())));
+    assert!(moves[8..].iter().all(|m| m.get_score() == 0));
+
+    let mut moves = ScoredMoveList::new();
+    moves.push(ChessMove::new(Square::A2, Square::A3, None), -5);
+    moves.push(ChessMove::new(Square::A2, Square::A4, None), 7);
+    moves.push(ChessMove::new(Square::B2, Square::B3, None), 0);
+    assert_eq!(moves.next().map(|m| m.get_score()), Some(7));
+    assert_eq!(moves.remaining().len(), 2);
+    assert_eq!(moves.map(|m| m.get_score()).collect::<Vec<_>>(), [0, -5]);
+}
+
+#[test]
+fn test_move_picker() {
+    let board =
+        Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    let legal = MoveGen::new_legal(&board).collect::<HashSet<_>>();
+    let castle = ChessMove::new(Square::E1, Square::G1, None);
+    let quiet = ChessMove::new(Square::A2, Square::A3, None);
+    let illegal = ChessMove::new(Square::A2, Square::A5, None);
+
+    // injected moves come first, in order, skipping illegal moves and duplicates
+    let mut picker = MovePicker::new_mvv_lva(&board);
+    picker
+        .set_hash_move(castle)
+        .add_killer(illegal)
+        .add_killer(castle)
+        .add_killer(quiet);
+    let moves = picker.collect::<Vec<_>>();
+    assert_eq!(moves[..2], [castle, quiet]);
+    assert!(board.is_capture(moves[2]));
+    assert_eq!(moves.len(), legal.len());
+    assert_eq!(moves.into_iter().collect::<HashSet<_>>(), legal);
+
+    // an illegal hash move is skipped, and a custom score orders the rest
+    let mut picker = MovePicker::new(&board, |board, m| {
+        if board.is_castling(m) {
+            100
+        } else {
+            -(m.get_dest().to_index() as i32)
+        }
+    });
+    picker.set_hash_move(illegal);
+    let moves = picker.collect::<Vec<_>>();
+    assert!(board.is_castling(moves[0]) && board.is_castling(moves[1]));
+    assert_eq!(moves[2].get_dest(), Square::B1);
+    assert_eq!(moves.into_iter().collect::<HashSet<_>>(), legal);
+
+    // too many killers are ignored
+    let mut picker = MovePicker::new_mvv_lva(&board);
+    for _ in 0..(MAX_KILLERS + 2) {
+        picker.add_killer(quiet);
+    }
+    assert_eq!(picker.count(), legal.len());
+}
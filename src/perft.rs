@@ -0,0 +1,343 @@
+use crate::bitboard::EMPTY;
+use crate::board::Board;
+use crate::cache_table::CacheTable;
+use crate::chess_move::ChessMove;
+use crate::movegen::MoveGen;
+
+use std::cmp;
+use std::ops::{Add, AddAssign};
+use std::thread;
+
+/// Count the leaf nodes of the legal move tree `depth` plies deep.
+///
+/// ```
+/// use minorhacks_chess::{perft, Board};
+///
+/// let board = Board::default();
+/// assert_eq!(perft(&board, 0), 1);
+/// assert_eq!(perft(&board, 3), 8902);
+/// ```
+pub fn perft(board: &Board, depth: usize) -> u64 {
+    match depth {
+        0 => 1,
+        1 => MoveGen::new_legal(board).len() as u64,
+        _ => MoveGen::new_legal(board)
+            .map(|m| perft(&board.make_move_new(m), depth - 1))
+            .sum(),
+    }
+}
+
+/// Count the leaf nodes below each legal move, `depth` plies deep in total.  The counts add up
+/// to `perft(board, depth)`, so comparing them with another move generator narrows down which
+/// move a bug is under.
+///
+/// ```
+/// use minorhacks_chess::{perft_divide, Board, ChessMove, Square};
+///
+/// let board = Board::default();
+/// let divide = perft_divide(&board, 3);
+/// assert_eq!(divide.len(), 20);
+/// assert!(divide.contains(&(ChessMove::new(Square::E2, Square::E4, None), 600)));
+/// assert_eq!(divide.iter().map(|&(_, count)| count).sum::<u64>(), 8902);
+/// ```
+pub fn perft_divide(board: &Board, depth: usize) -> Vec<(ChessMove, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+    MoveGen::new_legal(board)
+        .map(|m| (m, perft(&board.make_move_new(m), depth - 1)))
+        .collect()
+}
+
+/// Mix the depth into a position's hash, so that counts at different depths don't collide.
+fn perft_key(board: &Board, depth: usize) -> u64 {
+    board.get_hash() ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Count the leaf nodes like `perft`, but store the count of every subtree in `table`, keyed by
+/// `Board::get_hash` and the depth.  Transpositions are only counted once, which makes deep
+/// perfts much faster.  The table can be reused for other positions.
+///
+/// ```
+/// use minorhacks_chess::{perft, perft_hashed, Board, CacheTable};
+///
+/// let board = Board::default();
+/// let mut table = CacheTable::new(1 << 16, 0);
+/// assert_eq!(perft_hashed(&board, 4, &mut table), 197281);
+/// assert_eq!(perft_hashed(&board, 4, &mut table), perft(&board, 4));
+/// ```
+pub fn perft_hashed(board: &Board, depth: usize, table: &mut CacheTable<u64>) -> u64 {
+    if depth <= 1 {
+        return perft(board, depth);
+    }
+
+    let key = perft_key(board, depth);
+    if let Some(count) = table.get(key) {
+        return count;
+    }
+
+    let count = MoveGen::new_legal(board)
+        .map(|m| perft_hashed(&board.make_move_new(m), depth - 1, table))
+        .sum();
+    table.add(key, count);
+    count
+}
+
+/// Count the leaf nodes like `perft`, splitting the moves at the root across `threads` threads.
+/// No more threads are started than there are moves at the root.
+///
+/// ```
+/// use minorhacks_chess::{perft_parallel, Board};
+///
+/// let board = Board::default();
+/// assert_eq!(perft_parallel(&board, 4, 4), 197281);
+/// ```
+pub fn perft_parallel(board: &Board, depth: usize, threads: usize) -> u64 {
+    if depth <= 1 || threads <= 1 {
+        return perft(board, depth);
+    }
+
+    let moves = MoveGen::new_legal(board);
+    let threads = cmp::min(threads, moves.len());
+    let mut work = vec![vec![]; threads];
+    for (i, m) in moves.enumerate() {
+        work[i % threads].push(board.make_move_new(m));
+    }
+
+    work.into_iter()
+        .filter(|boards| !boards.is_empty())
+        .map(|boards: Vec<Board>| {
+            thread::spawn(move || boards.iter().map(|b| perft(b, depth - 1)).sum::<u64>())
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().expect("perft thread panicked"))
+        .sum()
+}
+
+/// Detailed counts of the leaf nodes of a perft, as published for the usual test positions.
+///
+/// Every count but `nodes` is about the move that reached the leaf: for example, `captures` is
+/// the number of leaves reached by a capture.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    nodes: u64,
+    captures: u64,
+    en_passants: u64,
+    castles: u64,
+    promotions: u64,
+    checks: u64,
+    discovered_checks: u64,
+    double_checks: u64,
+    checkmates: u64,
+}
+
+impl PerftStats {
+    /// Count the leaf nodes of the legal move tree `depth` plies deep, along with the kinds of
+    /// moves that reach them.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, PerftStats};
+    ///
+    /// let stats = PerftStats::new(&Board::default(), 4);
+    /// assert_eq!(stats.get_nodes(), 197281);
+    /// assert_eq!(stats.get_captures(), 1576);
+    /// assert_eq!(stats.get_checks(), 469);
+    /// assert_eq!(stats.get_checkmates(), 8);
+    /// ```
+    pub fn new(board: &Board, depth: usize) -> PerftStats {
+        let mut stats = PerftStats::default();
+        if depth == 0 {
+            stats.nodes = 1;
+        } else {
+            stats.add_subtree(board, depth);
+        }
+        stats
+    }
+
+    fn add_subtree(&mut self, board: &Board, depth: usize) {
+        for m in MoveGen::new_legal(board) {
+            let after = board.make_move_new(m);
+            if depth > 1 {
+                self.add_subtree(&after, depth - 1);
+                continue;
+            }
+
+            self.nodes += 1;
+            if board.is_capture(m) {
+                self.captures += 1;
+            }
+            if board.is_en_passant(m) {
+                self.en_passants += 1;
+            }
+            if board.is_castling(m) {
+                self.castles += 1;
+            }
+            if m.get_promotion().is_some() {
+                self.promotions += 1;
+            }
+
+            let checkers = *after.checkers();
+            if checkers != EMPTY {
+                // the squares the moving pieces landed on, including the rook when castling
+                let color = board.side_to_move();
+                let landed = after.color_combined(color) & !board.color_combined(color);
+
+                self.checks += 1;
+                if checkers & !landed != EMPTY {
+                    self.discovered_checks += 1;
+                }
+                if checkers.popcnt() > 1 {
+                    self.double_checks += 1;
+                }
+                if MoveGen::new_legal(&after).len() == 0 {
+                    self.checkmates += 1;
+                }
+            }
+        }
+    }
+
+    /// Get the number of leaf nodes.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Get the number of leaves reached by a capture, including en passant.
+    pub fn get_captures(&self) -> u64 {
+        self.captures
+    }
+
+    /// Get the number of leaves reached by an en passant capture.
+    pub fn get_en_passants(&self) -> u64 {
+        self.en_passants
+    }
+
+    /// Get the number of leaves reached by castling.
+    pub fn get_castles(&self) -> u64 {
+        self.castles
+    }
+
+    /// Get the number of leaves reached by a promotion.
+    pub fn get_promotions(&self) -> u64 {
+        self.promotions
+    }
+
+    /// Get the number of leaves where the side to move is in check.
+    pub fn get_checks(&self) -> u64 {
+        self.checks
+    }
+
+    /// Get the number of checks given by a piece other than the one that moved.
+    pub fn get_discovered_checks(&self) -> u64 {
+        self.discovered_checks
+    }
+
+    /// Get the number of checks given by two pieces at once.
+    pub fn get_double_checks(&self) -> u64 {
+        self.double_checks
+    }
+
+    /// Get the number of leaves where the side to move is checkmated.
+    pub fn get_checkmates(&self) -> u64 {
+        self.checkmates
+    }
+}
+
+impl Add for PerftStats {
+    type Output = PerftStats;
+
+    fn add(mut self, other: PerftStats) -> PerftStats {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: PerftStats) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passants += other.en_passants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.discovered_checks += other.discovered_checks;
+        self.double_checks += other.double_checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+#[cfg(test)]
+use std::str::FromStr;
+
+#[cfg(test)]
+fn check_perft_stats(fen: &str, depth: usize, expected: [u64; 9]) {
+    let board = Board::from_str(fen).unwrap();
+    let stats = PerftStats::new(&board, depth);
+    let actual = [
+        stats.get_nodes(),
+        stats.get_captures(),
+        stats.get_en_passants(),
+        stats.get_castles(),
+        stats.get_promotions(),
+        stats.get_checks(),
+        stats.get_discovered_checks(),
+        stats.get_double_checks(),
+        stats.get_checkmates(),
+    ];
+    assert_eq!(actual, expected, "{}", fen);
+}
+
+#[test]
+fn test_perft_stats() {
+    check_perft_stats(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        4,
+        [197281, 1576, 0, 0, 0, 469, 0, 0, 8],
+    );
+    check_perft_stats(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        3,
+        [97862, 17102, 45, 3162, 0, 993, 0, 0, 1],
+    );
+    check_perft_stats(
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        4,
+        [43238, 3348, 123, 0, 0, 1680, 106, 0, 17],
+    );
+
+    let stats = PerftStats::new(&Board::default(), 1) + PerftStats::new(&Board::default(), 0);
+    assert_eq!(stats.get_nodes(), 21);
+}
+
+#[test]
+fn test_perft_variants() {
+    let mut table = CacheTable::new(1 << 12, 0);
+    for fen in [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ]
+    .iter()
+    {
+        let board = Board::from_str(fen).unwrap();
+        for depth in 0..4 {
+            let expected = if depth == 0 {
+                1
+            } else {
+                MoveGen::movegen_perft_test(&board, depth) as u64
+            };
+
+            assert_eq!(perft(&board, depth), expected);
+            assert_eq!(perft_hashed(&board, depth, &mut table), expected);
+            assert_eq!(perft_parallel(&board, depth, 3), expected);
+            assert_eq!(perft_parallel(&board, depth, 1000), expected);
+            assert_eq!(
+                perft_divide(&board, depth)
+                    .iter()
+                    .map(|&(_, count)| count)
+                    .sum::<u64>(),
+                if depth == 0 { 0 } else { expected }
+            );
+        }
+    }
+}
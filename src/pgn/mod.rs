@@ -0,0 +1,7 @@
+//! Reading and writing games in PGN (Portable Game Notation).
+
+mod reader;
+pub use self::reader::*;
+
+mod writer;
+pub use self::writer::*;
@@ -0,0 +1,723 @@
+use crate::board::{Board, BoardStatus};
+use crate::chess_move::ChessMove;
+use crate::color::Color;
+use crate::error::Error;
+use crate::game::Game;
+use crate::move_tree::{split_commands, MoveNode};
+use std::io::BufRead;
+use std::str::FromStr;
+
+/// Read games from PGN text, one at a time.
+///
+/// The reader works through its input line by line, so it can be used on large multi-game files
+/// without loading them into memory.  Each game is returned as a `Game`, with its tags, comments,
+/// NAGs, commands such as `[%clk 0:03:00]` and (optionally) variations.  `SetUp` and `FEN` tags
+/// are used for the starting position.
+///
+/// PGN only records the result of a game, not how it ended.  If a game ends with a decisive
+/// result but no checkmate, it is recorded as a resignation by the losing side.  A drawn game that
+/// can't be claimed as a draw is recorded as an accepted draw offer.
+///
+/// ```
+/// use minorhacks_chess::{GameResult, PgnReader};
+///
+/// let pgn = r#"[Event "Casual game"]
+/// [White "Alice"]
+/// [Black "Bob"]
+/// [Result "0-1"]
+///
+/// 1. f3 e5 2. g4 {A blunder} Qh4# 0-1
+///
+/// [Event "Casual game"]
+/// [White "Bob"]
+/// [Black "Alice"]
+/// [Result "1-0"]
+///
+/// 1. e4 $1 e5 (1... c5) 2. Nf3 1-0
+/// "#;
+///
+/// let games = PgnReader::new(pgn.as_bytes())
+///     .collect::<Result<Vec<_>, _>>()
+///     .expect("Valid PGN");
+///
+/// assert_eq!(games.len(), 2);
+/// assert_eq!(games[0].tag("White"), Some("Alice"));
+/// assert_eq!(games[0].result(), Some(GameResult::BlackCheckmates));
+/// assert_eq!(games[0].mainline()[2].comments_after(), &["A blunder".to_owned()]);
+/// assert_eq!(games[1].result(), Some(GameResult::BlackResigns));
+/// assert_eq!(games[1].mainline()[0].nags(), &[1]);
+/// ```
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line: Vec<char>,
+    line_number: usize,
+    column: usize,
+    eof: bool,
+    keep_variations: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    /// Create a new `PgnReader` that reads from `reader`.
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            reader,
+            line: vec![],
+            line_number: 0,
+            column: 0,
+            eof: false,
+            keep_variations: false,
+        }
+    }
+
+    /// Should variations be kept?  By default, they are skipped.
+    ///
+    /// ```
+    /// use minorhacks_chess::{ChessMove, PgnReader, Square};
+    ///
+    /// let pgn = "1. e4 (1. d4 d5 (1... Nf6)) e5 *";
+    ///
+    /// let game = PgnReader::new(pgn.as_bytes())
+    ///     .keep_variations(true)
+    ///     .next()
+    ///     .expect("One game")
+    ///     .expect("Valid PGN");
+    ///
+    /// let variation = &game.mainline()[0].variations()[0];
+    /// assert_eq!(variation.len(), 2);
+    /// assert_eq!(variation[0].get_move(), ChessMove::new(Square::D2, Square::D4, None));
+    /// assert_eq!(
+    ///     variation[1].variations()[0][0].get_move(),
+    ///     ChessMove::new(Square::G8, Square::F6, None)
+    /// );
+    /// ```
+    pub fn keep_variations(&mut self, keep_variations: bool) -> &mut Self {
+        self.keep_variations = keep_variations;
+        self
+    }
+
+    /// Read the next line of input, skipping lines escaped with `%`.  Returns false at the end of
+    /// the input.
+    fn next_line(&mut self) -> Result<bool, Error> {
+        loop {
+            let mut line = String::new();
+            let read = self.reader.read_line(&mut line).map_err(|e| {
+                self.eof = true;
+                Error::IoFailure {
+                    message: e.to_string(),
+                }
+            })?;
+            if read == 0 {
+                self.eof = true;
+                self.line.clear();
+                self.column = 0;
+                return Ok(false);
+            }
+
+            self.line_number += 1;
+            if line.starts_with('%') {
+                continue;
+            }
+
+            self.line = line.trim_end_matches(&['\r', '\n'][..]).chars().collect();
+            self.line.push('\n');
+            self.column = 0;
+            return Ok(true);
+        }
+    }
+
+    /// Look at the next character, without consuming it.
+    fn peek(&mut self) -> Result<Option<char>, Error> {
+        while self.column >= self.line.len() {
+            if self.eof || !self.next_line()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.line[self.column]))
+    }
+
+    /// Consume the next character.
+    fn next_char(&mut self) -> Result<Option<char>, Error> {
+        let result = self.peek()?;
+        if result.is_some() {
+            self.column += 1;
+        }
+        Ok(result)
+    }
+
+    /// The line and column of the next character, both starting at 1.
+    fn location(&self) -> (usize, usize) {
+        (self.line_number, self.column + 1)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        while let Some(c) = self.peek()? {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.column += 1;
+        }
+        Ok(())
+    }
+
+    /// Read characters for as long as `f` accepts them.
+    fn read_while<F: Fn(char) -> bool>(&mut self, f: F) -> Result<String, Error> {
+        let mut result = String::new();
+        while let Some(c) = self.peek()? {
+            if !f(c) {
+                break;
+            }
+            result.push(c);
+            self.column += 1;
+        }
+        Ok(result)
+    }
+
+    /// Read everything up to (and including) `end`, returning the text before it.
+    fn read_until(&mut self, end: char, what: &str) -> Result<String, Error> {
+        let (line, column) = self.location();
+        let mut result = String::new();
+        loop {
+            match self.next_char()? {
+                Some(c) if c == end => return Ok(result),
+                Some(c) => result.push(c),
+                None => return Err(invalid_pgn(line, column, format!("unterminated {}", what))),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        let (line, column) = self.location();
+        match self.next_char()? {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(invalid_pgn(
+                line,
+                column,
+                format!("expected '{}', found '{}'", expected, c),
+            )),
+            None => Err(invalid_pgn(
+                line,
+                column,
+                format!("expected '{}', found the end of the input", expected),
+            )),
+        }
+    }
+
+    /// Read a tag pair such as `[Event "Casual game"]`.
+    fn read_tag(&mut self) -> Result<(String, String), Error> {
+        self.expect('[')?;
+        self.skip_whitespace()?;
+
+        let (line, column) = self.location();
+        let name = self.read_while(|c| c.is_ascii_alphanumeric() || c == '_')?;
+        if name.is_empty() {
+            return Err(invalid_pgn(line, column, "expected a tag name".to_owned()));
+        }
+
+        self.skip_whitespace()?;
+        let (line, column) = self.location();
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.next_char()? {
+                Some('"') => break,
+                Some('\\') => match self.next_char()? {
+                    Some(c) if c != '\n' => value.push(c),
+                    _ => return Err(invalid_pgn(line, column, "unterminated string".to_owned())),
+                },
+                Some('\n') | None => {
+                    return Err(invalid_pgn(line, column, "unterminated string".to_owned()))
+                }
+                Some(c) => value.push(c),
+            }
+        }
+
+        self.skip_whitespace()?;
+        self.expect(']')?;
+        Ok((name, value))
+    }
+
+    /// Skip over a variation, including any nested variations.
+    fn skip_variation(&mut self) -> Result<(), Error> {
+        let (line, column) = self.location();
+        self.expect('(')?;
+
+        let mut depth = 1;
+        loop {
+            match self.next_char()? {
+                Some('(') => depth += 1,
+                Some(')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                // parentheses in comments don't count
+                Some('{') => {
+                    self.read_until('}', "comment")?;
+                }
+                Some(';') => {
+                    self.read_until('\n', "comment")?;
+                }
+                Some(_) => {}
+                None => {
+                    return Err(invalid_pgn(
+                        line,
+                        column,
+                        "unterminated variation".to_owned(),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Read a line of moves starting from `board`, along with their annotations, until the end
+    /// of the game or, for a variation opened at `open`, the `)` that closes it.  Returns the
+    /// result token, if there was one.
+    fn read_line(
+        &mut self,
+        mut board: Board,
+        nodes: &mut Vec<MoveNode>,
+        open: Option<(usize, usize)>,
+    ) -> Result<Option<String>, Error> {
+        // the position before the last move, where its variations start
+        let mut previous = board;
+        // comments before the first move of the line
+        let mut comments_before = vec![];
+
+        loop {
+            self.skip_whitespace()?;
+            let (line, column) = self.location();
+            let c = match self.peek()? {
+                Some(c) => c,
+                None => match open {
+                    Some((line, column)) => {
+                        return Err(invalid_pgn(
+                            line,
+                            column,
+                            "unterminated variation".to_owned(),
+                        ))
+                    }
+                    None => return Ok(None),
+                },
+            };
+
+            match c {
+                // a new game, without a result token for this one
+                '[' if self.column == 0 && open.is_none() => return Ok(None),
+                '{' | ';' => {
+                    self.column += 1;
+                    let end = if c == '{' { '}' } else { '\n' };
+                    let (text, commands) = split_commands(&self.read_until(end, "comment")?);
+                    match nodes.last_mut() {
+                        Some(node) => {
+                            node.commands_mut().extend(commands);
+                            if !text.is_empty() {
+                                node.comments_after_mut().push(text);
+                            }
+                        }
+                        None if !text.is_empty() => comments_before.push(text),
+                        None => {}
+                    }
+                }
+                '(' if !self.keep_variations => {
+                    self.skip_variation()?;
+                }
+                '(' => {
+                    self.column += 1;
+                    if nodes.is_empty() {
+                        return Err(invalid_pgn(
+                            line,
+                            column,
+                            "variation before the first move".to_owned(),
+                        ));
+                    }
+
+                    let mut variation = vec![];
+                    self.read_line(previous, &mut variation, Some((line, column)))?;
+                    if let (Some(node), false) = (nodes.last_mut(), variation.is_empty()) {
+                        node.push_variation(variation);
+                    }
+                }
+                ')' if open.is_some() => {
+                    self.column += 1;
+                    return Ok(None);
+                }
+                '$' => {
+                    self.column += 1;
+                    let nag = self.read_while(|c| c.is_ascii_digit())?;
+                    let nag = u8::from_str(&nag)
+                        .map_err(|_| invalid_pgn(line, column, "invalid NAG".to_owned()))?;
+                    if let Some(node) = nodes.last_mut() {
+                        node.nags_mut().push(nag);
+                    }
+                }
+                '!' | '?' => {
+                    let suffix = self.read_while(|c| c == '!' || c == '?')?;
+                    let nag = match suffix.as_str() {
+                        "!" => 1,
+                        "?" => 2,
+                        "!!" => 3,
+                        "??" => 4,
+                        "!?" => 5,
+                        "?!" => 6,
+                        _ => {
+                            return Err(invalid_pgn(
+                                line,
+                                column,
+                                format!("invalid move suffix \"{}\"", suffix),
+                            ))
+                        }
+                    };
+                    if let Some(node) = nodes.last_mut() {
+                        node.nags_mut().push(nag);
+                    }
+                }
+                '*' if open.is_none() => {
+                    self.column += 1;
+                    return Ok(Some("*".to_owned()));
+                }
+                '.' => self.column += 1,
+                c if c.is_ascii_alphanumeric() => {
+                    let token =
+                        self.read_while(|c| c.is_ascii_alphanumeric() || "_+#=:-/".contains(c))?;
+
+                    if token == "1-0" || token == "0-1" || token == "1/2-1/2" {
+                        if open.is_some() {
+                            return Err(invalid_pgn(
+                                line,
+                                column,
+                                "result inside a variation".to_owned(),
+                            ));
+                        }
+                        return Ok(Some(token));
+                    }
+
+                    // move numbers
+                    if token.chars().all(|c| c.is_ascii_digit()) {
+                        continue;
+                    }
+
+                    // a dead position doesn't end the game when reading, see `Game::result`
+                    if let BoardStatus::Checkmate | BoardStatus::Stalemate = board.status() {
+                        return Err(invalid_pgn(
+                            line,
+                            column,
+                            format!("move \"{}\" after the end of the game", token),
+                        ));
+                    }
+
+                    // castling is sometimes written with zeros
+                    let san = if token.starts_with('0') {
+                        token.replace('0', "O")
+                    } else {
+                        token.clone()
+                    };
+
+                    let chess_move = ChessMove::from_san(&board, &san).map_err(|_| {
+                        invalid_pgn(line, column, format!("invalid move \"{}\"", token))
+                    })?;
+
+                    let mut node = MoveNode::new(chess_move);
+                    if nodes.is_empty() {
+                        node.comments_before_mut().append(&mut comments_before);
+                    }
+                    nodes.push(node);
+                    previous = board;
+                    board = board.make_move_new(chess_move);
+                }
+                c => {
+                    return Err(invalid_pgn(
+                        line,
+                        column,
+                        format!("unexpected character '{}'", c),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Read the next game.  Returns `None` at the end of the input.
+    fn read_game(&mut self) -> Result<Option<Game>, Error> {
+        self.skip_whitespace()?;
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut tags = vec![];
+        let mut fen = None;
+        while self.peek()? == Some('[') {
+            let (line, column) = self.location();
+            let (name, value) = self.read_tag()?;
+            if name == "FEN" {
+                fen = Some((value.clone(), line, column));
+            }
+            tags.push((name, value));
+            self.skip_whitespace()?;
+        }
+
+        let mut game = match fen {
+            Some((fen, line, column)) => Game::from_str(&fen)
+                .map_err(|_| invalid_pgn(line, column, format!("invalid FEN \"{}\"", fen)))?,
+            None => Game::new(),
+        };
+        for (name, value) in tags.iter() {
+            game.set_tag(name, value);
+        }
+
+        let mut nodes = vec![];
+        let result = self.read_line(game.start_position(), &mut nodes, None)?;
+        for node in nodes {
+            game.make_move_node(node);
+        }
+
+        if let Some(result) = result {
+            if game.tag("Result").is_none() {
+                game.set_tag("Result", &result);
+            }
+
+            if game.result().is_none() {
+                match result.as_str() {
+                    "1-0" => {
+                        game.resign(Color::Black);
+                    }
+                    "0-1" => {
+                        game.resign(Color::White);
+                    }
+                    // claim the draw if possible, and otherwise agree to it
+                    "1/2-1/2" if !game.declare_draw() => {
+                        game.offer_draw(game.side_to_move());
+                        game.accept_draw();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Some(game))
+    }
+
+    /// After an error, skip ahead to the tags of the next game: a line starting with `[` after a
+    /// blank line.
+    fn skip_game(&mut self) {
+        let mut blank = false;
+        while !self.eof {
+            match self.next_line() {
+                Ok(true) => {
+                    if blank && self.line[0] == '[' {
+                        return;
+                    }
+                    blank = self.line.iter().all(|c| c.is_whitespace());
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, Error>;
+
+    fn next(&mut self) -> Option<Result<Game, Error>> {
+        match self.read_game() {
+            Ok(Some(game)) => Some(Ok(game)),
+            Ok(None) => None,
+            Err(e) => {
+                self.skip_game();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn invalid_pgn(line: usize, column: usize, message: String) -> Error {
+    Error::InvalidPgn {
+        line,
+        column,
+        message,
+    }
+}
+
+#[cfg(test)]
+use crate::game::{Action, GameResult};
+#[cfg(test)]
+use crate::move_tree::{Command, Evaluation};
+#[cfg(test)]
+use std::time::Duration;
+
+#[cfg(test)]
+fn read_all(pgn: &str) -> Vec<Result<Game, Error>> {
+    PgnReader::new(pgn.as_bytes()).collect()
+}
+
+#[test]
+fn test_pgn_tags() {
+    let pgn = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+[Annotator "Someone \"quoted\" \\ here"]
+
+1. e4 e5 1/2-1/2
+"#;
+    let games = read_all(pgn);
+    assert_eq!(games.len(), 1);
+    let game = games[0].as_ref().unwrap();
+
+    assert_eq!(game.tags().len(), 8);
+    assert_eq!(game.tag("Site"), Some("Belgrade, Serbia JUG"));
+    assert_eq!(game.tag("Annotator"), Some("Someone \"quoted\" \\ here"));
+    assert_eq!(game.result(), Some(GameResult::DrawAccepted));
+}
+
+#[test]
+fn test_pgn_setup() {
+    let pgn = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b Q - 0 40"]
+
+40... Kd7 41. O-O-O+ Ke7 42. Rd2 *
+"#;
+    let game = read_all(pgn).remove(0).unwrap();
+    assert_eq!(
+        game.start_position(),
+        Board::from_str("4k3/8/8/8/8/8/8/R3K3 b Q - 0 40").unwrap()
+    );
+    assert_eq!(
+        game.current_position(),
+        Board::from_str("8/4k3/8/8/8/8/3R4/2K5 b - - 4 42").unwrap()
+    );
+    assert_eq!(game.result(), None);
+    assert_eq!(game.tag("Result"), Some("*"));
+}
+
+#[test]
+fn test_pgn_comments_and_nags() {
+    let pgn = "% this line is ignored
+{Before the game} 1. e4 {[%clk 0:03:00]} e5!? ; to the end of the line
+2. 0-0?? {is not possible}";
+    let games = read_all(pgn);
+    let error = games[0].as_ref().unwrap_err();
+    match error {
+        Error::InvalidPgn { line, column, .. } => assert_eq!((*line, *column), (3, 4)),
+        _ => panic!("unexpected error {}", error),
+    }
+
+    let pgn = "{Before the game} 1. e4 {[%clk 0:03:00]} e5!? ; to the end of the line
+2. Nf3 $14 Nc6 3. Bc4 {Italian [%eval 0.25] game} Nf6 4. 0-0 $2 *";
+    let game = read_all(pgn).remove(0).unwrap();
+    let mainline = game.mainline();
+    assert_eq!(mainline.len(), 7);
+    assert_eq!(game.actions().len(), 7);
+
+    assert_eq!(
+        mainline[0].comments_before(),
+        &["Before the game".to_owned()]
+    );
+    assert!(mainline[0].comments_after().is_empty());
+    assert_eq!(
+        mainline[0].commands(),
+        &[Command::Clock(Duration::from_secs(180))]
+    );
+    assert_eq!(mainline[1].nags(), &[5]);
+    assert_eq!(
+        mainline[1].comments_after(),
+        &["to the end of the line".to_owned()]
+    );
+    assert_eq!(mainline[2].nags(), &[14]);
+    assert_eq!(mainline[4].comments_after(), &["Italian game".to_owned()]);
+    assert_eq!(
+        mainline[4].commands(),
+        &[Command::Eval(Evaluation::Centipawns(25))]
+    );
+    assert_eq!(mainline[6].nags(), &[2]);
+}
+
+#[test]
+fn test_pgn_variations() {
+    let pgn = "1. e4 e5 (1... c5 {(Sicilian)} 2. Nf3 (2. c3) d6) (1... e6 $1) 2. Nf3 *";
+
+    let game = read_all(pgn).remove(0).unwrap();
+    assert!(game.mainline().iter().all(|n| n.variations().is_empty()));
+    assert_eq!(game.actions().len(), 3);
+
+    let game = PgnReader::new(pgn.as_bytes())
+        .keep_variations(true)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(game.actions().len(), 3);
+
+    let variations = game.mainline()[1].variations();
+    assert_eq!(variations.len(), 2);
+    assert_eq!(
+        variations[0]
+            .iter()
+            .map(|n| n.get_move().to_string())
+            .collect::<Vec<_>>(),
+        vec!["c7c5", "g1f3", "d7d6"]
+    );
+    assert_eq!(
+        variations[0][0].comments_after(),
+        &["(Sicilian)".to_owned()]
+    );
+    assert_eq!(
+        game.node(&[1, 0, 1, 0, 0]).unwrap().get_move().to_string(),
+        "c2c3"
+    );
+    assert_eq!(variations[1][0].nags(), &[1]);
+
+    // variations must be legal, closed, and follow a move
+    for pgn in [
+        "1. e4 e5 (1... Nf3) *",
+        "1. e4 e5 (1... c5 *",
+        "(1. d4) 1. e4 *",
+        "1. e4 (1. d4 1-0) *",
+    ]
+    .iter()
+    {
+        let result = PgnReader::new(pgn.as_bytes())
+            .keep_variations(true)
+            .next()
+            .unwrap();
+        assert!(result.is_err(), "{}", pgn);
+    }
+}
+
+#[test]
+fn test_pgn_errors() {
+    let pgn = r#"[Event "First"]
+
+1. e4 e5 2. Ke3 *
+
+[Event "Second"]
+
+1. d4 ) *
+
+[Event "Third"]
+[White "Unterminated]
+
+1. e4 *
+
+[Event "Fourth"]
+
+1. d4 d5 1-0
+"#;
+    let games = read_all(pgn);
+    assert_eq!(games.len(), 4);
+
+    let positions = games[0..3]
+        .iter()
+        .map(|g| match g {
+            Err(Error::InvalidPgn { line, column, .. }) => (*line, *column),
+            _ => panic!("expected an error"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![(3, 13), (7, 7), (10, 8)]);
+
+    let game = games[3].as_ref().unwrap();
+    assert_eq!(game.tag("Event"), Some("Fourth"));
+    assert_eq!(game.result(), Some(GameResult::BlackResigns));
+    assert_eq!(game.actions().last(), Some(&Action::Resign(Color::Black)));
+}
@@ -0,0 +1,336 @@
+use crate::board::Board;
+use crate::color::Color;
+use crate::error::Error;
+use crate::game::Game;
+use crate::move_tree::MoveNode;
+use std::io::Write;
+
+/// The longest line written in the movetext.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// The Seven Tag Roster, which is always written first, and in this order.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Write games as PGN, in the standard export format.
+///
+/// The Seven Tag Roster is always written, filling in `?` for missing tags, followed by the other
+/// tags of the game.  `SetUp` and `FEN` tags are added for games that don't start from the
+/// initial position.  The result is always taken from `Game::result()`.
+///
+/// ```
+/// use minorhacks_chess::{ChessMove, Color, Game, PgnWriter, Square};
+///
+/// let mut game = Game::new();
+/// game.set_tag("White", "Alice");
+/// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+/// game.make_move(ChessMove::new(Square::E7, Square::E5, None));
+/// game.resign(Color::Black);
+///
+/// let mut pgn = vec![];
+/// PgnWriter::new(&mut pgn).write_game(&game).expect("Valid game");
+///
+/// assert_eq!(String::from_utf8(pgn).unwrap(), r#"[Event "?"]
+/// [Site "?"]
+/// [Date "????.??.??"]
+/// [Round "?"]
+/// [White "Alice"]
+/// [Black "?"]
+/// [Result "1-0"]
+///
+/// 1. e4 e5 1-0
+/// "#);
+/// ```
+pub struct PgnWriter<W: Write> {
+    writer: W,
+    games: usize,
+}
+
+impl<W: Write> PgnWriter<W> {
+    /// Create a new `PgnWriter` that writes to `writer`.
+    pub fn new(writer: W) -> PgnWriter<W> {
+        PgnWriter { writer, games: 0 }
+    }
+
+    /// Write a game.  Games after the first are separated by a blank line.
+    pub fn write_game(&mut self, game: &Game) -> Result<(), Error> {
+        let mut pgn = String::new();
+        if self.games > 0 {
+            pgn.push('\n');
+        }
+        pgn.push_str(&game_to_pgn(game));
+
+        self.writer
+            .write_all(pgn.as_bytes())
+            .map_err(|e| Error::IoFailure {
+                message: e.to_string(),
+            })?;
+        self.games += 1;
+        Ok(())
+    }
+
+    /// Get back the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Convert a game to PGN.
+pub(crate) fn game_to_pgn(game: &Game) -> String {
+    let result = result_token(game);
+    let start = game.start_position();
+
+    let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+        .iter()
+        .map(|&(name, default)| {
+            let value = if name == "Result" {
+                result
+            } else {
+                game.tag(name).unwrap_or(default)
+            };
+            (name.to_owned(), value.to_owned())
+        })
+        .collect();
+
+    if start != Board::default() {
+        tags.push(("SetUp".to_owned(), "1".to_owned()));
+        tags.push(("FEN".to_owned(), start.to_string()));
+        if start.is_chess960() && game.tag("Variant").is_none() {
+            tags.push(("Variant".to_owned(), "Chess960".to_owned()));
+        }
+    }
+
+    for (name, value) in game.tags() {
+        if !tags.iter().any(|(n, _)| n == name) {
+            tags.push((name.clone(), value.clone()));
+        }
+    }
+
+    let mut pgn = String::new();
+    for (name, value) in tags.iter() {
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+    pgn.push('\n');
+
+    let mut tokens = movetext_tokens(game);
+    tokens.push(result.to_owned());
+    pgn.push_str(&wrap(&tokens));
+    pgn.push('\n');
+    pgn
+}
+
+/// Get the PGN result token for a game.
+fn result_token(game: &Game) -> &'static str {
+    match game.result() {
+        None => "*",
+        Some(result) => match result.winner() {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        },
+    }
+}
+
+/// Escape a tag value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Split the moves and annotations of a game into the tokens of the movetext, which can be
+/// separated by any whitespace.
+fn movetext_tokens(game: &Game) -> Vec<String> {
+    let mut tokens = vec![];
+    line_tokens(game.start_position(), game.mainline(), &mut tokens);
+    tokens
+}
+
+/// Add the tokens for a line of moves played from `board`, with their annotations and variations.
+fn line_tokens(mut board: Board, line: &[MoveNode], tokens: &mut Vec<String>) {
+    // black moves need a move number at the start, and after any comment or variation
+    let mut needs_number = true;
+
+    for node in line.iter() {
+        for comment in node.comments_before().iter() {
+            comment_tokens(comment, tokens);
+            needs_number = true;
+        }
+
+        if board.side_to_move() == Color::White {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if needs_number {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+        needs_number = false;
+
+        tokens.push(node.get_move().to_san(&board));
+        for nag in node.nags().iter() {
+            tokens.push(format!("${}", nag));
+        }
+
+        // commands share a comment with the first comment after the move
+        let mut comments = node.comments_after().to_vec();
+        if !node.commands().is_empty() {
+            let mut commands = node
+                .commands()
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>();
+            if !comments.is_empty() {
+                commands.push(comments.remove(0));
+            }
+            comments.insert(0, commands.join(" "));
+        }
+        for comment in comments.iter() {
+            comment_tokens(comment, tokens);
+            needs_number = true;
+        }
+
+        for variation in node.variations().iter() {
+            let first = tokens.len();
+            line_tokens(board, variation, tokens);
+            tokens[first].insert(0, '(');
+            if let Some(last) = tokens.last_mut() {
+                last.push(')');
+            }
+            needs_number = true;
+        }
+
+        board = board.make_move_new(node.get_move());
+    }
+}
+
+fn comment_tokens(comment: &str, tokens: &mut Vec<String>) {
+    let comment = format!("{{{}}}", comment.replace('}', ")"));
+    tokens.extend(comment.split_whitespace().map(|s| s.to_owned()));
+}
+
+/// Join tokens with spaces, starting a new line instead when a line would get too long.
+fn wrap(tokens: &[String]) -> String {
+    let mut result = String::new();
+    let mut line_length = 0;
+    for token in tokens.iter() {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            result.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            result.push(' ');
+            line_length += 1;
+        }
+        result.push_str(token);
+        line_length += token.len();
+    }
+    result
+}
+
+#[cfg(test)]
+use crate::chess_move::ChessMove;
+#[cfg(test)]
+use crate::pgn::PgnReader;
+#[cfg(test)]
+use std::str::FromStr;
+
+#[test]
+fn test_pgn_round_trip() {
+    let pgn = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+[Annotator "Someone \"quoted\""]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6 4.
+Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. c4 c6
+12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5 Nxe4 18.
+Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24.
+Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30.
+a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 35. Ra7 g6 36. Ra6+ Kc5
+37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6
+1/2-1/2
+"#;
+    let game = PgnReader::new(pgn.as_bytes()).next().unwrap().unwrap();
+    assert_eq!(game.to_pgn(), pgn);
+}
+
+#[test]
+fn test_pgn_black_to_move() {
+    let mut game = Game::from_str("4k3/8/8/8/8/8/8/R3K3 b Q - 0 40").unwrap();
+    game.set_tag("Event", "Endgame");
+    for san in ["Kd7", "O-O-O+", "Ke7", "Rd2"].iter() {
+        let chess_move = ChessMove::from_san(&game.current_position(), san).unwrap();
+        game.make_move(chess_move);
+    }
+
+    assert_eq!(
+        game.to_pgn(),
+        r#"[Event "Endgame"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b Q - 0 40"]
+
+40... Kd7 41. O-O-O+ Ke7 42. Rd2 *
+"#
+    );
+}
+
+#[test]
+fn test_pgn_wrapping() {
+    let mut game = Game::new();
+    game.make_move(ChessMove::from_str("e2e4").unwrap());
+    game.make_move(ChessMove::from_str("e7e5").unwrap());
+    game.node_mut(&[0]).unwrap().comments_before_mut().push(
+        "A very long comment that needs to be split over more than one line because it does not fit"
+            .to_owned(),
+    );
+    game.node_mut(&[0]).unwrap().nags_mut().push(1);
+
+    let pgn = game.to_pgn();
+    let movetext = pgn.split("\n\n").nth(1).unwrap();
+    assert_eq!(
+        movetext,
+        "{A very long comment that needs to be split over more than one line because it
+does not fit} 1. e4 $1 e5 *
+"
+    );
+    assert!(pgn.lines().all(|line| line.len() <= 80));
+}
+
+#[test]
+fn test_pgn_variations_round_trip() {
+    let movetext = "{Start} 1. e4 $1 {[%clk 0:03:00] [%eval 0.25] Best by test} {Second comment}
+1... c5 (1... e5 2. Nf3 (2. f4 exf4 {[%cal Gd1h5]}) 2... Nc6) (1... c6) 2. Nf3
+{[%csl Rd4,Gd5]} 2... d6 *
+";
+    let pgn = format!(
+        r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+
+{}"#,
+        movetext
+    );
+
+    let game = PgnReader::new(pgn.as_bytes())
+        .keep_variations(true)
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(game.to_pgn(), pgn);
+}
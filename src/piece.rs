@@ -58,6 +58,51 @@ impl Piece {
     }
 }
 
+/// How much each piece is worth, in centipawns, for `Board::see`.  Kings are never captured, so
+/// they are worth nothing.
+///
+/// ```
+/// use minorhacks_chess::{Piece, PieceValues};
+///
+/// let mut values = PieceValues::default();
+/// assert_eq!(values.get(Piece::Rook), 500);
+/// assert_eq!(values.get(Piece::King), 0);
+///
+/// values.set(Piece::Bishop, 350);
+/// assert_eq!(values, PieceValues::new(100, 320, 350, 500, 900));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct PieceValues {
+    values: [i32; NUM_PIECES],
+}
+
+impl PieceValues {
+    /// Create a set of piece values.
+    pub fn new(pawn: i32, knight: i32, bishop: i32, rook: i32, queen: i32) -> PieceValues {
+        PieceValues {
+            values: [pawn, knight, bishop, rook, queen, 0],
+        }
+    }
+
+    /// How much is `piece` worth?
+    #[inline]
+    pub fn get(&self, piece: Piece) -> i32 {
+        self.values[piece.to_index()]
+    }
+
+    /// Change how much `piece` is worth.
+    pub fn set(&mut self, piece: Piece, value: i32) -> &mut Self {
+        self.values[piece.to_index()] = value;
+        self
+    }
+}
+
+impl Default for PieceValues {
+    fn default() -> Self {
+        PieceValues::new(100, 320, 330, 500, 900)
+    }
+}
+
 impl fmt::Display for Piece {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -0,0 +1,432 @@
+use crate::board::Board;
+use crate::chess_move::ChessMove;
+use crate::error::Error;
+use crate::file::File;
+use crate::movegen::MoveGen;
+use crate::piece::Piece;
+use crate::rank::Rank;
+use crate::square::Square;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The size, in bytes, of one entry in a Polyglot book file.
+const ENTRY_SIZE: usize = 16;
+
+/// A book move for a particular position, along with its weight.
+///
+/// ```
+/// use minorhacks_chess::{Board, ChessMove, PolyglotBook, Square};
+///
+/// // A book with a single entry: 1. e4 from the starting position, with a weight of 10
+/// let mut bytes = vec![];
+/// bytes.extend_from_slice(&0x463b96181691fc9cu64.to_be_bytes());
+/// bytes.extend_from_slice(&0x031cu16.to_be_bytes());
+/// bytes.extend_from_slice(&10u16.to_be_bytes());
+/// bytes.extend_from_slice(&0u32.to_be_bytes());
+///
+/// let book = PolyglotBook::from_bytes(&bytes).expect("Valid book");
+/// let entries = book.entries(&Board::default());
+///
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].get_move(), ChessMove::new(Square::E2, Square::E4, None));
+/// assert_eq!(entries[0].get_weight(), 10);
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PolyglotEntry {
+    chess_move: ChessMove,
+    weight: u16,
+    learn: u32,
+}
+
+impl PolyglotEntry {
+    /// The move to play.
+    #[inline]
+    pub fn get_move(&self) -> ChessMove {
+        self.chess_move
+    }
+
+    /// How often this move should be played, relative to the other moves for the same position.
+    #[inline]
+    pub fn get_weight(&self) -> u16 {
+        self.weight
+    }
+
+    /// The "learn" field of the entry.  This is not used by most books.
+    #[inline]
+    pub fn get_learn(&self) -> u32 {
+        self.learn
+    }
+}
+
+/// One entry of a book, as stored in the file.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(super) struct RawEntry {
+    pub(super) key: u64,
+    pub(super) chess_move: u16,
+    pub(super) weight: u16,
+    pub(super) learn: u32,
+}
+
+impl RawEntry {
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut result = [0u8; ENTRY_SIZE];
+        result[0..8].copy_from_slice(&self.key.to_be_bytes());
+        result[8..10].copy_from_slice(&self.chess_move.to_be_bytes());
+        result[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        result[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        result
+    }
+
+    fn from_bytes(bytes: &[u8]) -> RawEntry {
+        let mut key = [0u8; 8];
+        key.copy_from_slice(&bytes[0..8]);
+        RawEntry {
+            key: u64::from_be_bytes(key),
+            chess_move: u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn: u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        }
+    }
+}
+
+/// A Polyglot opening book, read into memory.
+///
+/// Positions are looked up with `Board::polyglot_hash()`, so this works with any book in the
+/// standard Polyglot `.bin` format.
+///
+/// ```no_run
+/// use minorhacks_chess::{Board, PolyglotBook};
+///
+/// let book = PolyglotBook::open("book.bin").expect("Valid book");
+///
+/// for entry in book.entries(&Board::default()) {
+///     println!("{} ({})", entry.get_move(), entry.get_weight());
+/// }
+///
+/// let m = book.choose_move(&Board::default(), 42);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PolyglotBook {
+    entries: Vec<RawEntry>,
+}
+
+impl PolyglotBook {
+    /// Read a Polyglot book from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<PolyglotBook, Error> {
+        let bytes = fs::read(path).map_err(|e| Error::IoFailure {
+            message: e.to_string(),
+        })?;
+        PolyglotBook::from_bytes(&bytes)
+    }
+
+    /// Read a Polyglot book from anything that implements `Read`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<PolyglotBook, Error> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|e| Error::IoFailure {
+                message: e.to_string(),
+            })?;
+        PolyglotBook::from_bytes(&bytes)
+    }
+
+    /// Read a Polyglot book from the contents of a `.bin` file.
+    ///
+    /// The entries are expected to be sorted by key, as the format requires, but books that are
+    /// not sorted are accepted as well.
+    pub fn from_bytes(bytes: &[u8]) -> Result<PolyglotBook, Error> {
+        let chunks = bytes.chunks_exact(ENTRY_SIZE);
+        if !chunks.remainder().is_empty() {
+            return Err(Error::InvalidPolyglotBook);
+        }
+
+        let mut entries: Vec<RawEntry> = chunks.map(RawEntry::from_bytes).collect();
+        entries.sort_by_key(|e| e.key);
+
+        Ok(PolyglotBook { entries })
+    }
+
+    /// Create a book from entries that are already sorted.
+    pub(super) fn from_sorted_entries(entries: Vec<RawEntry>) -> PolyglotBook {
+        PolyglotBook { entries }
+    }
+
+    /// Get the contents of a `.bin` file for this book.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in self.entries.iter() {
+            result.extend_from_slice(&entry.to_bytes());
+        }
+        result
+    }
+
+    /// Write this book, in the Polyglot `.bin` format, to anything that implements `Write`.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer
+            .write_all(&self.to_bytes())
+            .map_err(|e| Error::IoFailure {
+                message: e.to_string(),
+            })
+    }
+
+    /// Save this book to a Polyglot `.bin` file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_bytes()).map_err(|e| Error::IoFailure {
+            message: e.to_string(),
+        })
+    }
+
+    /// How many entries are in this book?
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Is this book empty?
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the book moves for a position, in the order they appear in the book (which is
+    /// normally highest weight first).
+    ///
+    /// Entries that are not legal moves in this position (for example, because of a hash
+    /// collision) are skipped.
+    pub fn entries(&self, board: &Board) -> Vec<PolyglotEntry> {
+        let key = board.polyglot_hash();
+        let start = self.entries.partition_point(|e| e.key < key);
+        let found = self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .collect::<Vec<&RawEntry>>();
+
+        if found.is_empty() {
+            return vec![];
+        }
+
+        let legal = MoveGen::new_legal(board).collect::<Vec<ChessMove>>();
+        found
+            .into_iter()
+            .filter_map(|e| {
+                let chess_move = decode_move(board, e.chess_move)?;
+                if legal.contains(&chess_move) {
+                    Some(PolyglotEntry {
+                        chess_move,
+                        weight: e.weight,
+                        learn: e.learn,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Pick a book move for a position at random, with the odds of each move proportional to
+    /// its weight.  The same `seed` always picks the same move, so pass in a different seed (for
+    /// example, from a random number generator) each time you want a new choice.
+    ///
+    /// Returns `None` if the position is not in the book.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, PolyglotBook, Square};
+    ///
+    /// // 1. e4 with a weight of 1, and 1. d4 with a weight of 0 (never played)
+    /// let mut bytes = vec![];
+    /// for &(chess_move, weight) in [(0x031cu16, 1u16), (0x02dbu16, 0u16)].iter() {
+    ///     bytes.extend_from_slice(&0x463b96181691fc9cu64.to_be_bytes());
+    ///     bytes.extend_from_slice(&chess_move.to_be_bytes());
+    ///     bytes.extend_from_slice(&weight.to_be_bytes());
+    ///     bytes.extend_from_slice(&0u32.to_be_bytes());
+    /// }
+    ///
+    /// let book = PolyglotBook::from_bytes(&bytes).expect("Valid book");
+    /// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+    ///
+    /// for seed in 0..10 {
+    ///     assert_eq!(book.choose_move(&Board::default(), seed), Some(e4));
+    /// }
+    /// ```
+    pub fn choose_move(&self, board: &Board, seed: u64) -> Option<ChessMove> {
+        let entries = self.entries(board);
+        if entries.is_empty() {
+            return None;
+        }
+
+        let random = splitmix64(seed);
+        let total: u64 = entries.iter().map(|e| e.weight as u64).sum();
+        if total == 0 {
+            // nothing is weighted, so treat every move the same
+            return Some(entries[(random % entries.len() as u64) as usize].chess_move);
+        }
+
+        let mut pick = random % total;
+        for entry in entries.iter() {
+            if pick < entry.weight as u64 {
+                return Some(entry.chess_move);
+            }
+            pick -= entry.weight as u64;
+        }
+        unreachable!();
+    }
+}
+
+/// Convert a Polyglot move to a `ChessMove`.
+///
+/// Polyglot encodes castling as the king capturing its own rook, which is already how Chess960
+/// castles are represented, but standard castles need to be converted to the king moving two
+/// squares.
+fn decode_move(board: &Board, chess_move: u16) -> Option<ChessMove> {
+    let field = |shift: u16| ((chess_move >> shift) & 7) as usize;
+
+    let source = Square::make_square(Rank::from_index(field(9)), File::from_index(field(6)));
+    let mut dest = Square::make_square(Rank::from_index(field(3)), File::from_index(field(0)));
+    let promotion = match field(12) {
+        0 => None,
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => return None,
+    };
+
+    if !board.is_chess960()
+        && board.piece_on(source) == Some(Piece::King)
+        && board.color_on(dest) == Some(board.side_to_move())
+    {
+        let file = if dest.get_file() > source.get_file() {
+            File::G
+        } else {
+            File::C
+        };
+        dest = Square::make_square(dest.get_rank(), file);
+    }
+
+    Some(ChessMove::new(source, dest, promotion))
+}
+
+/// Convert a legal `ChessMove` to a Polyglot move.  This is the reverse of `decode_move`.
+pub(super) fn encode_move(board: &Board, chess_move: ChessMove) -> u16 {
+    let source = chess_move.get_source();
+    let mut dest = chess_move.get_dest();
+
+    if !board.is_chess960()
+        && board.piece_on(source) == Some(Piece::King)
+        && (source.get_file().to_index() as i8 - dest.get_file().to_index() as i8).abs() == 2
+    {
+        let file = if dest.get_file() > source.get_file() {
+            File::H
+        } else {
+            File::A
+        };
+        dest = Square::make_square(dest.get_rank(), file);
+    }
+
+    let promotion = match chess_move.get_promotion() {
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(Piece::Queen) => 4,
+        _ => 0,
+    };
+
+    (dest.get_file().to_index() as u16)
+        | (dest.get_rank().to_index() as u16) << 3
+        | (source.get_file().to_index() as u16) << 6
+        | (source.get_rank().to_index() as u16) << 9
+        | promotion << 12
+}
+
+/// Turn a seed into a well-mixed random number.
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+use std::str::FromStr;
+
+#[cfg(test)]
+fn book_bytes(entries: &[(u64, u16, u16)]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for &(key, chess_move, weight) in entries.iter() {
+        bytes.extend_from_slice(&key.to_be_bytes());
+        bytes.extend_from_slice(&chess_move.to_be_bytes());
+        bytes.extend_from_slice(&weight.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn test_invalid_book() {
+    assert!(PolyglotBook::from_bytes(&[0; 15]).is_err());
+    assert!(PolyglotBook::from_bytes(&[]).unwrap().is_empty());
+    assert!(PolyglotBook::open("/this/file/does/not/exist.bin").is_err());
+}
+
+#[test]
+fn test_castles_and_promotions() {
+    let board = Board::from_str("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let key = board.polyglot_hash();
+
+    // e1h1, e1a1, b7a8=N, b7b8=Q and an illegal e1e3 to be skipped
+    let book = PolyglotBook::from_bytes(&book_bytes(&[
+        (key.wrapping_add(1), 0, 1),
+        (key, 0x0107, 5),
+        (key, 0x0100, 4),
+        (key, 0x1c78, 3),
+        (key, 0x4c79, 2),
+        (key, 0x0114, 1),
+        (key.wrapping_sub(1), 0, 1),
+    ]))
+    .unwrap();
+
+    let moves = book
+        .entries(&board)
+        .iter()
+        .map(|e| (e.get_move(), e.get_weight()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        moves,
+        vec![
+            (ChessMove::new(Square::E1, Square::G1, None), 5),
+            (ChessMove::new(Square::E1, Square::C1, None), 4),
+            (
+                ChessMove::new(Square::B7, Square::A8, Some(Piece::Knight)),
+                3
+            ),
+            (
+                ChessMove::new(Square::B7, Square::B8, Some(Piece::Queen)),
+                2
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_weighted_choice() {
+    let key = Board::default().polyglot_hash();
+    // e2e4 with weight 3, d2d4 with weight 1
+    let book =
+        PolyglotBook::from_bytes(&book_bytes(&[(key, 0x031c, 3), (key, 0x02db, 1)])).unwrap();
+    let e4 = ChessMove::new(Square::E2, Square::E4, None);
+
+    let picks = (0..1000)
+        .filter(|seed| book.choose_move(&Board::default(), *seed) == Some(e4))
+        .count();
+    assert!(picks > 700 && picks < 800);
+
+    // the same seed always gives the same move
+    assert_eq!(
+        book.choose_move(&Board::default(), 7),
+        book.choose_move(&Board::default(), 7)
+    );
+
+    let board = Board::default().make_move_new(e4);
+    assert_eq!(book.choose_move(&board, 7), None);
+}
@@ -0,0 +1,9 @@
+//! Support for opening books in the Polyglot `.bin` format.
+
+mod book;
+pub use self::book::*;
+
+pub(crate) mod keys;
+
+mod writer;
+pub use self::writer::*;
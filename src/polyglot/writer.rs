@@ -0,0 +1,287 @@
+use super::book::{encode_move, PolyglotBook, RawEntry};
+use crate::game::Game;
+use std::collections::HashMap;
+
+/// Build a Polyglot opening book from a collection of games.
+///
+/// Each move played in the first `max_ply` plies of a finished game is scored from the point of
+/// view of the side that played it: `win_weight` if that side went on to win, `draw_weight` for a
+/// draw and `loss_weight` for a loss.  The scores for a move are added up over all the games it
+/// was played in, and scaled down if needed to fit the 16-bit weights of the book format.  Moves
+/// with a total score of zero are left out of the book, as are games without a result.
+///
+/// ```
+/// use minorhacks_chess::{Board, ChessMove, Color, Game, PolyglotBookBuilder, Square};
+///
+/// let e4 = ChessMove::new(Square::E2, Square::E4, None);
+/// let d4 = ChessMove::new(Square::D2, Square::D4, None);
+///
+/// let mut won = Game::new();
+/// won.make_move(e4);
+/// won.resign(Color::Black);
+///
+/// let mut drawn = Game::new();
+/// drawn.make_move(d4);
+/// drawn.offer_draw(Color::Black);
+/// drawn.accept_draw();
+///
+/// let book = PolyglotBookBuilder::new()
+///     .add_game(&won)
+///     .add_game(&drawn)
+///     .build();
+///
+/// let entries = book.entries(&Board::default());
+/// assert_eq!(entries.len(), 2);
+/// assert_eq!((entries[0].get_move(), entries[0].get_weight()), (e4, 2));
+/// assert_eq!((entries[1].get_move(), entries[1].get_weight()), (d4, 1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PolyglotBookBuilder {
+    win_weight: u32,
+    draw_weight: u32,
+    loss_weight: u32,
+    max_ply: usize,
+    scores: HashMap<u64, HashMap<u16, u64>>,
+}
+
+impl Default for PolyglotBookBuilder {
+    fn default() -> Self {
+        PolyglotBookBuilder::new()
+    }
+}
+
+impl PolyglotBookBuilder {
+    /// Create an empty `PolyglotBookBuilder`.  By default, wins score 2, draws score 1, losses
+    /// score 0, and every move of every game is used.
+    pub fn new() -> PolyglotBookBuilder {
+        PolyglotBookBuilder {
+            win_weight: 2,
+            draw_weight: 1,
+            loss_weight: 0,
+            max_ply: usize::MAX,
+            scores: HashMap::new(),
+        }
+    }
+
+    /// Set the score for a move played by the side that went on to win.
+    ///
+    /// This only affects games added afterwards.
+    pub fn win_weight(&mut self, weight: u32) -> &mut Self {
+        self.win_weight = weight;
+        self
+    }
+
+    /// Set the score for a move played in a game that was drawn.
+    ///
+    /// This only affects games added afterwards.
+    pub fn draw_weight(&mut self, weight: u32) -> &mut Self {
+        self.draw_weight = weight;
+        self
+    }
+
+    /// Set the score for a move played by the side that went on to lose.
+    ///
+    /// This only affects games added afterwards.
+    pub fn loss_weight(&mut self, weight: u32) -> &mut Self {
+        self.loss_weight = weight;
+        self
+    }
+
+    /// Only use the first `max_ply` moves (counting both sides) of each game.
+    ///
+    /// This only affects games added afterwards.
+    ///
+    /// ```
+    /// use minorhacks_chess::{Board, ChessMove, Color, Game, PolyglotBookBuilder, Square};
+    ///
+    /// let mut game = Game::new();
+    /// game.make_move(ChessMove::new(Square::E2, Square::E4, None));
+    /// game.make_move(ChessMove::new(Square::E7, Square::E5, None));
+    /// game.resign(Color::White);
+    ///
+    /// let book = PolyglotBookBuilder::new().max_ply(1).add_game(&game).build();
+    /// assert_eq!(book.len(), 0);
+    ///
+    /// let book = PolyglotBookBuilder::new().max_ply(2).add_game(&game).build();
+    /// assert_eq!(book.len(), 1);
+    /// ```
+    pub fn max_ply(&mut self, max_ply: usize) -> &mut Self {
+        self.max_ply = max_ply;
+        self
+    }
+
+    /// Add the moves of a game to the book.
+    pub fn add_game(&mut self, game: &Game) -> &mut Self {
+        let result = match game.result() {
+            Some(result) => result,
+            None => return self,
+        };
+
+        let mut board = game.start_position();
+        for node in game.mainline().iter().take(self.max_ply) {
+            let chess_move = node.get_move();
+            let score = match result.winner() {
+                None => self.draw_weight,
+                Some(color) if color == board.side_to_move() => self.win_weight,
+                Some(_) => self.loss_weight,
+            };
+
+            if score > 0 {
+                *self
+                    .scores
+                    .entry(board.polyglot_hash())
+                    .or_default()
+                    .entry(encode_move(&board, chess_move))
+                    .or_insert(0) += score as u64;
+            }
+
+            board = board.make_move_new(chess_move);
+        }
+
+        self
+    }
+
+    /// Add the moves of several games to the book.
+    pub fn add_games<'a, I: IntoIterator<Item = &'a Game>>(&mut self, games: I) -> &mut Self {
+        for game in games {
+            self.add_game(game);
+        }
+        self
+    }
+
+    /// Create the book.  Entries are sorted by position, and then from highest to lowest weight.
+    pub fn build(&self) -> PolyglotBook {
+        let mut entries = vec![];
+
+        for (&key, moves) in self.scores.iter() {
+            let max = moves.values().cloned().max().unwrap_or(0);
+            for (&chess_move, &score) in moves.iter() {
+                let weight = if max > u16::MAX as u64 {
+                    // keep the odds the same, but make sure the move is still played sometimes
+                    ((score * u16::MAX as u64) / max).max(1)
+                } else {
+                    score
+                };
+
+                entries.push(RawEntry {
+                    key,
+                    chess_move,
+                    weight: weight as u16,
+                    learn: 0,
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            a.key
+                .cmp(&b.key)
+                .then(b.weight.cmp(&a.weight))
+                .then(a.chess_move.cmp(&b.chess_move))
+        });
+
+        PolyglotBook::from_sorted_entries(entries)
+    }
+}
+
+#[cfg(test)]
+use crate::board::Board;
+#[cfg(test)]
+use crate::chess_move::ChessMove;
+#[cfg(test)]
+use crate::color::Color;
+#[cfg(test)]
+use crate::game::fake_pgn_parser;
+#[cfg(test)]
+use std::str::FromStr;
+
+#[cfg(test)]
+fn finished_game(moves: &str, winner: Option<Color>) -> Game {
+    let mut game = fake_pgn_parser(moves);
+    match winner {
+        Some(color) => {
+            game.resign(!color);
+        }
+        None => {
+            game.offer_draw(game.side_to_move());
+            game.accept_draw();
+        }
+    }
+    game
+}
+
+#[cfg(test)]
+fn book_moves(book: &PolyglotBook, board: &Board) -> Vec<(String, u16)> {
+    book.entries(board)
+        .iter()
+        .map(|e| (e.get_move().to_string(), e.get_weight()))
+        .collect()
+}
+
+#[test]
+fn test_book_round_trip() {
+    let games = [
+        finished_game("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O", Some(Color::White)),
+        finished_game("1. e4 e5 2. Nf3 Nf6", Some(Color::Black)),
+        finished_game("1. e4 c5", None),
+        finished_game("1. d4 d5", Some(Color::White)),
+        // unfinished games are ignored
+        fake_pgn_parser("1. c4 c5"),
+    ];
+
+    let built = PolyglotBookBuilder::new()
+        .win_weight(3)
+        .draw_weight(1)
+        .loss_weight(0)
+        .add_games(games.iter())
+        .build();
+
+    let mut bytes = vec![];
+    built.write(&mut bytes).unwrap();
+    let book = PolyglotBook::from_bytes(&bytes).unwrap();
+    assert_eq!(book, built);
+
+    // the keys must be sorted for other Polyglot readers
+    let keys = bytes
+        .chunks(16)
+        .map(|c| u64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+        .collect::<Vec<u64>>();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    assert_eq!(keys, sorted);
+
+    let start = Board::default();
+    assert_eq!(
+        book_moves(&book, &start),
+        vec![("e2e4".to_owned(), 4), ("d2d4".to_owned(), 3)]
+    );
+
+    let board = start.make_move_new(ChessMove::from_str("e2e4").unwrap());
+    assert_eq!(
+        book_moves(&book, &board),
+        vec![("e7e5".to_owned(), 3), ("c7c5".to_owned(), 1)]
+    );
+
+    // castling is stored as the king capturing its own rook, and read back as a castle
+    let castles = fake_pgn_parser("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5").current_position();
+    assert_eq!(book_moves(&book, &castles), vec![("e1g1".to_owned(), 3)]);
+}
+
+#[test]
+fn test_book_ply_cutoff_and_scaling() {
+    let game = finished_game("1. e4 e5 2. Nf3", Some(Color::White));
+
+    let book = PolyglotBookBuilder::new()
+        .max_ply(2)
+        .win_weight(40000)
+        .loss_weight(1)
+        .add_game(&game)
+        .add_game(&game)
+        .build();
+    assert_eq!(book.len(), 2);
+
+    let board = Board::default();
+    assert_eq!(book_moves(&book, &board), vec![("e2e4".to_owned(), 65535)]);
+
+    let board = board.make_move_new(ChessMove::from_str("e2e4").unwrap());
+    assert_eq!(book_moves(&book, &board), vec![("e7e5".to_owned(), 2)]);
+}
@@ -0,0 +1,732 @@
+use crate::bitboard::EMPTY;
+use crate::board::Board;
+use crate::cache_table::CacheTable;
+use crate::chess_move::ChessMove;
+use crate::movegen::{mvv_lva, MoveGen, ScoredMoveList};
+use crate::piece::{Piece, PieceValues, ALL_PIECES};
+use crate::square::NUM_SQUARES;
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+/// The deepest the search goes, counting the quiescence search and check extensions.
+pub const MAX_PLY: usize = 128;
+
+/// The score of checkmating the opponent right now.  Mating in `n` plies scores
+/// `MATE_SCORE - n`, and being mated in `n` plies scores `n - MATE_SCORE`.
+pub const MATE_SCORE: i32 = 32000;
+
+/// Scores at least this far from zero are mates.
+const MATE_BOUND: i32 = MATE_SCORE - MAX_PLY as i32;
+
+const INFINITY: i32 = MATE_SCORE + 1;
+
+/// How much shallower the search after a null move is.
+const NULL_MOVE_REDUCTION: i32 = 2;
+
+/// How often, in nodes, the clock is read.  This must be a power of two.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// History scores are halved when one of them grows past this, so they stay below the score of
+/// killer moves.
+const HISTORY_MAX: i32 = 1 << 16;
+
+const HASH_MOVE_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 20;
+const KILLER_SCORE: i32 = 1 << 18;
+
+/// Score a position for the search, in centipawns.
+///
+/// The score is from the point of view of the side to move: positive scores are good for the
+/// side to move.  Keep scores well inside `MATE_SCORE`, which is reserved for checkmates.
+///
+/// ```
+/// use minorhacks_chess::{Board, Evaluator, PieceValues};
+/// use std::str::FromStr;
+///
+/// // an extra knight for the side to move
+/// let board = Board::from_str("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1").expect("Valid FEN");
+/// assert_eq!(PieceValues::default().evaluate(&board), 320);
+/// ```
+pub trait Evaluator {
+    /// Score `board` from the point of view of the side to move.
+    fn evaluate(&self, board: &Board) -> i32;
+}
+
+/// Count material: the value of my pieces minus the value of my opponent's pieces.
+impl Evaluator for PieceValues {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let color = board.side_to_move();
+        ALL_PIECES
+            .iter()
+            .map(|&piece| {
+                let balance = board.piece_count(color, piece) as i32
+                    - board.piece_count(!color, piece) as i32;
+                balance * self.get(piece)
+            })
+            .sum()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// What the transposition table remembers about a position.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+struct TableEntry {
+    depth: i32,
+    score: i32,
+    bound: Bound,
+    best_move: Option<ChessMove>,
+}
+
+const EMPTY_ENTRY: TableEntry = TableEntry {
+    depth: -1,
+    score: 0,
+    bound: Bound::Upper,
+    best_move: None,
+};
+
+/// Mate scores are stored relative to the position in the table, not the root.
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Does the side to move have a piece other than pawns and its king?  Null move pruning is
+/// unsound without one, because of zugzwang.
+fn has_non_pawn_material(board: &Board) -> bool {
+    let kings_and_pawns = board.pieces(Piece::King) | board.pieces(Piece::Pawn);
+    board.color_combined(board.side_to_move()) & !kings_and_pawns != EMPTY
+}
+
+/// The outcome of `Search::search`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    score: i32,
+    depth: u32,
+    nodes: u64,
+    pv: Vec<ChessMove>,
+}
+
+impl SearchResult {
+    /// Get the best move found, or `None` if there are no legal moves.
+    pub fn get_best_move(&self) -> Option<ChessMove> {
+        self.pv.first().copied()
+    }
+
+    /// Get the score of the position, in centipawns from the point of view of the side to move.
+    /// See `MATE_SCORE` for how checkmates are scored.
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+
+    /// If the score is a forced checkmate, get the number of plies until it happens.  The
+    /// number is negative if the side to move is getting mated.
+    pub fn get_mate_in(&self) -> Option<i32> {
+        if self.score >= MATE_BOUND {
+            Some(MATE_SCORE - self.score)
+        } else if self.score <= -MATE_BOUND {
+            Some(-MATE_SCORE - self.score)
+        } else {
+            None
+        }
+    }
+
+    /// Get the depth of the last iteration that was finished.
+    pub fn get_depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Get the number of nodes searched, including the ones of an unfinished iteration.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Get the principal variation: the best move, followed by the best replies.  It may be cut
+    /// short by transposition table hits.
+    pub fn get_pv(&self) -> &[ChessMove] {
+        &self.pv
+    }
+}
+
+/// An alpha-beta searcher.
+///
+/// The search is a negamax alpha-beta search with iterative deepening, and a quiescence search
+/// of captures and promotions at the leaves.  A transposition table built on `CacheTable`
+/// remembers positions between iterations and between searches, and null move pruning, killer
+/// moves and the history heuristic speed it up.
+///
+/// The search stops after `max_depth` iterations, or when it has used up its node or time
+/// budget.  It always finishes depth 1, so there is a move to play.  Repetitions are only
+/// detected among the positions of the search itself.
+///
+/// ```
+/// use minorhacks_chess::{Board, ChessMove, PieceValues, Search, Square};
+/// use std::str::FromStr;
+///
+/// let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").expect("Valid FEN");
+///
+/// let mut search = Search::new(PieceValues::default(), 1 << 16);
+/// search.set_max_depth(4);
+///
+/// let result = search.search(&board);
+/// assert_eq!(result.get_best_move(), Some(ChessMove::new(Square::A1, Square::A8, None)));
+/// assert_eq!(result.get_mate_in(), Some(1));
+/// ```
+pub struct Search<E: Evaluator> {
+    evaluator: E,
+    table: CacheTable<TableEntry>,
+    table_size: usize,
+    max_depth: u32,
+    max_nodes: Option<u64>,
+    max_time: Option<Duration>,
+    killers: [[Option<ChessMove>; 2]; MAX_PLY],
+    history: Box<[[i32; NUM_SQUARES]; NUM_SQUARES]>,
+    pv: Vec<Vec<ChessMove>>,
+    path: Vec<u64>,
+    nodes: u64,
+    start: Instant,
+    can_stop: bool,
+    stopped: bool,
+}
+
+impl<E: Evaluator> Search<E> {
+    /// Create a new `Search`, with a transposition table of `table_size` entries.
+    ///
+    /// panic!() if `table_size` is not a power of two, like `CacheTable::new`.
+    pub fn new(evaluator: E, table_size: usize) -> Search<E> {
+        Search {
+            evaluator,
+            table: CacheTable::new(table_size, EMPTY_ENTRY),
+            table_size,
+            max_depth: MAX_PLY as u32 / 2,
+            max_nodes: None,
+            max_time: None,
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[0; NUM_SQUARES]; NUM_SQUARES]),
+            pv: vec![vec![]; MAX_PLY + 1],
+            path: Vec::with_capacity(MAX_PLY),
+            nodes: 0,
+            start: Instant::now(),
+            can_stop: false,
+            stopped: false,
+        }
+    }
+
+    /// Get the evaluation the search uses.
+    pub fn get_evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Get the deepest iteration of the search.
+    pub fn get_max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Set the deepest iteration of the search.  It is at least 1.
+    pub fn set_max_depth(&mut self, depth: u32) -> &mut Self {
+        self.max_depth = cmp::max(depth, 1);
+        self
+    }
+
+    /// Get the number of nodes after which the search stops, if any.
+    pub fn get_max_nodes(&self) -> Option<u64> {
+        self.max_nodes
+    }
+
+    /// Stop the search after `nodes` nodes.
+    pub fn set_max_nodes(&mut self, nodes: u64) -> &mut Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Get the time after which the search stops, if any.
+    pub fn get_max_time(&self) -> Option<Duration> {
+        self.max_time
+    }
+
+    /// Stop the search after `time`.
+    pub fn set_max_time(&mut self, time: Duration) -> &mut Self {
+        self.max_time = Some(time);
+        self
+    }
+
+    /// Forget everything learned from earlier searches.
+    pub fn clear(&mut self) {
+        self.table = CacheTable::new(self.table_size, EMPTY_ENTRY);
+        self.killers = [[None; 2]; MAX_PLY];
+        *self.history = [[0; NUM_SQUARES]; NUM_SQUARES];
+    }
+
+    /// Search `board` for the best move.
+    pub fn search(&mut self, board: &Board) -> SearchResult {
+        self.nodes = 0;
+        self.start = Instant::now();
+        self.can_stop = false;
+        self.stopped = false;
+        self.killers = [[None; 2]; MAX_PLY];
+        self.path.clear();
+
+        let mut result = SearchResult {
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: vec![],
+        };
+
+        for depth in 1..=self.max_depth {
+            let score = self.negamax(board, depth as i32, -INFINITY, INFINITY, 0, false);
+            if self.stopped {
+                break;
+            }
+
+            result.score = score;
+            result.depth = depth;
+            result.pv = self.pv[0].clone();
+
+            // a mate this close won't get any better
+            if result.pv.is_empty() || MATE_SCORE - score.abs() <= depth as i32 {
+                break;
+            }
+            self.can_stop = true;
+            if self.out_of_nodes() || self.out_of_time() {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn out_of_nodes(&self) -> bool {
+        match self.max_nodes {
+            Some(nodes) => self.nodes >= nodes,
+            None => false,
+        }
+    }
+
+    fn out_of_time(&self) -> bool {
+        match self.max_time {
+            Some(time) => self.start.elapsed() >= time,
+            None => false,
+        }
+    }
+
+    /// Count a node, and check whether the search has to stop.
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        if self.can_stop && !self.stopped {
+            let check_time = self.nodes & (TIME_CHECK_INTERVAL - 1) == 0;
+            if self.out_of_nodes() || (check_time && self.out_of_time()) {
+                self.stopped = true;
+            }
+        }
+        self.stopped
+    }
+
+    fn update_pv(&mut self, ply: usize, m: ChessMove) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        head[ply].clear();
+        head[ply].push(m);
+        head[ply].extend_from_slice(&tail[0]);
+    }
+
+    /// Remember a quiet move that caused a beta cutoff.
+    fn update_quiet_cutoff(&mut self, m: ChessMove, depth: i32, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+
+        let entry = &mut self.history[m.get_source().to_index()][m.get_dest().to_index()];
+        *entry += depth * depth;
+        if *entry > HISTORY_MAX {
+            for row in self.history.iter_mut() {
+                for score in row.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+
+    /// Order the moves: the hash move, then captures and promotions by MVV-LVA, then killer
+    /// moves, then the other quiet moves by their history.
+    fn order_moves(
+        &self,
+        board: &Board,
+        hash_move: Option<ChessMove>,
+        ply: usize,
+    ) -> ScoredMoveList {
+        let killers = self.killers[ply];
+        ScoredMoveList::from_moves(MoveGen::new_legal(board), |m| {
+            if Some(m) == hash_move {
+                HASH_MOVE_SCORE
+            } else if board.is_capture(m) || m.get_promotion().is_some() {
+                CAPTURE_SCORE + mvv_lva(board, m)
+            } else if killers[0] == Some(m) {
+                KILLER_SCORE
+            } else if killers[1] == Some(m) {
+                KILLER_SCORE - 1
+            } else {
+                self.history[m.get_source().to_index()][m.get_dest().to_index()]
+            }
+        })
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        mut depth: i32,
+        mut alpha: i32,
+        mut beta: i32,
+        ply: usize,
+        allow_null: bool,
+    ) -> i32 {
+        self.pv[ply].clear();
+        if self.visit() {
+            return 0;
+        }
+
+        let hash = board.get_hash();
+        let in_check = *board.checkers() != EMPTY;
+        if ply > 0 {
+            if (board.halfmove_clock() >= 100 && !in_check)
+                || board.is_insufficient_material()
+                || self.path.contains(&hash)
+            {
+                return 0;
+            }
+
+            // mate distance pruning
+            alpha = cmp::max(alpha, ply as i32 - MATE_SCORE);
+            beta = cmp::min(beta, MATE_SCORE - ply as i32 - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(board);
+        }
+
+        if in_check {
+            depth += 1;
+        }
+        if depth <= 0 {
+            return self.quiescence(board, alpha, beta, ply);
+        }
+
+        let mut hash_move = None;
+        if let Some(entry) = self.table.get(hash) {
+            hash_move = entry.best_move;
+            if ply > 0 && entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        if allow_null
+            && !in_check
+            && depth > NULL_MOVE_REDUCTION
+            && has_non_pawn_material(board)
+            && self.evaluator.evaluate(board) >= beta
+        {
+            if let Some(null) = board.null_move() {
+                self.path.push(hash);
+                let score = -self.negamax(
+                    &null,
+                    depth - 1 - NULL_MOVE_REDUCTION,
+                    -beta,
+                    -beta + 1,
+                    ply + 1,
+                    false,
+                );
+                self.path.pop();
+                if self.stopped {
+                    return 0;
+                }
+                if score >= beta {
+                    // don't trust mates found by passing
+                    return cmp::min(score, MATE_BOUND - 1);
+                }
+            }
+        }
+
+        let moves = self.order_moves(board, hash_move, ply);
+        if moves.len() == 0 {
+            return if in_check { ply as i32 - MATE_SCORE } else { 0 };
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        self.path.push(hash);
+        for m in moves.map(|m| m.get_move()) {
+            let score = -self.negamax(
+                &board.make_move_new(m),
+                depth - 1,
+                -beta,
+                -alpha,
+                ply + 1,
+                true,
+            );
+            if self.stopped {
+                self.path.pop();
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, m);
+                    if alpha >= beta {
+                        if !board.is_capture(m) && m.get_promotion().is_none() {
+                            self.update_quiet_cutoff(m, depth, ply);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        self.path.pop();
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.add(
+            hash,
+            TableEntry {
+                depth,
+                score: score_to_table(best_score, ply),
+                bound,
+                best_move,
+            },
+        );
+
+        best_score
+    }
+
+    /// Search captures and promotions until the position is quiet, so that the evaluation isn't
+    /// taken in the middle of an exchange.  Captures that lose material are skipped.  In check,
+    /// every evasion is searched instead.
+    fn quiescence(&mut self, board: &Board, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+        if self.visit() {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(board);
+        }
+
+        let in_check = *board.checkers() != EMPTY;
+        let mut best_score = -INFINITY;
+        let moves = if in_check {
+            let moves = ScoredMoveList::new_mvv_lva(board, MoveGen::new_legal_evasions(board));
+            if moves.len() == 0 {
+                return ply as i32 - MATE_SCORE;
+            }
+            moves
+        } else {
+            best_score = self.evaluator.evaluate(board);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = cmp::max(alpha, best_score);
+
+            let captures = MoveGen::new_legal_captures(board)
+                .filter(|m| m.get_promotion().is_some() || board.see_ge(*m, 0));
+            ScoredMoveList::new_mvv_lva(board, captures)
+        };
+
+        for m in moves.map(|m| m.get_move()) {
+            let score = -self.quiescence(&board.make_move_new(m), -beta, -alpha, ply + 1);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, m);
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        best_score
+    }
+}
+
+#[cfg(test)]
+use crate::bitboard::BitBoard;
+#[cfg(test)]
+use crate::color::Color;
+#[cfg(test)]
+use crate::square::Square;
+#[cfg(test)]
+use std::str::FromStr;
+
+#[cfg(test)]
+fn search_to_depth(fen: &str, depth: u32) -> SearchResult {
+    let board = Board::from_str(fen).unwrap();
+    let mut search = Search::new(PieceValues::default(), 1 << 16);
+    search.set_max_depth(depth);
+    let result = search.search(&board);
+
+    // the principal variation is made of legal moves
+    let mut after = board;
+    for m in result.get_pv() {
+        assert!(after.legal(*m), "{} in {}", m, fen);
+        after = after.make_move_new(*m);
+    }
+    result
+}
+
+#[test]
+fn test_search_mates() {
+    // back rank mate
+    let result = search_to_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 4);
+    assert_eq!(result.get_mate_in(), Some(1));
+    assert_eq!(result.get_depth(), 1);
+
+    // mate in two with a rook roller
+    let result = search_to_depth("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1", 5);
+    assert_eq!(result.get_mate_in(), Some(3));
+    assert_eq!(result.get_pv().len(), 3);
+    assert_eq!(result.get_score(), MATE_SCORE - 3);
+
+    // getting mated
+    let result = search_to_depth("7k/R7/8/8/8/8/8/1R4K1 b - - 0 1", 4);
+    assert_eq!(result.get_mate_in(), Some(-2));
+    assert_eq!(
+        result.get_best_move(),
+        Some(ChessMove::new(Square::H8, Square::G8, None))
+    );
+
+    // no moves at all
+    let result = search_to_depth("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+    assert_eq!(result.get_best_move(), None);
+    assert_eq!(result.get_score(), 0);
+    let result = search_to_depth("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1", 3);
+    assert_eq!(result.get_best_move(), None);
+    assert_eq!(result.get_score(), -MATE_SCORE);
+}
+
+#[test]
+fn test_search_material() {
+    // take the hanging queen
+    let result = search_to_depth("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 3);
+    assert_eq!(
+        result.get_best_move(),
+        Some(ChessMove::new(Square::D1, Square::D5, None))
+    );
+    assert!(result.get_score() > 400);
+
+    // the pawn is defended, which the quiescence search sees
+    let result = search_to_depth("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", 1);
+    assert!(result.get_score() > 600);
+    assert_ne!(
+        result.get_best_move(),
+        Some(ChessMove::new(Square::D1, Square::D5, None))
+    );
+}
+
+#[test]
+fn test_search_limits() {
+    let board = Board::default();
+
+    let mut search = Search::new(PieceValues::default(), 1 << 16);
+    search.set_max_depth(100).set_max_nodes(20_000);
+    let result = search.search(&board);
+    assert!(result.get_best_move().is_some());
+    assert!(result.get_depth() < 100);
+    assert!(result.get_nodes() <= 20_001);
+
+    let mut search = Search::new(PieceValues::default(), 1 << 16);
+    search
+        .set_max_depth(100)
+        .set_max_time(Duration::from_millis(50));
+    let start = Instant::now();
+    let result = search.search(&board);
+    assert!(result.get_best_move().is_some());
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // searching again uses the transposition table
+    let mut search = Search::new(PieceValues::default(), 1 << 16);
+    search.set_max_depth(4);
+    let first = search.search(&board);
+    let second = search.search(&board);
+    assert_eq!(first.get_score(), second.get_score());
+    assert!(second.get_nodes() < first.get_nodes());
+
+    search.clear();
+    assert_eq!(search.search(&board), first);
+}
+
+#[test]
+fn test_search_evaluator() {
+    // prefer to push pawns
+    struct Advance;
+    impl Evaluator for Advance {
+        fn evaluate(&self, board: &Board) -> i32 {
+            let color = board.side_to_move();
+            let mine = board.pieces(Piece::Pawn) & board.color_combined(color);
+            let theirs = board.pieces(Piece::Pawn) & board.color_combined(!color);
+            let advance = |pawns: BitBoard, color| {
+                pawns
+                    .map(|sq| sq.get_rank().to_index() as i32)
+                    .map(|rank| {
+                        if color == Color::White {
+                            rank
+                        } else {
+                            7 - rank
+                        }
+                    })
+                    .sum::<i32>()
+            };
+            advance(mine, color) - advance(theirs, !color)
+        }
+    }
+
+    let board = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let mut search = Search::new(Advance, 1 << 10);
+    search.set_max_depth(1);
+    assert_eq!(
+        search.search(&board).get_best_move(),
+        Some(ChessMove::new(Square::E2, Square::E4, None))
+    );
+}
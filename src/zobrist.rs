@@ -825,6 +825,788 @@ const ZOBRIST_EP: [[u64; NUM_FILES]; NUM_COLORS] = [[
     16795260471835440987,
 ]];
 
+const ZOBRIST_MATERIAL: [[[u64; NUM_SQUARES]; NUM_PIECES]; NUM_COLORS] = [[[
+    2039601259243433688,
+    5946413567669797662,
+    6155991459509682761,
+    17674391301715447853,
+    15048938131492824703,
+    13742313578706093061,
+    13249135080256755695,
+    3832891763505247383,
+    6472365234635903235,
+    18135244506265090703,
+    15895568703080162695,
+    8939549066337058521,
+    12930551551351937872,
+    10295762062262325027,
+    16131055545599543702,
+    18055804931382238704,
+    3083855787354999847,
+    15371203681385884277,
+    17777549223046273496,
+    10993930007527389035,
+    6764995766655522574,
+    2046808098385638904,
+    7842862858046362013,
+    1240821965117072309,
+    11999189046427382339,
+    9914043403175575707,
+    909759828931292302,
+    7098746236989433613,
+    1342390708065094298,
+    11475803233449662564,
+    10966002411387986597,
+    16691542185719585127,
+    15161121856547996771,
+    7535894983250547487,
+    8429766569948867496,
+    5942847288034287937,
+    14320035415816252530,
+    12475026720241156851,
+    12746178385944961149,
+    7283182637253974640,
+    17928487689447830378,
+    6645035265551293547,
+    5721846527282908406,
+    8384682987710311658,
+    849749413478856162,
+    7809288930607936688,
+    17843314113684212040,
+    18223458351566612054,
+    15981038262630137926,
+    5645134896159966624,
+    4668242279271526633,
+    16531537246767170149,
+    16162879910915784623,
+    10590878630920396568,
+    578462069867067334,
+    3406638847590147112,
+    9151448142765386496,
+    16728258759944261965,
+    16143389730164487137,
+    15725813634473695631,
+    4768564137430486059,
+    8284527880170404336,
+    2327230405513310736,
+    540335531049718990,
+   ], [
+    17761024098790246617,
+    15143946769655323985,
+    13636654780018465459,
+    161900963095482084,
+    7549951665447164663,
+    4158911074774718801,
+    2520078355869185490,
+    14030666910395784504,
+    14774453083624316827,
+    11011475422639558839,
+    2926181187558066481,
+    14153625773025684671,
+    10160120783916146946,
+    18269582499923566740,
+    5704057027627697087,
+    11279006112971165888,
+    14691956092584996260,
+    12802774030794429694,
+    11596995655853222789,
+    9213801524740848631,
+    16943678347454551190,
+    10544493860302689955,
+    6796770846098790203,
+    5736731726468645435,
+    13995076055137939066,
+    2755373864736569575,
+    15661873767357175469,
+    14629278141254963367,
+    550155425948248826,
+    12633911216344708475,
+    89900831084154194,
+    13831151786709353798,
+    1549331363841016983,
+    8592807910153729739,
+    9697463010778187938,
+    802965749976383711,
+    14579472404610300834,
+    5876085288164632042,
+    11565876284560351343,
+    4630239973527534120,
+    924926843228817835,
+    7325258910692929609,
+    9523693354855407586,
+    7680890411686605111,
+    12934643827218927012,
+    15593573510018524733,
+    17485307118856727076,
+    7066677713908584937,
+    13643813163084712112,
+    10354950957482870135,
+    4828740784911100635,
+    17215145965176804553,
+    1711373625237959948,
+    1552135647225984781,
+    17602745975448726191,
+    12906098188266378622,
+    15457539506506394862,
+    16325481513952950904,
+    1053612884018832858,
+    14148620809149316444,
+    9063879940876253321,
+    16791348241700150109,
+    5899442116925870310,
+    7198525047426377521,
+   ], [
+    16392607055345172164,
+    14487662494451557516,
+    8245193558857718263,
+    13039684306586999865,
+    9489818780646669135,
+    12396984166646300749,
+    12237225897234720877,
+    1870003663394081934,
+    14028706804605243733,
+    12293201369465936301,
+    2758690225242131392,
+    8785856041946707697,
+    4837715012545917955,
+    15824158133093310054,
+    11481407622433094719,
+    9001432666660795113,
+    14818333672326357176,
+    396043364121576012,
+    18342092900282663095,
+    6080403645442513610,
+    7966642829742365265,
+    9689089343510533547,
+    8180643858467490965,
+    10770988270965351,
+    5925689037602512060,
+    12039786576076553966,
+    17325040243703072838,
+    3079010004696972253,
+    8917962817068406926,
+    620866514712212556,
+    4301319075746481023,
+    9951506998893918277,
+    4251992937143892301,
+    17589444949017603560,
+    10925822100068946457,
+    5288468432628530249,
+    3782742937349523359,
+    1952315894697238686,
+    16499452906473247658,
+    1435726308595277228,
+    7469066043250802021,
+    3606700204473050524,
+    15253700535728051605,
+    3264745371489909791,
+    15243024816436851209,
+    16835357956739211591,
+    4403120360840224911,
+    17100770496477982358,
+    5420999595562423876,
+    14498303573476422493,
+    1292831172801707277,
+    16569692297666851352,
+    2638972820810555668,
+    7147666539369238058,
+    16944731651706085002,
+    13741370019548969730,
+    9154183944224371678,
+    8040393698579863177,
+    3818149719055000174,
+    17563957037503874049,
+    14323060282531409918,
+    13214639575365303240,
+    12318989895766547176,
+    6804231168256376253,
+   ], [
+    1684502330163531063,
+    17526640544365955526,
+    9773217410025810332,
+    11572471601824461157,
+    10617578346082862875,
+    2067248887016939962,
+    13827365885673616808,
+    7514942649281799264,
+    11668316970514698721,
+    17601036361308632206,
+    17064221160352508051,
+    6160000482516915398,
+    18017882233838633663,
+    15250386400147612100,
+    5420059893679803688,
+    9199797114548212458,
+    521871807348300165,
+    10377623212896770799,
+    9094284142903628812,
+    1570937245122804223,
+    6504097745373669327,
+    2303344221856489568,
+    12626620617101020212,
+    8888785889054743202,
+    7768287847409058400,
+    9142779255160663201,
+    8282172235699801097,
+    14191870156484430789,
+    13513771637171160542,
+    14124945049600037288,
+    560567060088113749,
+    4664934547959462784,
+    8493644937894816031,
+    8636279464269020234,
+    2958772336006658072,
+    6030633147193001540,
+    8854401847756275603,
+    6899316676457867909,
+    13399868949391589264,
+    9891229502145137491,
+    12773412840600813277,
+    2295891449702262926,
+    14555663118122166941,
+    2264364767343124779,
+    13436653738267059497,
+    3410112395885816519,
+    6136512820325188643,
+    4047209619478926023,
+    3528159527940653771,
+    5408366673588569182,
+    9849274571450283422,
+    15554597185698555915,
+    11695676389072624322,
+    14671344150954070845,
+    3740699118539039600,
+    11293620923877092403,
+    1474516428438308013,
+    3710072379505969662,
+    14644944125185728344,
+    12068750219111863197,
+    15083698020217356402,
+    1229490080156577752,
+    1860075128229758395,
+    11139163447885100535,
+   ], [
+    10439149081848157719,
+    7538627539204386329,
+    8183001192928735096,
+    12898562207891344185,
+    15328833420122546923,
+    15312578235358728013,
+    9332418348466458906,
+    2803676912679876482,
+    6217445249005605022,
+    10538554838729421104,
+    8151965162454134651,
+    10561699044202657223,
+    3989825515224106714,
+    11577781280954309590,
+    2012261918409675114,
+    14215428045539137587,
+    3190483686439652337,
+    16000286979196367961,
+    10215015343839492506,
+    13612854683733070977,
+    7385462897467084283,
+    8087477190034519272,
+    4423691295025369311,
+    2188411216677345982,
+    6082208058897750845,
+    5636012253375316353,
+    6810729611226948844,
+    11238726289712124452,
+    9114754688533254025,
+    1198209510007000839,
+    6629697227691454778,
+    11054500345943229711,
+    10967973992770340124,
+    10068048869808025583,
+    1741852764164813444,
+    6932032047390894315,
+    4454468634289452937,
+    6441780259364400227,
+    17350924608205935997,
+    17093086411362002440,
+    18394133587468498330,
+    7986750687529361860,
+    1719399316731537101,
+    8501130058111974903,
+    15126759474813561957,
+    15089214626057532223,
+    16878831824681400015,
+    17464404018104483920,
+    3749054183947935058,
+    3389757815696340014,
+    11366637192614390481,
+    11369383763567490856,
+    11903593827640720937,
+    4560137126507961675,
+    4121577344083410675,
+    8494314502680257277,
+    3023314443054240364,
+    16321535386855026902,
+    16995800648980313582,
+    13180221961484422358,
+    16664782476262387626,
+    18198695884890277773,
+    12560140059471053204,
+    5619319934724047492,
+   ], [
+    7435783570231476393,
+    3674157852615047591,
+    6499170762526008662,
+    16172736695855578750,
+    4672219924678248738,
+    16866690393006073478,
+    11744238526068824473,
+    4924591883839171678,
+    16984459963642951195,
+    11340477128302023695,
+    4633319057547925825,
+    4652702166066811851,
+    12316683820424527684,
+    16429784033140169000,
+    5129668857684219294,
+    4125973884372972931,
+    9572204179710292923,
+    6726827099385464747,
+    18162840327984695846,
+    16812952188856565824,
+    5661329126419643998,
+    15143405386188111025,
+    7413963187751978821,
+    10340900722379523921,
+    12595827740824945257,
+    11375966474052815373,
+    9873311616832792283,
+    17821746721761278094,
+    15491305694251631186,
+    3564574805140207379,
+    11900644501837342516,
+    7257715453125307289,
+    7311888719466027544,
+    10937515485380242207,
+    2294391995340299548,
+    1171065084877373194,
+    16378562368189377133,
+    13034546488693318785,
+    16603881493561377577,
+    664174610291216994,
+    11209463212037249741,
+    17218780292852276670,
+    7675414088660304003,
+    17808837991854505731,
+    1947523315900953643,
+    14651515386221767912,
+    6996472648885914056,
+    3642120646263761268,
+    3326426180077329978,
+    11348388895269750879,
+    16965327185636600622,
+    96713103045551873,
+    5116875776817529876,
+    7280338756688141766,
+    7696735646042437885,
+    15589368036680566834,
+    11528133407130457535,
+    2375122415084380189,
+    4324733151743257469,
+    13183936409366689935,
+    3888057128316622680,
+    16508131608661056313,
+    11465796242676676457,
+    8433787503432308055,
+  ]], [[
+    1073704174269179838,
+    1724820963047847708,
+    649607209516216247,
+    11413087463977193708,
+    8348321880694787980,
+    16498056702100372608,
+    17715326983783660142,
+    7740684145201996687,
+    17317590112544267686,
+    12541270496854410382,
+    17968106522533271891,
+    1182737811139440602,
+    9705812096175426797,
+    12075399465653327984,
+    17919602900743460905,
+    3439287761897499835,
+    2096055237089918787,
+    7139422049560848497,
+    14797513759424740159,
+    8775217987626133417,
+    13280558657173038606,
+    283886763202422898,
+    9395848162389001958,
+    10811062147206269418,
+    10492101094348435789,
+    15956304793169083687,
+    15353674752122769938,
+    15303208583932722397,
+    5351338425505255419,
+    2897458793636828051,
+    2171436225004161007,
+    13094319972144044997,
+    3481836820763318534,
+    12148133637701648301,
+    14049401984761308283,
+    3327346192806431333,
+    8071923062027270177,
+    11722536822962790222,
+    9038077557339519572,
+    13704463939206301033,
+    276578112781073792,
+    2179319433607122488,
+    16738349480716420813,
+    14716809155330475291,
+    15644817070692757742,
+    9241447321843482534,
+    7747098288374844289,
+    12890161493627675887,
+    8465620120034759323,
+    3397109105400924990,
+    5463513975232563589,
+    16145550884861549193,
+    5374868933257499778,
+    1080448493231852550,
+    13764530206260587232,
+    3481212959623895944,
+    6034134483702998226,
+    15002332955409021059,
+    10634219251400876512,
+    8296528015878377761,
+    17433672598099769655,
+    3415262912470770472,
+    15825040659412276577,
+    5344966919498389306,
+   ], [
+    11135405526772316787,
+    506658045511200595,
+    8374224887605171613,
+    10989870983373670658,
+    4181233916457353419,
+    11348576249701884190,
+    9383579122671059434,
+    3202906121184005080,
+    14136714096735203948,
+    9423054248695743932,
+    6128507632154613819,
+    66694179606863830,
+    1514712907988515182,
+    16349997284308212253,
+    10196124571911939245,
+    9278168172454732037,
+    8310265496013404745,
+    10096755361056881630,
+    8579955196476987256,
+    17917752128999075460,
+    13800619029074550861,
+    11923458424831032610,
+    856649214716342732,
+    2707891692013637155,
+    11512831571606814417,
+    7600680634419721561,
+    17428448295552172087,
+    1900950627214863412,
+    4795344610904029430,
+    10321208820113975707,
+    8402315485008920196,
+    3537178771948460275,
+    3479626523278272737,
+    6145131234208049800,
+    1917356158352773546,
+    16385310642589807037,
+    15917753783064045481,
+    12150753411386703096,
+    1797672986550460105,
+    15551051395044591347,
+    13928693610325660440,
+    51721951365880764,
+    4506455404883722958,
+    15211803605571032573,
+    9411359918843373789,
+    5764159998659796317,
+    16079162214478298314,
+    13339332090979967345,
+    4978741545685484201,
+    6519321533990961287,
+    12668223656859992958,
+    12924145872341801310,
+    1569318868891852056,
+    9933746683225599715,
+    8088682071051786338,
+    4506997794855326022,
+    6020897424775529759,
+    15749287281613604982,
+    18233685290728403377,
+    16176335247436579980,
+    17108835932432437041,
+    16419110451931684496,
+    3439545633125794947,
+    16177269024100809829,
+   ], [
+    10559833789781837339,
+    13194147002270812761,
+    17228994057286971962,
+    17228816369002353004,
+    15973354538170939099,
+    18139182134709385273,
+    6680874378300608596,
+    4436722301829308220,
+    3038138843334326113,
+    1939751511593973637,
+    7937716272255606386,
+    1560787769970603140,
+    7374899277500606388,
+    1725080924944118486,
+    2714171882215436324,
+    4291363510714251544,
+    15604055984634438595,
+    11102906168543901365,
+    3661465411333325052,
+    15853932426735663332,
+    16270595203751597308,
+    12122464085941151459,
+    201946739561789433,
+    734372367956866599,
+    13525192725010230236,
+    16640051121473896703,
+    6641906004641818018,
+    5787723594617501009,
+    17796821109437554725,
+    9967414520016132733,
+    8748543221240057910,
+    411888125639943222,
+    15582901288670007850,
+    1123497109512559435,
+    4947015285392903437,
+    10106608432439949893,
+    16367711320440260116,
+    10194660126856377319,
+    4876767080101456270,
+    16286744364994394767,
+    16913597730226281176,
+    2779669597129242873,
+    17054336807657294334,
+    3895386835649742725,
+    12768645881893627371,
+    7684661929297047344,
+    5526782838867828764,
+    4005155744670453031,
+    702430523517592807,
+    2104516519736138808,
+    6532761805162739847,
+    16870102350496481332,
+    12296436141252581478,
+    2027097794133845820,
+    18406311654562423561,
+    17263194880192950106,
+    4514411397144898823,
+    8130303484920165886,
+    1765342785452966709,
+    11361396688441329971,
+    5544772652297366804,
+    16069442389216250094,
+    13623138415468907110,
+    7531034373952247174,
+   ], [
+    3226627046329383437,
+    1679576577417408497,
+    1023137135298895749,
+    8854041246097966606,
+    9944519134690699935,
+    18000443884792012294,
+    6971942770829212297,
+    5904280643799420338,
+    4415066403328319702,
+    14742321182585511327,
+    3598070350321222710,
+    6024347936998109011,
+    8407379407594282807,
+    14327294965998231309,
+    7545592903818287943,
+    14189562065173227584,
+    7633738641789464965,
+    5905518392418339851,
+    14133244227281423771,
+    18406390095491775951,
+    357074657116185767,
+    4672073338286224263,
+    9394970473575879429,
+    12339736883198435166,
+    17770826333936952411,
+    10461886905979945083,
+    12173471885557423985,
+    11538110437295553140,
+    11559824949235078164,
+    9295937665613481217,
+    4112441270135763635,
+    13077833857030772457,
+    1578610272256988193,
+    8119553339068467815,
+    2427043930655835960,
+    9827991830345162731,
+    11010333111105733705,
+    2702990455606497474,
+    14226081664261148526,
+    7626115832679210170,
+    5695175363183686024,
+    12050028744626035504,
+    18282876851852889734,
+    7223039205593687678,
+    10277339109458380955,
+    4402853519656333263,
+    12232666549772705935,
+    7996048653961045413,
+    7111653593749251115,
+    6361731608386131911,
+    12498056110909734411,
+    16690691879508194600,
+    1493039848524489903,
+    8342266597991646070,
+    2784417548984770468,
+    13764644503752144680,
+    15813572898519378103,
+    7142997226309004669,
+    33428664805679928,
+    16279107708873937413,
+    4051736849310348182,
+    5191771145038061772,
+    11584012735155246472,
+    4485263501586154691,
+   ], [
+    17427964904105176827,
+    8522536752777416351,
+    9898309775934773370,
+    16889767553587656209,
+    6937571638581119802,
+    2337683295707210386,
+    2188627653951873300,
+    14230965351928412851,
+    2250734799839848924,
+    6350944795868126091,
+    2570200050808337970,
+    7032589709652954287,
+    5842271803024290986,
+    18238808014127756262,
+    5170289902515030733,
+    6130269471950140340,
+    5887596696969556482,
+    9917166894217369679,
+    4761077977080382395,
+    8124097780028293817,
+    9140572105020032633,
+    5004757174672822966,
+    6391462288086003878,
+    182632860143486849,
+    7099896838145803339,
+    1043354843041333800,
+    14851213054828561637,
+    16798150019253872680,
+    15103975413818135565,
+    13022863814433030321,
+    4459576620016029096,
+    1555641614492291299,
+    893061778896259085,
+    17310404318926972102,
+    18218946486497843063,
+    10011280929850017827,
+    1504286401563663394,
+    1216202678788776547,
+    8942090793117595816,
+    10273101977503615508,
+    15328949326624007645,
+    16200540411967823817,
+    231480416021658152,
+    10717638586052221168,
+    4932287191466886398,
+    4788198773559465626,
+    3318443289736711262,
+    13129465151632512057,
+    15048809305875238234,
+    16542942072721070761,
+    6728618962032513718,
+    6417801412722152125,
+    5411798322743848607,
+    15978470981026029578,
+    17657699622763703958,
+    2813025186030467568,
+    9021381774577693672,
+    910748608365855421,
+    17835196880203856047,
+    16417864261484154660,
+    8203928038011104053,
+    3810209190659302713,
+    215713620344845881,
+    6662173430158388788,
+   ], [
+    11019681559171308387,
+    15181590162435063321,
+    12843420745946033439,
+    4123338731875519506,
+    1170694067993621581,
+    13539031556311658293,
+    13043510376139784304,
+    7176815106767784766,
+    1012945197180149890,
+    4420675480704128026,
+    3326525528687834368,
+    5928765505817289655,
+    11933625802381152405,
+    5551290057934649261,
+    4125235905843509513,
+    10669680537607471290,
+    7325678115970833186,
+    5500718993399995384,
+    17837072545775341263,
+    16825163797662864291,
+    17478709380749761047,
+    17212884011867429548,
+    9411628845277252452,
+    9492437968227800735,
+    4830910874064025543,
+    10536355711058076843,
+    5609275889831790814,
+    2163801984031952332,
+    15624582315685060029,
+    13958027382649531311,
+    11400263338906762799,
+    11718662754652328603,
+    1774408121108002800,
+    11863063609379104889,
+    12823597012674966818,
+    17645570271283071673,
+    6866595658419421571,
+    7364198990074937242,
+    14869000434436087007,
+    11788532586055455536,
+    7528375602748985981,
+    6511874894557337557,
+    6611486650331211373,
+    17026827762516464576,
+    10129921275097907970,
+    296512013609536906,
+    10229524071513197932,
+    16443172395474563130,
+    5530870515693429925,
+    15047681914829330341,
+    6940925575304187761,
+    16956010771240296089,
+    12904078330922243993,
+    13517572964854732793,
+    10458514884404281632,
+    1649846653128168337,
+    1556782101741781380,
+    437383636634910232,
+    5967769277313613162,
+    2985810133482098731,
+    3344834780654544453,
+    8441630652482854646,
+    7474264925893087717,
+    5096399179743125117,
+]]];
+
 
 
 impl Zobrist {
@@ -839,6 +1621,17 @@ impl Zobrist {
         }
     }
 
+    /// Get the value for the `index`th piece of a particular type, for use in material keys
+    #[inline]
+    pub fn material(piece: Piece, index: usize, color: Color) -> u64 {
+        unsafe {
+            *ZOBRIST_MATERIAL
+                .get_unchecked(color.to_index())
+                .get_unchecked(piece.to_index())
+                .get_unchecked(index)
+        }
+    }
+
     #[inline]
     pub fn castles(castle_rights: CastleRights, color: Color) -> u64 {
         unsafe {